@@ -0,0 +1,249 @@
+use crate::models::UsageEvent;
+use crate::money::Money;
+use crate::notifications::{Category, Notification};
+use crate::settings::AnomalySettings;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AnomalyKind {
+    /// Spend in one window far above the usual rate.
+    Burst,
+    /// A single event above the cost ceiling.
+    ExpensiveEvent,
+}
+
+impl AnomalyKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AnomalyKind::Burst => "burst",
+            AnomalyKind::ExpensiveEvent => "expensiveEvent",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "burst" => Some(AnomalyKind::Burst),
+            "expensiveEvent" => Some(AnomalyKind::ExpensiveEvent),
+            _ => None,
+        }
+    }
+}
+
+/// Unusual spending found in the event stream.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    /// Start of the burst window, or when the expensive event happened.
+    pub at: DateTime<Utc>,
+    /// The model with the most spend in the window, or the event's model.
+    pub model: String,
+    pub spend_dollars: Money,
+    /// Usual spend per window for a burst, or the floor when that is higher;
+    /// the ceiling for an expensive event.
+    pub threshold_dollars: Money,
+    pub requests: i32,
+}
+
+impl Anomaly {
+    /// Anomalies are recorded once: a burst window that keeps growing, or
+    /// the same event seen on the next refresh, is the same anomaly.
+    /// Expensive events in the same millisecond are told apart by model.
+    pub fn same_as(&self, other: &Anomaly) -> bool {
+        self.key() == other.key()
+    }
+
+    /// Identity for `same_as` and notification de-duplication.
+    fn key(&self) -> String {
+        match self.kind {
+            AnomalyKind::Burst => format!("burst:{}", self.at.timestamp_millis()),
+            AnomalyKind::ExpensiveEvent => {
+                format!("expensiveEvent:{}:{}", self.at.timestamp_millis(), self.model)
+            }
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.kind {
+            AnomalyKind::Burst => "Cursor spending spike",
+            AnomalyKind::ExpensiveEvent => "Expensive Cursor request",
+        }
+    }
+
+    pub fn notification(&self, window_minutes: u32) -> Notification {
+        Notification {
+            category: Category::Spike,
+            key: self.key(),
+            title: self.title().to_string(),
+            body: self.describe(window_minutes),
+        }
+    }
+
+    /// e.g. "$12.40 in 10 min on claude-4-opus (baseline $0.35)".
+    pub fn describe(&self, window_minutes: u32) -> String {
+        match self.kind {
+            AnomalyKind::Burst => format!(
+                "{} in {} min on {} (baseline {})",
+                self.spend_dollars, window_minutes, self.model, self.threshold_dollars
+            ),
+            AnomalyKind::ExpensiveEvent => format!(
+                "One {} request cost {} (ceiling {})",
+                self.model, self.spend_dollars, self.threshold_dollars
+            ),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Window {
+    spend: Money,
+    requests: i32,
+    by_model: HashMap<String, Money>,
+}
+
+/// Find bursts and expensive events in `events`. Windows are aligned to
+/// multiples of `window_minutes` since the epoch, so the same burst is found
+/// at the same time on every refresh.
+pub fn detect(events: &[UsageEvent], settings: &AnomalySettings) -> Vec<Anomaly> {
+    if !settings.enabled {
+        return Vec::new();
+    }
+    let window = Duration::minutes(settings.window_minutes.max(1) as i64);
+    let window_ms = window.num_milliseconds();
+    let mut anomalies = Vec::new();
+
+    let ceiling = settings.event_ceiling_dollars.and_then(Money::from_dollars);
+    let mut windows: BTreeMap<i64, Window> = BTreeMap::new();
+    for event in events {
+        let cost = event.cost();
+        let model = event.model.as_deref().unwrap_or("unknown");
+        if let Some(ceiling) = ceiling.filter(|c| cost > *c) {
+            anomalies.push(Anomaly {
+                kind: AnomalyKind::ExpensiveEvent,
+                at: event.time(),
+                model: model.to_string(),
+                spend_dollars: cost,
+                threshold_dollars: ceiling,
+                requests: 1,
+            });
+        }
+
+        let w = windows.entry(event.time().timestamp_millis().div_euclid(window_ms)).or_default();
+        w.spend += cost;
+        w.requests += 1;
+        *w.by_model.entry(model.to_string()).or_default() += cost;
+    }
+
+    let min_burst = Money::from_dollars(settings.min_burst_dollars).unwrap_or(Money::ZERO);
+    let floor = Money::from_dollars(settings.baseline_floor_dollars).unwrap_or(Money::ZERO);
+    let baseline_windows = (Duration::hours(settings.baseline_hours.max(1) as i64).num_milliseconds() / window_ms).max(1);
+    for (&index, w) in windows.iter().filter(|(_, w)| w.spend >= min_burst) {
+        // Average over every window in the baseline, quiet ones included
+        let baseline: Money = windows.range(index - baseline_windows..index).map(|(_, w)| w.spend).sum();
+        let usual = baseline.scale(1.0 / baseline_windows as f64).max(floor);
+        if w.spend <= usual.scale(settings.burst_factor) {
+            continue;
+        }
+        let (model, _) = w
+            .by_model
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .expect("a window has at least one event");
+        anomalies.push(Anomaly {
+            kind: AnomalyKind::Burst,
+            at: DateTime::from_timestamp_millis(index * window_ms).unwrap_or(DateTime::UNIX_EPOCH),
+            model: model.clone(),
+            spend_dollars: w.spend,
+            threshold_dollars: usual,
+            requests: w.requests,
+        });
+    }
+
+    anomalies.sort_by_key(|a| a.at);
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(model: &str, cents: f64, at: DateTime<Utc>) -> UsageEvent {
+        UsageEvent::test(model, at).cents(cents)
+    }
+
+    fn start() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2026-10-15T00:00:00Z").unwrap().with_timezone(&Utc)
+    }
+
+    /// A steady 20 cents every 10 minutes for a day.
+    fn steady_day() -> Vec<UsageEvent> {
+        (0..144).map(|i| event("gpt-5", 20.0, start() + Duration::minutes(10 * i + 1))).collect()
+    }
+
+    #[test]
+    fn flags_a_burst_well_above_the_baseline() {
+        let mut events = steady_day();
+        let burst = start() + Duration::hours(24);
+        for i in 0..30 {
+            events.push(event("claude-4-opus", 40.0, burst + Duration::seconds(10 * i)));
+        }
+        events.push(event("gpt-5", 20.0, burst + Duration::minutes(5)));
+
+        let anomalies = detect(&events, &AnomalySettings::default());
+        assert_eq!(anomalies.len(), 1);
+        let a = &anomalies[0];
+        assert_eq!(a.kind, AnomalyKind::Burst);
+        assert_eq!(a.at, burst);
+        assert_eq!(a.model, "claude-4-opus");
+        assert_eq!(a.requests, 31);
+        assert_eq!(a.spend_dollars.to_string(), "$12.20");
+        assert_eq!(a.threshold_dollars.to_string(), "$1.00");
+        assert_eq!(a.describe(10), "$12.20 in 10 min on claude-4-opus (baseline $1.00)");
+    }
+
+    #[test]
+    fn small_windows_are_not_bursts_even_after_a_quiet_day() {
+        let events = vec![event("gpt-5", 150.0, start())];
+        assert!(detect(&events, &AnomalySettings::default()).is_empty());
+
+        // Above the minimum, but within the floor times the burst factor
+        let session: Vec<_> = (0..10).map(|i| event("gpt-5", 45.0, start() + Duration::seconds(30 * i))).collect();
+        assert!(detect(&session, &AnomalySettings::default()).is_empty());
+
+        let spike: Vec<_> = (0..10).map(|i| event("gpt-5", 60.0, start() + Duration::seconds(30 * i))).collect();
+        let anomalies = detect(&spike, &AnomalySettings::default());
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].describe(10), "$6.00 in 10 min on gpt-5 (baseline $1.00)");
+    }
+
+    #[test]
+    fn flags_single_events_above_the_ceiling() {
+        let mut events = steady_day();
+        let at = start() + Duration::hours(3) + Duration::minutes(2);
+        events.push(event("o3-pro", 620.0, at));
+
+        let anomalies = detect(&events, &AnomalySettings::default());
+        let kinds: Vec<_> = anomalies.iter().map(|a| a.kind).collect();
+        // Within a steady day, $6.20 in one window is a burst as well
+        assert_eq!(kinds, [AnomalyKind::Burst, AnomalyKind::ExpensiveEvent]);
+        assert_eq!(anomalies[1].at, at);
+        assert_eq!(anomalies[1].describe(10), "One o3-pro request cost $6.20 (ceiling $5.00)");
+
+        // A second expensive event in the same millisecond is its own anomaly
+        events.push(event("claude-4-opus", 540.0, at));
+        let anomalies = detect(&events, &AnomalySettings::default());
+        let expensive: Vec<_> = anomalies.iter().filter(|a| a.kind == AnomalyKind::ExpensiveEvent).collect();
+        assert_eq!(expensive.len(), 2);
+        assert!(!expensive[0].same_as(expensive[1]));
+        assert_ne!(expensive[0].notification(10).key, expensive[1].notification(10).key);
+
+        let disabled = AnomalySettings {
+            enabled: false,
+            ..Default::default()
+        };
+        assert!(detect(&events, &disabled).is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::models::{UsageDisplayData, UsageEvent};
+use crate::schema::SCHEMA_VERSION;
+use crate::APP_IDENTIFIER;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const CACHE_FILE: &str = "last-usage.json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+    #[error("Cannot locate app cache directory")]
+    NoCacheDir,
+    #[error("Cannot access usage cache: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid usage cache: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// The last successful refresh, kept on disk so the app has something to
+/// show after a restart without network.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedUsage {
+    /// `schema::SCHEMA_VERSION` when written.
+    pub version: u32,
+    /// Cursor user id the data belongs to.
+    pub user_id: String,
+    pub fetched_at: DateTime<Utc>,
+    pub data: UsageDisplayData,
+    pub events: Vec<UsageEvent>,
+}
+
+fn cache_path() -> Result<PathBuf, CacheError> {
+    dirs::cache_dir()
+        .map(|d| d.join(APP_IDENTIFIER).join(CACHE_FILE))
+        .ok_or(CacheError::NoCacheDir)
+}
+
+/// Load the cached refresh for `user_id`, or `None` if there is none yet, it
+/// belongs to another account, it was written by a build with a different
+/// event schema or it cannot be parsed.
+pub fn load(user_id: &str) -> Result<Option<CachedUsage>, CacheError> {
+    load_from(&cache_path()?, user_id)
+}
+
+fn load_from(path: &Path, user_id: &str) -> Result<Option<CachedUsage>, CacheError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = std::fs::read(path)?;
+    // The next successful refresh replaces it
+    let cached: CachedUsage = match serde_json::from_slice(&contents) {
+        Ok(cached) => cached,
+        Err(e) => {
+            tracing::warn!(error = %e, "ignoring unreadable usage cache");
+            return Ok(None);
+        }
+    };
+    if cached.version != SCHEMA_VERSION {
+        tracing::info!(version = cached.version, expected = SCHEMA_VERSION, "ignoring usage cache from another version");
+        return Ok(None);
+    }
+    if cached.user_id != user_id {
+        tracing::info!("ignoring usage cache from another account");
+        return Ok(None);
+    }
+    Ok(Some(cached))
+}
+
+/// Save a successful refresh. Written to a temporary file first so a crash
+/// mid-write never leaves a truncated cache behind.
+pub fn save(cached: &CachedUsage) -> Result<(), CacheError> {
+    save_to(&cache_path()?, cached)
+}
+
+fn save_to(path: &Path, cached: &CachedUsage) -> Result<(), CacheError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec(cached)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor_api::aggregate;
+    use chrono::TimeZone;
+
+    /// An empty directory of its own for each test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cursor-status-bar-{}-cache-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn cached(user_id: &str) -> CachedUsage {
+        let now = Utc.with_ymd_and_hms(2026, 3, 15, 12, 0, 0).unwrap();
+        let events = vec![
+            UsageEvent::test("claude-4-sonnet", now - chrono::Duration::hours(1)).tokens(1000, 500, 0, 0).cents(150.0),
+            UsageEvent::test("gpt-5", now - chrono::Duration::days(2)).tokens(100, 50, 0, 0).cents(20.0),
+        ];
+        let start = Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap();
+        CachedUsage {
+            version: SCHEMA_VERSION,
+            user_id: user_id.to_string(),
+            fetched_at: now,
+            data: aggregate(start, &events, now, &Default::default(), &Default::default()),
+            events,
+        }
+    }
+
+    #[test]
+    fn saves_through_a_temporary_file_and_loads_back() {
+        let dir = temp_dir("round-trip");
+        let path = dir.join(CACHE_FILE);
+        save_to(&path, &cached("user_a")).unwrap();
+        // Saving again replaces the file
+        save_to(&path, &cached("user_a")).unwrap();
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        let loaded = load_from(&path, "user_a").unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(files, [CACHE_FILE]);
+        let loaded = loaded.expect("cache for user_a");
+        let expected = cached("user_a");
+        assert_eq!(loaded.fetched_at, expected.fetched_at);
+        assert_eq!(loaded.data.total_spend_dollars, expected.data.total_spend_dollars);
+        assert_eq!(loaded.data.line_items.len(), 2);
+        assert_eq!(loaded.events.len(), 2);
+        assert_eq!(loaded.events[0].cost(), expected.events[0].cost());
+    }
+
+    #[test]
+    fn ignores_a_cache_it_cannot_use() {
+        let dir = temp_dir("ignored");
+        let path = dir.join(CACHE_FILE);
+        let missing = load_from(&path, "user_a").unwrap();
+
+        save_to(&path, &cached("user_a")).unwrap();
+        let other_account = load_from(&path, "user_b").unwrap();
+
+        let mut json: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        json["version"] = (SCHEMA_VERSION + 1).into();
+        std::fs::write(&path, json.to_string()).unwrap();
+        let other_version = load_from(&path, "user_a").unwrap();
+
+        json["version"] = SCHEMA_VERSION.into();
+        json.as_object_mut().unwrap().remove("userId");
+        std::fs::write(&path, json.to_string()).unwrap();
+        let without_user = load_from(&path, "user_a").unwrap();
+
+        std::fs::write(&path, "{\"version\": 1, \"userId\": \"user_a\", \"fetc").unwrap();
+        let truncated = load_from(&path, "user_a").unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(missing.is_none());
+        assert!(other_account.is_none());
+        assert!(other_version.is_none());
+        assert!(without_user.is_none());
+        assert!(truncated.is_none());
+    }
+}
@@ -0,0 +1,248 @@
+use crate::event_store::{EventSource, EventStore, StoreError};
+use crate::models::{TokenUsage, UsageEvent};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+use std::io::Read;
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("Cannot read CSV: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("CSV is missing the '{0}' column")]
+    MissingColumn(&'static str),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+/// Outcome of importing one CSV file.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub rows: usize,
+    pub imported: usize,
+    pub duplicates: usize,
+    pub errors: Vec<RowError>,
+}
+
+/// A row that could not be parsed. `line` is 1-based and counts the header.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowError {
+    pub line: u64,
+    pub message: String,
+}
+
+/// Column positions in a dashboard export. Header names are matched
+/// case-insensitively; older exports use slightly different names.
+struct Columns {
+    date: usize,
+    model: Option<usize>,
+    kind: Option<usize>,
+    input_with_cache_write: Option<usize>,
+    input_without_cache_write: Option<usize>,
+    cache_read: Option<usize>,
+    output: Option<usize>,
+    cost: Option<usize>,
+}
+
+impl Columns {
+    fn from_headers(headers: &csv::StringRecord) -> Result<Columns, ImportError> {
+        let find = |names: &[&str]| {
+            headers
+                .iter()
+                .position(|h| names.iter().any(|n| h.trim().eq_ignore_ascii_case(n)))
+        };
+
+        Ok(Columns {
+            date: find(&["Date", "Timestamp"]).ok_or(ImportError::MissingColumn("Date"))?,
+            model: find(&["Model"]),
+            kind: find(&["Kind"]),
+            input_with_cache_write: find(&["Input (w/ Cache Write)"]),
+            input_without_cache_write: find(&["Input (w/o Cache Write)", "Input Tokens"]),
+            cache_read: find(&["Cache Read", "Cache Read Tokens"]),
+            output: find(&["Output", "Output Tokens"]),
+            cost: find(&["Cost", "Cost ($)"]),
+        })
+    }
+}
+
+/// Import a Cursor dashboard CSV export into the event store, as events of
+/// `account`. Rows already in the store (e.g. fetched from the API) are
+/// counted as duplicates; unparseable rows are reported and skipped.
+pub fn import_csv(path: &Path, store: &mut EventStore, account: &str) -> Result<ImportReport, ImportError> {
+    import_from(csv::ReaderBuilder::new().flexible(true).from_path(path)?, store, account)
+}
+
+fn import_from<R: Read>(
+    mut reader: csv::Reader<R>,
+    store: &mut EventStore,
+    account: &str,
+) -> Result<ImportReport, ImportError> {
+    let columns = Columns::from_headers(reader.headers()?)?;
+
+    let mut report = ImportReport::default();
+    let mut events = Vec::new();
+
+    for record in reader.records() {
+        report.rows += 1;
+        let record = match record {
+            Ok(r) => r,
+            Err(e) => {
+                let line = e.position().map(|p| p.line()).unwrap_or(0);
+                report.errors.push(RowError { line, message: e.to_string() });
+                continue;
+            }
+        };
+        let line = record.position().map(|p| p.line()).unwrap_or(0);
+
+        match parse_row(&record, &columns) {
+            Ok(event) => events.push(event),
+            Err(message) => report.errors.push(RowError { line, message }),
+        }
+    }
+
+    report.imported = store.insert_events(account, &events, EventSource::CsvImport)?;
+    report.duplicates = events.len() - report.imported;
+    Ok(report)
+}
+
+fn parse_row(record: &csv::StringRecord, columns: &Columns) -> Result<UsageEvent, String> {
+    let field = |i: Option<usize>| i.and_then(|i| record.get(i)).map(str::trim).unwrap_or("");
+
+    let timestamp = parse_date(field(Some(columns.date)))?;
+    let tokens = |i: Option<usize>, name: &str| parse_count(field(i)).map_err(|_| format!("invalid {} '{}'", name, field(i)));
+
+    let input = tokens(columns.input_without_cache_write, "input tokens")?;
+    let input_with_write = tokens(columns.input_with_cache_write, "input tokens")?;
+    let (cents, chargeable) = parse_cost(field(columns.cost))?;
+    let model = field(columns.model);
+    let kind = field(columns.kind);
+
+    Ok(UsageEvent {
+        timestamp: timestamp.timestamp_millis().to_string(),
+        model: Some(if model.is_empty() { "unknown" } else { model }.to_string()),
+        kind: (!kind.is_empty()).then(|| kind.to_string()),
+        usage_based_costs: None,
+        is_token_based_call: None,
+        is_chargeable: chargeable,
+        token_usage: Some(TokenUsage {
+            input_tokens: Some(input),
+            output_tokens: Some(tokens(columns.output, "output tokens")?),
+            cache_write_tokens: Some((input_with_write - input).max(0)),
+            cache_read_tokens: Some(tokens(columns.cache_read, "cache read tokens")?),
+            total_cents: cents,
+            estimated_cents: None,
+        }),
+    })
+}
+
+/// Dashboard dates are ISO 8601 in UTC; a bare date-time is taken as UTC.
+fn parse_date(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(dt.and_utc());
+        }
+    }
+    Err(format!("invalid date '{}'", value))
+}
+
+/// Token counts may use thousands separators; blank means zero.
+fn parse_count(value: &str) -> Result<i64, std::num::ParseIntError> {
+    if value.is_empty() || value == "-" {
+        return Ok(0);
+    }
+    value.replace(',', "").parse()
+}
+
+/// Parse the Cost column into cents. "Included" rows are free and not
+/// chargeable; a blank cost is left unknown.
+fn parse_cost(value: &str) -> Result<(Option<f64>, Option<bool>), String> {
+    if value.is_empty() || value == "-" {
+        return Ok((None, None));
+    }
+    if value.eq_ignore_ascii_case("included") || value.eq_ignore_ascii_case("free") {
+        return Ok((Some(0.0), Some(false)));
+    }
+
+    let dollars: f64 = value
+        .trim_start_matches('$')
+        .replace(',', "")
+        .parse()
+        .map_err(|_| format!("invalid cost '{}'", value))?;
+    Ok((Some(dollars * 100.0), Some(dollars > 0.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::Money;
+
+    const CSV: &str = "\
+Date,Kind,Model,Input (w/ Cache Write),Input (w/o Cache Write),Cache Read,Output,Total Tokens,Cost
+2026-03-02T10:15:00.000Z,Usage-based,claude-4-sonnet,\"1,500\",1000,200,300,2000,$0.04
+2026-03-02T10:15:00.000Z,Usage-based,claude-4-sonnet,\"1,500\",1000,200,300,2000,$0.04
+2026-03-02 11:00:00,Included in Pro,gpt-5,10,10,0,5,15,Included
+2026-03-03T09:00:00+02:00,Usage-based,,-,-,-,-,0,-
+yesterday,Usage-based,gpt-5,1,1,0,1,2,$0.01
+2026-03-04T09:00:00Z,Usage-based,gpt-5,lots,1,0,1,2,$0.01
+";
+
+    fn import(store: &mut EventStore, account: &str) -> ImportReport {
+        import_from(csv::Reader::from_reader(CSV.as_bytes()), store, account).unwrap()
+    }
+
+    #[test]
+    fn parses_dates_counts_and_costs() {
+        let utc = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        assert_eq!(parse_date("2026-03-02T10:15:00.000Z"), Ok(utc("2026-03-02T10:15:00Z")));
+        assert_eq!(parse_date("2026-03-03T09:00:00+02:00"), Ok(utc("2026-03-03T07:00:00Z")));
+        assert_eq!(parse_date("2026-03-02 11:00:00"), Ok(utc("2026-03-02T11:00:00Z")));
+        assert_eq!(parse_date("2026-03-02T11:00:00.5"), Ok(utc("2026-03-02T11:00:00.5Z")));
+        assert!(parse_date("03/02/2026").is_err());
+
+        assert_eq!(parse_count("1,234,567"), Ok(1_234_567));
+        assert_eq!(parse_count(""), Ok(0));
+        assert_eq!(parse_count("-"), Ok(0));
+        assert!(parse_count("1.5k").is_err());
+
+        assert_eq!(parse_cost("$1,204.50"), Ok((Some(120_450.0), Some(true))));
+        assert_eq!(parse_cost("0"), Ok((Some(0.0), Some(false))));
+        assert_eq!(parse_cost("Included"), Ok((Some(0.0), Some(false))));
+        assert_eq!(parse_cost("-"), Ok((None, None)));
+        assert!(parse_cost("n/a").is_err());
+    }
+
+    #[test]
+    fn imports_rows_and_reports_bad_ones() {
+        let mut store = EventStore::open_in_memory().unwrap();
+        let report = import(&mut store, "user_a");
+        assert_eq!(report.rows, 6);
+        // Two identical rows are two requests
+        assert_eq!(report.imported, 4);
+        assert_eq!(report.duplicates, 0);
+        let lines: Vec<u64> = report.errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, [6, 7]);
+        assert!(report.errors[1].message.contains("lots"));
+
+        let from = DateTime::parse_from_rfc3339("2026-03-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let events = store.events_between("user_a", from, from + chrono::Duration::days(7)).unwrap();
+        let usage = events[0].token_usage.as_ref().unwrap();
+        // Cache writes are the difference between the two input columns
+        assert_eq!(usage.cache_write_tokens, Some(500));
+        assert_eq!(usage.input_tokens, Some(1000));
+        assert_eq!(events[0].cost(), Money::from_cents(4.0).unwrap());
+        assert_eq!(events[2].is_chargeable, Some(false));
+        assert_eq!(events[3].model.as_deref(), Some("unknown"));
+        assert_eq!(events[3].token_usage.as_ref().unwrap().total_cents, None);
+
+        // Importing again adds nothing; another account keeps its own copy
+        let again = import(&mut store, "user_a");
+        assert_eq!((again.imported, again.duplicates), (0, 4));
+        assert_eq!(import(&mut store, "user_b").imported, 4);
+        assert!(store.events_between("user_c", from, from + chrono::Duration::days(7)).unwrap().is_empty());
+    }
+}
@@ -1,82 +1,252 @@
+use crate::model_registry::ModelRegistry;
 use crate::models::*;
-use chrono::{Datelike, DateTime, Duration, Local, NaiveDate, Utc};
-use reqwest::Client;
+use crate::money::Money;
+use crate::reporting::ReportingClock;
+use crate::schema::{self, EventsPage, SchemaDrift};
+use crate::secret::{self, Secret};
+use chrono::{Datelike, DateTime, Duration, Local, Months, NaiveDate, Utc};
+use reqwest::{Client, StatusCode};
 use std::collections::HashMap;
 
+/// Non-success HTTP response from the Cursor API.
+#[derive(Debug, thiserror::Error)]
+#[error("HTTP {status}: {body}")]
+pub struct HttpStatusError {
+    pub status: StatusCode,
+    pub body: String,
+}
+
+/// Classify a refresh error so the UI can tell "no network" apart from "logged out".
+pub fn classify_error(error: &(dyn std::error::Error + 'static)) -> ErrorKind {
+    if let Some(e) = error.downcast_ref::<HttpStatusError>() {
+        return match e.status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ErrorKind::Auth,
+            _ => ErrorKind::Api,
+        };
+    }
+    if let Some(e) = error.downcast_ref::<reqwest::Error>() {
+        if e.is_connect() || e.is_timeout() || e.is_request() {
+            return ErrorKind::Offline;
+        }
+    }
+    ErrorKind::Api
+}
+
+/// Origin of the real API; overridden in tests to point at a fixture server.
+const DEFAULT_BASE_URL: &str = "https://cursor.com";
+
+/// Events requested per page of `get-filtered-usage-events`.
+const PAGE_SIZE: usize = 1000;
+/// Upper bound on pages per fetch, in case the reported total never adds up.
+const MAX_PAGES: u32 = 50;
+/// How long events from before the billing period and the spending limit
+/// are reused before they are fetched again.
+const EARLIER_USAGE_TTL_MINUTES: i64 = 60;
+
+/// Everything one refresh needs from the API.
+#[derive(Debug)]
+pub struct RecentEvents {
+    pub billing_start: DateTime<Utc>,
+    pub events: Vec<UsageEvent>,
+    pub drift: SchemaDrift,
+    /// Per-model request counters from the legacy endpoint.
+    pub legacy_usage: Vec<(String, LegacyModelUsage)>,
+    /// Usage-based spending limit from the dashboard settings.
+    pub spend_limit: Option<Money>,
+    /// What the next refresh can reuse; `None` when the spending limit
+    /// could not be fetched, so the next refresh tries again.
+    pub earlier: Option<EarlierUsage>,
+}
+
+impl RecentEvents {
+    /// What a refresh shows: `aggregate` at `now`, plus the request quotas
+    /// and spending limit fetched alongside the events. Custom periods are
+    /// left to `periods::summarize`.
+    pub fn display_data(&self, now: DateTime<Utc>, clock: &ReportingClock, models: &ModelRegistry) -> UsageDisplayData {
+        let mut data = aggregate(self.billing_start, &self.events, now, clock, models);
+        data.quotas = request_quotas(&self.legacy_usage, self.billing_start, now);
+        data.spend_limit_dollars = self.spend_limit;
+        data
+    }
+}
+
+/// Events from before the billing period and the spending limit, which
+/// rarely change, kept between refreshes so most of them only fetch the
+/// current billing period.
+#[derive(Debug, Clone)]
+pub struct EarlierUsage {
+    pub billing_start: DateTime<Utc>,
+    pub fetched_at: DateTime<Utc>,
+    /// Events from before `billing_start`.
+    pub events: Vec<UsageEvent>,
+    pub spend_limit: Option<Money>,
+}
+
+impl EarlierUsage {
+    /// Whether a refresh at `now` in the billing period starting at
+    /// `billing_start` can reuse this.
+    fn reusable(&self, billing_start: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        self.billing_start == billing_start && now - self.fetched_at < Duration::minutes(EARLIER_USAGE_TTL_MINUTES)
+    }
+}
+
 pub struct CursorApi {
     client: Client,
-    session_token: String,
+    session_token: Secret,
     user_id: String,
+    base_url: String,
 }
 
 impl CursorApi {
-    pub fn new(session_token: String, user_id: String) -> Self {
+    /// `client` is shared across refreshes; see `http::build_client`.
+    pub fn new(client: Client, session_token: Secret, user_id: String) -> Self {
         Self {
-            client: Client::new(),
+            client,
             session_token,
             user_id,
+            base_url: DEFAULT_BASE_URL.to_string(),
         }
     }
 
-    /// Fetch the billing period start date from the legacy endpoint.
-    async fn fetch_billing_period_start(&self) -> Result<DateTime<Utc>, Box<dyn std::error::Error + Send + Sync>> {
-        let url = format!("https://cursor.com/api/usage?user={}", self.user_id);
+    /// Send requests to `base_url` instead of cursor.com.
+    #[cfg(test)]
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Fetch the billing period start date and per-model request counters
+    /// from the legacy endpoint.
+    async fn fetch_legacy_usage(
+        &self,
+    ) -> Result<(DateTime<Utc>, Vec<(String, LegacyModelUsage)>), Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/api/usage?user={}", self.base_url, self.user_id);
 
         let resp = self
             .client
             .get(&url)
-            .header("Cookie", format!("WorkosCursorSessionToken={}", self.session_token))
+            .header("Cookie", format!("WorkosCursorSessionToken={}", self.session_token.expose()))
             .send()
             .await?;
 
         let status = resp.status();
         if !status.is_success() {
             let body = resp.text().await.unwrap_or_default();
-            return Err(format!("HTTP {}: {}", status, body).into());
+            tracing::warn!(%status, endpoint = "usage", "non-success response");
+            return Err(HttpStatusError { status, body }.into());
         }
 
         let legacy: LegacyUsageResponse = resp.json().await?;
+        let models = legacy.models();
 
         if let Some(start_str) = legacy.start_of_month() {
             if let Ok(dt) = DateTime::parse_from_rfc3339(start_str) {
-                return Ok(dt.with_timezone(&Utc));
+                return Ok((dt.with_timezone(&Utc), models));
             }
             // Try parsing as ISO 8601 with fractional seconds
             if let Ok(dt) = chrono::DateTime::parse_from_str(start_str, "%Y-%m-%dT%H:%M:%S%.fZ") {
-                return Ok(dt.with_timezone(&Utc));
+                return Ok((dt.with_timezone(&Utc), models));
             }
         }
 
         // Fallback: start of current calendar month
+        tracing::debug!("no usable startOfMonth, falling back to calendar month");
         let now = Local::now();
         let start = NaiveDate::from_ymd_opt(now.year(), now.month(), 1)
             .unwrap()
             .and_hms_opt(0, 0, 0)
             .unwrap();
-        Ok(DateTime::from_naive_utc_and_offset(start, Utc))
+        Ok((DateTime::from_naive_utc_and_offset(start, Utc), models))
+    }
+
+    /// Fetch the usage-based spending limit set in the dashboard. `None`
+    /// when there is no limit.
+    pub async fn fetch_spend_limit(&self) -> Result<Option<Money>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/api/dashboard/get-hard-limit", self.base_url);
+
+        let resp = self
+            .client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .header("Cookie", format!("WorkosCursorSessionToken={}", self.session_token.expose()))
+            .header("Origin", "https://cursor.com")
+            .header("Referer", "https://cursor.com/dashboard?tab=settings")
+            .body("{}")
+            .send()
+            .await?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            tracing::warn!(%status, endpoint = "get-hard-limit", "non-success response");
+            return Err(HttpStatusError { status, body }.into());
+        }
+
+        let response: HardLimitResponse = resp.json().await?;
+        Ok(response.spend_limit())
+    }
+
+    /// Fetch usage events from the current API, following pages until the
+    /// reported total is reached. Events that fail to parse are skipped and
+    /// counted in the returned `SchemaDrift`.
+    pub async fn fetch_usage_events(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<(Vec<UsageEvent>, SchemaDrift), Box<dyn std::error::Error + Send + Sync>> {
+        let mut events = Vec::new();
+        let mut drift = SchemaDrift::default();
+        let mut seen = 0;
+        for page in 1..=MAX_PAGES {
+            let EventsPage {
+                total,
+                raw_count,
+                events: batch,
+                drift: page_drift,
+            } = self.fetch_events_page(from, to, page).await?;
+            seen += raw_count;
+            events.extend(batch);
+            drift.merge(page_drift);
+
+            let done = match total {
+                Some(total) => seen as i64 >= total,
+                None => raw_count < PAGE_SIZE,
+            };
+            if done || raw_count == 0 {
+                break;
+            }
+        }
+
+        if drift.skipped_events > 0 {
+            tracing::warn!(skipped = drift.skipped_events, unknown_fields = ?drift.unknown_fields, "usage events did not match the expected format");
+        } else if !drift.unknown_fields.is_empty() {
+            tracing::debug!(unknown_fields = ?drift.unknown_fields, "usage events have unknown fields");
+        }
+        tracing::debug!(count = events.len(), %from, %to, "fetched usage events");
+        Ok((events, drift))
     }
 
-    /// Fetch usage events from the current API.
-    async fn fetch_usage_events(
+    async fn fetch_events_page(
         &self,
         from: DateTime<Utc>,
         to: DateTime<Utc>,
-    ) -> Result<Vec<UsageEvent>, Box<dyn std::error::Error + Send + Sync>> {
-        let url = "https://cursor.com/api/dashboard/get-filtered-usage-events";
+        page: u32,
+    ) -> Result<EventsPage, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/api/dashboard/get-filtered-usage-events", self.base_url);
 
         let body = serde_json::json!({
             "teamId": 0,
             "startDate": from.timestamp_millis().to_string(),
             "endDate": to.timestamp_millis().to_string(),
-            "page": 1,
-            "pageSize": 1000
+            "page": page,
+            "pageSize": PAGE_SIZE
         });
 
         let resp = self
             .client
-            .post(url)
+            .post(&url)
             .header("Content-Type", "application/json")
-            .header("Cookie", format!("WorkosCursorSessionToken={}", self.session_token))
+            .header("Cookie", format!("WorkosCursorSessionToken={}", self.session_token.expose()))
             .header("Origin", "https://cursor.com")
             .header("Referer", "https://cursor.com/dashboard?tab=usage")
             .header("Sec-Fetch-Site", "same-origin")
@@ -97,132 +267,779 @@ impl CursorApi {
         let status = resp.status();
         if !status.is_success() {
             let body = resp.text().await.unwrap_or_default();
-            return Err(format!("HTTP {}: {}", status, body).into());
+            tracing::warn!(%status, endpoint = "get-filtered-usage-events", "non-success response");
+            return Err(HttpStatusError { status, body }.into());
         }
 
-        let events_resp: UsageEventsResponse = resp.json().await?;
-        Ok(events_resp.usage_events_display.unwrap_or_default())
+        let response: UsageEventsResponse = resp.json().await?;
+        Ok(schema::parse_events_page(response))
     }
 
-    /// Fetch all data and aggregate into display format.
-    pub async fn fetch_display_data(&self) -> Result<UsageDisplayData, Box<dyn std::error::Error + Send + Sync>> {
-        let billing_start = self.fetch_billing_period_start().await?;
+    /// Fetch the billing period start and every event since the earlier of
+    /// (previous billing start, 60 days ago), so each period can be compared
+    /// with the one before it. The spending limit is fetched too, but a
+    /// failure there only loses the limit, not the refresh.
+    ///
+    /// When `earlier` is from the same billing period and recent enough, its
+    /// events and limit are reused and only the billing period is fetched.
+    pub async fn fetch_recent_events(
+        &self,
+        earlier: Option<EarlierUsage>,
+    ) -> Result<RecentEvents, Box<dyn std::error::Error + Send + Sync>> {
+        let (billing_start, legacy_usage) = self.fetch_legacy_usage().await?;
         let now = Utc::now();
 
-        // Fetch from the earlier of (billing start, 30 days ago)
-        let thirty_days_ago = now - Duration::days(30);
-        let fetch_start = billing_start.min(thirty_days_ago);
+        if let Some(earlier) = earlier.filter(|e| e.reusable(billing_start, now)) {
+            let (fetched, drift) = self.fetch_usage_events(billing_start, now).await?;
+            let mut events = earlier.events.clone();
+            events.extend(fetched.into_iter().filter(|e| e.time() >= billing_start));
+            return Ok(RecentEvents {
+                billing_start,
+                events,
+                drift,
+                legacy_usage,
+                spend_limit: earlier.spend_limit,
+                earlier: Some(earlier),
+            });
+        }
 
-        let events = self.fetch_usage_events(fetch_start, now).await?;
+        let fetch_start = (billing_start - Months::new(1)).min(now - Duration::days(60));
+        let (events, drift) = self.fetch_usage_events(fetch_start, now).await?;
+        let spend_limit = self.fetch_spend_limit().await;
+        if let Err(e) = &spend_limit {
+            tracing::warn!(error = %secret::sanitize_error(&e.to_string()), "cannot fetch spending limit");
+        }
+        let earlier = spend_limit.as_ref().ok().map(|&spend_limit| EarlierUsage {
+            billing_start,
+            fetched_at: now,
+            events: events.iter().filter(|e| e.time() < billing_start).cloned().collect(),
+            spend_limit,
+        });
+        Ok(RecentEvents {
+            billing_start,
+            events,
+            drift,
+            legacy_usage,
+            spend_limit: spend_limit.unwrap_or(None),
+            earlier,
+        })
+    }
+}
 
-        // Time boundaries
-        let start_of_today = Local::now()
-            .date_naive()
-            .and_hms_opt(0, 0, 0)
-            .unwrap()
-            .and_local_timezone(Local)
-            .unwrap()
-            .with_timezone(&Utc);
-        let seven_days_ago = now - Duration::days(7);
-
-        // Aggregate by model (billing period) and by time buckets
-        let mut by_model: HashMap<String, (i32, f64, i64)> = HashMap::new();
-        let mut total_cents: f64 = 0.0;
-        let mut total_tokens: i64 = 0;
-
-        let mut today_cents: f64 = 0.0;
-        let mut today_reqs: i32 = 0;
-        let mut today_tokens: i64 = 0;
-
-        let mut week7_cents: f64 = 0.0;
-        let mut week7_reqs: i32 = 0;
-        let mut week7_tokens: i64 = 0;
-
-        let mut days30_cents: f64 = 0.0;
-        let mut days30_reqs: i32 = 0;
-        let mut days30_tokens: i64 = 0;
-
-        for event in &events {
-            let model = event.model.clone().unwrap_or_else(|| "unknown".to_string());
-            let cents = event.cost_cents();
-            let tokens = event
-                .token_usage
-                .as_ref()
-                .map(|t| t.total_tokens())
-                .unwrap_or(0);
-
-            // Parse timestamp (milliseconds since epoch)
-            let timestamp_ms: f64 = event.timestamp.parse().unwrap_or(0.0);
-            let event_date = DateTime::from_timestamp_millis(timestamp_ms as i64)
-                .unwrap_or(DateTime::UNIX_EPOCH);
-
-            // Billing period totals
-            if event_date >= billing_start {
-                total_cents += cents;
-                total_tokens += tokens;
-
-                let entry = by_model.entry(model).or_insert((0, 0.0, 0));
-                entry.0 += 1;
-                entry.1 += cents;
-                entry.2 += tokens;
+/// Aggregate events into display format: billing period totals by model plus
+/// today / week / last 30 days buckets relative to `now`, with day and week
+/// boundaries from `clock` and line items grouped by `models`. Custom periods,
+/// quotas and the spending limit are left empty; see `periods::summarize`,
+/// which can read further back from the event store, and `request_quotas`.
+pub fn aggregate(
+    billing_start: DateTime<Utc>,
+    events: &[UsageEvent],
+    now: DateTime<Utc>,
+    clock: &ReportingClock,
+    models: &ModelRegistry,
+) -> UsageDisplayData {
+    let thirty_days_ago = now - Duration::days(30);
+
+    // Time boundaries
+    let start_of_today = clock.today_start(now);
+    let week_start = clock.week_start(now);
+
+    // Aggregate billing period totals and time buckets
+    let mut total_spend = Money::ZERO;
+    let mut usage_based_spend = Money::ZERO;
+    let mut total_tokens: i64 = 0;
+
+    let mut today_spend = Money::ZERO;
+    let mut today_reqs: i32 = 0;
+    let mut today_tokens: i64 = 0;
+
+    let mut week7_spend = Money::ZERO;
+    let mut week7_reqs: i32 = 0;
+    let mut week7_tokens: i64 = 0;
+
+    let mut days30_spend = Money::ZERO;
+    let mut days30_reqs: i32 = 0;
+    let mut days30_tokens: i64 = 0;
+
+    for event in events {
+        let cost = event.cost();
+        let tokens = event
+            .token_usage
+            .as_ref()
+            .map(|t| t.total_tokens())
+            .unwrap_or(0);
+
+        let event_date = event.time();
+
+        // Billing period totals
+        if event_date >= billing_start {
+            total_spend += cost;
+            total_tokens += tokens;
+            if event.is_usage_based() {
+                usage_based_spend += event.reported_cost().unwrap_or(Money::ZERO);
+            }
+        }
+
+        // Time bucket aggregation
+        if event_date >= start_of_today {
+            today_spend += cost;
+            today_reqs += 1;
+            today_tokens += tokens;
+        }
+        if event_date >= week_start {
+            week7_spend += cost;
+            week7_reqs += 1;
+            week7_tokens += tokens;
+        }
+        if event_date >= thirty_days_ago {
+            days30_spend += cost;
+            days30_reqs += 1;
+            days30_tokens += tokens;
+        }
+    }
+
+    let line_items = models.line_items(events.iter().filter(|e| e.time() >= billing_start));
+    let kinds = kind_breakdown(events.iter().filter(|e| e.time() >= billing_start), models);
+
+    let compare = |label: &str, spend: Money, start: DateTime<Utc>, previous_start: DateTime<Utc>| {
+        let previous_end = (previous_start + (now - start)).min(start);
+        let previous = summarize_range(label, events, previous_start, previous_end);
+        Some(PeriodComparison::new(label, spend, previous.spend_dollars, previous.requests))
+    };
+
+    let billing_period_event_count: i32 = line_items.iter().map(|i| i.request_count).sum();
+
+    UsageDisplayData {
+        total_requests: billing_period_event_count,
+        total_spend_dollars: total_spend,
+        total_tokens,
+        line_items,
+        billing_period_start: billing_start.to_rfc3339(),
+        today: PeriodSummary {
+            label: "Today".to_string(),
+            requests: today_reqs,
+            spend_dollars: today_spend,
+            tokens: today_tokens,
+            comparison: compare(
+                "yesterday",
+                today_spend,
+                start_of_today,
+                clock.day_start(clock.day_of(now) - Duration::days(1)),
+            ),
+        },
+        last7_days: PeriodSummary {
+            label: clock.week_label().to_string(),
+            requests: week7_reqs,
+            spend_dollars: week7_spend,
+            tokens: week7_tokens,
+            comparison: compare(
+                clock.previous_week_label(),
+                week7_spend,
+                week_start,
+                clock.previous_week_start(now),
+            ),
+        },
+        last30_days: PeriodSummary {
+            label: "Last 30 Days".to_string(),
+            requests: days30_reqs,
+            spend_dollars: days30_spend,
+            tokens: days30_tokens,
+            comparison: compare(
+                "previous 30 days",
+                days30_spend,
+                thirty_days_ago,
+                thirty_days_ago - Duration::days(30),
+            ),
+        },
+        custom_periods: Vec::new(),
+        kinds,
+        quotas: Vec::new(),
+        spend_limit_dollars: None,
+        usage_based_spend_dollars: usage_based_spend,
+        period_comparison: compare(
+            "previous period",
+            total_spend,
+            billing_start,
+            billing_start - Months::new(1),
+        ),
+    }
+}
+
+/// Quotas for the models in the legacy response that have one, as of `now`.
+pub fn request_quotas(
+    legacy_usage: &[(String, LegacyModelUsage)],
+    billing_start: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Vec<RequestQuota> {
+    legacy_usage
+        .iter()
+        .filter_map(|(model, usage)| RequestQuota::new(model, usage, billing_start, now))
+        .collect()
+}
+
+/// Totals for the events in `[start, end)`.
+pub fn summarize_range<'a>(
+    label: &str,
+    events: impl IntoIterator<Item = &'a UsageEvent>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> PeriodSummary {
+    let mut summary = PeriodSummary {
+        label: label.to_string(),
+        requests: 0,
+        spend_dollars: Money::ZERO,
+        tokens: 0,
+        comparison: None,
+    };
+    for event in events {
+        let time = event.time();
+        if time >= start && time < end {
+            summary.requests += 1;
+            summary.spend_dollars += event.cost();
+            summary.tokens += event.token_usage.as_ref().map(|t| t.total_tokens()).unwrap_or(0);
+        }
+    }
+    summary
+}
+
+/// Spend, requests and tokens by request kind, each split by model, most
+/// spend first.
+pub fn kind_breakdown<'a>(events: impl IntoIterator<Item = &'a UsageEvent>, models: &ModelRegistry) -> Vec<KindSummary> {
+    let mut by_kind: HashMap<RequestKind, Vec<&UsageEvent>> = HashMap::new();
+    for event in events {
+        by_kind.entry(event.request_kind()).or_default().push(event);
+    }
+
+    let mut kinds: Vec<KindSummary> = by_kind
+        .into_iter()
+        .map(|(kind, events)| {
+            let models = models.line_items(events);
+            KindSummary {
+                kind,
+                requests: models.iter().map(|m| m.request_count).sum(),
+                spend_dollars: models.iter().map(|m| m.cost_dollars).sum(),
+                tokens: models.iter().map(|m| m.total_tokens).sum(),
+                models,
             }
+        })
+        .collect();
+    kinds.sort_by(|a, b| {
+        b.spend_dollars
+            .cmp(&a.spend_dollars)
+            .then_with(|| a.kind.as_str().cmp(b.kind.as_str()))
+    });
+    kinds
+}
 
-            // Time bucket aggregation
-            if event_date >= start_of_today {
-                today_cents += cents;
-                today_reqs += 1;
-                today_tokens += tokens;
+/// Aggregate events by model into line items, sorted by cost descending.
+pub fn line_items_for<'a>(events: impl IntoIterator<Item = &'a UsageEvent>) -> Vec<LineItem> {
+    line_items_by(events, |model| (model.to_string(), model.to_string()))
+}
+
+/// Aggregate events into line items by `group(model)`, which gives the
+/// group's key and display name. Sorted by cost descending.
+pub fn line_items_by<'a>(
+    events: impl IntoIterator<Item = &'a UsageEvent>,
+    group: impl Fn(&str) -> (String, String),
+) -> Vec<LineItem> {
+    #[derive(Default)]
+    struct Totals {
+        item: LineItem,
+        /// Reported charges that also have a list-price estimate, and that estimate.
+        priced_reported: Money,
+        priced_estimate: Money,
+        /// Spend per reported model in the group.
+        by_model: HashMap<String, Money>,
+    }
+
+    let mut groups: HashMap<String, String> = HashMap::new();
+    let mut by_model: HashMap<String, Totals> = HashMap::new();
+    for event in events {
+        let model = event.model.as_deref().unwrap_or("unknown");
+        let tokens = event.token_usage.as_ref().map(|t| t.total_tokens()).unwrap_or(0);
+
+        let key = match groups.get(model) {
+            Some(key) => key.clone(),
+            None => {
+                let (key, display_name) = group(model);
+                groups.insert(model.to_string(), key.clone());
+                by_model.entry(key.clone()).or_default().item.display_name = display_name;
+                key
             }
-            if event_date >= seven_days_ago {
-                week7_cents += cents;
-                week7_reqs += 1;
-                week7_tokens += tokens;
+        };
+        let totals = by_model.entry(key).or_default();
+        totals.item.request_count += 1;
+        totals.item.cost_dollars += event.cost();
+        *totals.by_model.entry(model.to_string()).or_default() += event.cost();
+        totals.item.total_tokens += tokens;
+        match (event.reported_cost(), event.estimated_cost()) {
+            (Some(reported), Some(estimate)) => {
+                totals.priced_reported += reported;
+                totals.priced_estimate += estimate;
             }
-            if event_date >= thirty_days_ago {
-                days30_cents += cents;
-                days30_reqs += 1;
-                days30_tokens += tokens;
+            (None, Some(estimate)) => {
+                totals.item.estimated_dollars += estimate;
+                totals.item.estimated_requests += 1;
             }
+            _ => {}
         }
+    }
 
-        // Build line items sorted by cost descending
-        let mut line_items: Vec<LineItem> = by_model
-            .into_iter()
-            .map(|(model, (count, cents, tokens))| LineItem {
-                model_name: model,
-                request_count: count,
-                cost_dollars: cents / 100.0,
-                total_tokens: tokens,
-            })
-            .collect();
-        line_items.sort_by(|a, b| b.cost_dollars.partial_cmp(&a.cost_dollars).unwrap());
-
-        let billing_period_event_count: i32 = line_items.iter().map(|i| i.request_count).sum();
-
-        Ok(UsageDisplayData {
-            total_requests: billing_period_event_count,
-            total_spend_dollars: total_cents / 100.0,
-            total_tokens,
-            line_items,
-            billing_period_start: billing_start.to_rfc3339(),
-            today: PeriodSummary {
-                label: "Today".to_string(),
-                requests: today_reqs,
-                spend_dollars: today_cents / 100.0,
-                tokens: today_tokens,
-            },
-            last7_days: PeriodSummary {
-                label: "Last 7 Days".to_string(),
-                requests: week7_reqs,
-                spend_dollars: week7_cents / 100.0,
-                tokens: week7_tokens,
-            },
-            last30_days: PeriodSummary {
-                label: "Last 30 Days".to_string(),
-                requests: days30_reqs,
-                spend_dollars: days30_cents / 100.0,
-                tokens: days30_tokens,
-            },
+    let mut line_items: Vec<LineItem> = by_model
+        .into_iter()
+        .map(|(group, totals)| {
+            let difference = totals
+                .priced_reported
+                .ratio(totals.priced_estimate)
+                .map(|r| r - 1.0)
+                .filter(|d| d.abs() > PRICE_DISCREPANCY);
+            let (model_name, _) = totals
+                .by_model
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+                .expect("a line item has at least one event");
+            LineItem {
+                model_name,
+                group,
+                price_discrepancy: difference,
+                ..totals.item
+            }
         })
+        .collect();
+    // Ties broken by group so the order does not depend on HashMap iteration
+    line_items.sort_by(|a, b| {
+        b.cost_dollars
+            .cmp(&a.cost_dollars)
+            .then_with(|| a.group.cmp(&b.group))
+    });
+    line_items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, FixtureServer, FIXTURE_USER_ID};
+    use crate::pricing::PricingTable;
+    use chrono::TimeZone;
+
+    fn api(server: &FixtureServer) -> CursorApi {
+        CursorApi::new(
+            Client::new(),
+            Secret::new("fixture-session-token".to_string()),
+            FIXTURE_USER_ID.to_string(),
+        )
+        .with_base_url(server.base_url())
+    }
+
+    fn utc(y: i32, m: u32, d: u32, h: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn empty_account_has_zero_totals() {
+        let server = FixtureServer::replay("empty").await;
+        let RecentEvents {
+            billing_start, events, ..
+        } = api(&server).fetch_recent_events(None).await.unwrap();
+
+        assert_eq!(billing_start, utc(2026, 3, 1, 0));
+        assert!(events.is_empty());
+
+        let data = aggregate(billing_start, &events, utc(2026, 3, 15, 12), &ReportingClock::default(), &ModelRegistry::default());
+        assert_eq!(data.total_requests, 0);
+        assert_eq!(data.total_spend_dollars, Money::ZERO);
+        assert!(data.line_items.is_empty());
+        assert_eq!(data.last30_days.requests, 0);
+    }
+
+    #[tokio::test]
+    async fn reads_request_quotas_from_the_legacy_endpoint() {
+        let server = FixtureServer::replay("multi_page").await;
+        let RecentEvents {
+            billing_start,
+            legacy_usage,
+            ..
+        } = api(&server).fetch_recent_events(None).await.unwrap();
+        assert_eq!(legacy_usage.len(), 1);
+        assert_eq!(legacy_usage[0].1.max_request_usage, Some(500));
+
+        // 2 requests in the first 10 days: the other 498 would take 2490 more
+        let quotas = request_quotas(&legacy_usage, billing_start, utc(2026, 3, 11, 0));
+        assert_eq!(quotas[0].describe(), "2 / 500 premium requests used");
+        assert_eq!(quotas[0].exhausted_at, Some(utc(2026, 3, 11, 0) + Duration::days(2490)));
+        assert_eq!(quotas[0].outlook(Some(utc(2026, 4, 1, 0)), &ReportingClock::default()), "Lasts the billing period at the current rate");
+
+        let busy = LegacyModelUsage {
+            num_requests: 400,
+            max_request_usage: Some(500),
+            ..Default::default()
+        };
+        let quota = RequestQuota::new("gpt-4", &busy, billing_start, utc(2026, 3, 11, 0)).unwrap();
+        assert_eq!(quota.exhausted_at, Some(utc(2026, 3, 13, 12)));
+        let spent = LegacyModelUsage {
+            num_requests: 500,
+            ..busy
+        };
+        let quota = RequestQuota::new("gpt-4", &spent, billing_start, utc(2026, 3, 11, 0)).unwrap();
+        assert!(quota.is_exhausted());
+        assert_eq!(quota.outlook(None, &ReportingClock::default()), "Quota used up; usage-based pricing applies");
+        assert!(RequestQuota::new("gpt-3.5-turbo", &LegacyModelUsage::default(), billing_start, utc(2026, 3, 11, 0)).is_none());
+    }
+
+    #[tokio::test]
+    async fn reads_the_spend_limit_and_tolerates_its_absence() {
+        let server = FixtureServer::replay("multi_page").await;
+        let RecentEvents {
+            billing_start,
+            events,
+            spend_limit,
+            ..
+        } = api(&server).fetch_recent_events(None).await.unwrap();
+        assert_eq!(spend_limit, Money::from_dollars(20.0));
+
+        // Included usage counts towards spend but not towards the limit
+        let mut events = events;
+        events[1].kind = Some("USAGE_EVENT_KIND_INCLUDED_IN_PRO".to_string());
+        let mut data = aggregate(billing_start, &events, utc(2026, 3, 15, 12), &ReportingClock::default(), &ModelRegistry::default());
+        data.spend_limit_dollars = spend_limit;
+        assert_eq!(data.total_spend_dollars, Money::from_cents(16.5).unwrap());
+        assert_eq!(data.usage_based_spend_dollars, Money::from_cents(12.5).unwrap());
+        assert_eq!(
+            data.spend_limit_headroom(),
+            Some(Money::from_dollars(20.0).unwrap().saturating_sub(Money::from_cents(12.5).unwrap()))
+        );
+
+        // No fixture for the endpoint: the refresh still succeeds, and the
+        // next one tries again
+        let server = FixtureServer::replay("empty").await;
+        let RecentEvents { spend_limit, earlier, .. } = api(&server).fetch_recent_events(None).await.unwrap();
+        assert_eq!(spend_limit, None);
+        assert!(earlier.is_none());
+
+        let disabled = HardLimitResponse {
+            hard_limit: Some(20.0),
+            no_usage_based_allowed: true,
+        };
+        assert_eq!(disabled.spend_limit(), None);
+    }
+
+    #[tokio::test]
+    async fn reuses_earlier_usage_for_an_hour() {
+        let server = FixtureServer::replay("multi_page").await;
+        let first = api(&server).fetch_recent_events(None).await.unwrap();
+        let earlier = first.earlier.unwrap();
+        // The page-2 event is from before the billing period
+        assert_eq!(earlier.events.len(), 1);
+        assert_eq!(earlier.spend_limit, Money::from_dollars(20.0));
+
+        // Replayed pages ignore the dates asked for, so the page-2 event
+        // comes back again but is only counted once
+        let second = api(&server).fetch_recent_events(Some(earlier.clone())).await.unwrap();
+        assert_eq!(second.events.len(), first.events.len());
+        assert_eq!(second.spend_limit, earlier.spend_limit);
+
+        let expired = EarlierUsage {
+            fetched_at: earlier.fetched_at - Duration::hours(2),
+            spend_limit: None,
+            ..earlier
+        };
+        let third = api(&server).fetch_recent_events(Some(expired)).await.unwrap();
+        assert_eq!(third.spend_limit, Money::from_dollars(20.0));
+        assert_eq!(third.events.len(), first.events.len());
+    }
+
+    #[tokio::test]
+    async fn follows_pages_until_total_is_reached() {
+        let server = FixtureServer::replay("multi_page").await;
+        let RecentEvents {
+            billing_start, events, ..
+        } = api(&server).fetch_recent_events(None).await.unwrap();
+        assert_eq!(events.len(), 3);
+
+        let data = aggregate(billing_start, &events, utc(2026, 3, 15, 12), &ReportingClock::default(), &ModelRegistry::default());
+        // The page-2 event is from before the billing period
+        assert_eq!(data.total_requests, 2);
+        assert_eq!(data.total_spend_dollars, Money::from_cents(16.5).unwrap());
+        assert_eq!(data.total_tokens, 2100);
+        assert_eq!(data.last7_days.requests, 2);
+        assert_eq!(data.last30_days.requests, 3);
+        assert_eq!(data.last30_days.spend_dollars, Money::from_cents(24.0).unwrap());
+
+        let models: Vec<_> = data.line_items.iter().map(|i| i.model_name.as_str()).collect();
+        assert_eq!(models, ["claude-4-sonnet", "gpt-5"]);
+        assert_eq!(data.line_items[0].request_count, 1);
+    }
+
+    #[tokio::test]
+    async fn refresh_totals_end_to_end() {
+        let server = FixtureServer::replay("multi_page").await;
+        let mut recent = api(&server).fetch_recent_events(None).await.unwrap();
+        PricingTable::default().apply(&mut recent.events);
+
+        let clock = ReportingClock::new(Some("UTC"), Default::default(), chrono::Weekday::Mon, 0).unwrap();
+        let data = recent.display_data(utc(2026, 3, 15, 12), &clock, &ModelRegistry::default());
+
+        assert_eq!(data.billing_period_start_utc(), Some(utc(2026, 3, 1, 0)));
+        assert_eq!(data.total_requests, 2);
+        assert_eq!(data.total_spend_dollars, Money::from_cents(16.5).unwrap());
+        assert_eq!(data.usage_based_spend_dollars, Money::from_cents(16.5).unwrap());
+        assert_eq!(data.total_tokens, 2100);
+        // Events on Mar 10 and 14, and one before the billing period on Feb 20
+        assert_eq!((data.today.requests, data.today.spend_dollars), (0, Money::ZERO));
+        assert_eq!(data.last7_days.requests, 2);
+        assert_eq!(data.last30_days.spend_dollars, Money::from_cents(24.0).unwrap());
+
+        assert_eq!((data.quotas[0].used, data.quotas[0].limit), (2, 500));
+        assert_eq!(data.spend_limit_dollars, Money::from_dollars(20.0));
+        assert_eq!(
+            data.spend_limit_headroom(),
+            Some(Money::from_dollars(20.0).unwrap().saturating_sub(Money::from_cents(16.5).unwrap()))
+        );
+        let costs: Vec<_> = data.line_items.iter().map(|i| (i.model_name.as_str(), i.cost_dollars)).collect();
+        assert_eq!(
+            costs,
+            [
+                ("claude-4-sonnet", Money::from_cents(12.5).unwrap()),
+                ("gpt-5", Money::from_cents(4.0).unwrap())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_fields_fall_back_to_defaults() {
+        let server = FixtureServer::replay("missing_fields").await;
+        let RecentEvents {
+            billing_start, events, ..
+        } = api(&server).fetch_recent_events(None).await.unwrap();
+
+        // No startOfMonth: the calendar month is used instead
+        assert_eq!(billing_start.day(), 1);
+        assert_eq!(events.len(), 2);
+
+        let items = line_items_for(&events);
+        let unknown = items.iter().find(|i| i.model_name == "unknown").unwrap();
+        assert_eq!(unknown.request_count, 1);
+        assert_eq!(unknown.cost_dollars, Money::ZERO);
+        let gpt = items.iter().find(|i| i.model_name == "gpt-5").unwrap();
+        assert_eq!(gpt.total_tokens, 40);
+    }
+
+    #[tokio::test]
+    async fn unauthorized_is_an_auth_error() {
+        let server = FixtureServer::replay("unauthorized").await;
+        let err = api(&server).fetch_recent_events(None).await.unwrap_err();
+
+        let status = err.downcast_ref::<HttpStatusError>().unwrap();
+        assert_eq!(status.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(classify_error(err.as_ref()), ErrorKind::Auth);
+    }
+
+    #[tokio::test]
+    async fn server_error_is_an_api_error() {
+        let server = FixtureServer::replay("server_error").await;
+        let err = api(&server).fetch_recent_events(None).await.unwrap_err();
+
+        let status = err.downcast_ref::<HttpStatusError>().unwrap();
+        assert_eq!(status.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(status.body.contains("Internal Server Error"));
+        assert_eq!(classify_error(err.as_ref()), ErrorKind::Api);
+    }
+
+    #[tokio::test]
+    async fn unreachable_server_is_offline() {
+        // Grab a free port and release it, so nothing is listening there
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let base_url = format!("http://{}", addr);
+        let api = CursorApi::new(Client::new(), Secret::new(String::new()), FIXTURE_USER_ID.to_string())
+            .with_base_url(&base_url);
+        let err = api.fetch_recent_events(None).await.unwrap_err();
+        assert_eq!(classify_error(err.as_ref()), ErrorKind::Offline);
+    }
+
+    #[tokio::test]
+    async fn recording_scrubs_tokens_and_user_id() {
+        let upstream = FixtureServer::replay("multi_page").await;
+        let recorder = FixtureServer::record(upstream.base_url(), "user_real_123").await;
+        let api = CursorApi::new(
+            Client::new(),
+            Secret::new("user_real_123%3A%3Asecret".to_string()),
+            "user_real_123".to_string(),
+        )
+        .with_base_url(recorder.base_url());
+        api.fetch_recent_events(None).await.unwrap();
+
+        let recorded = recorder.exchanges();
+        let paths: Vec<_> = recorded.iter().map(|e| (e.path.as_str(), e.page)).collect();
+        assert_eq!(
+            paths,
+            [
+                ("/api/usage", None),
+                ("/api/dashboard/get-filtered-usage-events", Some(1)),
+                ("/api/dashboard/get-filtered-usage-events", Some(2)),
+                ("/api/dashboard/get-hard-limit", None),
+            ]
+        );
+        let text = serde_json::to_string(&recorded).unwrap();
+        assert!(!text.contains("user_real_123"));
+        assert!(!text.contains("secret"));
+        assert_eq!(recorded[1].body, fixtures::load("multi_page")[1].body);
+    }
+
+    fn event(model: usize, micro_cents: i64, at: DateTime<Utc>) -> UsageEvent {
+        UsageEvent::test(&format!("model-{}", model), at)
+            .tokens(10, 0, 0, 0)
+            .cents(micro_cents as f64 / 1_000_000.0)
+    }
+
+    #[test]
+    fn periods_compare_against_the_same_stretch_of_the_previous_window() {
+        let clock = ReportingClock::new(Some("UTC"), Default::default(), chrono::Weekday::Mon, 0).unwrap();
+        let billing_start = utc(2026, 3, 1, 0);
+        let now = utc(2026, 3, 15, 12);
+        let cents = |c: i64| c * 1_000_000;
+        let events = vec![
+            // Yesterday morning counts; yesterday evening is after the same hour.
+            event(0, cents(100), utc(2026, 3, 14, 9)),
+            event(0, cents(500), utc(2026, 3, 14, 20)),
+            event(0, cents(135), utc(2026, 3, 15, 10)),
+            // Previous billing period, before and after the same point in it.
+            event(0, cents(200), utc(2026, 2, 10, 0)),
+            event(0, cents(900), utc(2026, 2, 20, 0)),
+        ];
+
+        let data = aggregate(billing_start, &events, now, &clock, &ModelRegistry::default());
+        let today = data.today.comparison.unwrap();
+        assert_eq!(today.previous_spend_dollars, Money::from_dollars(1.0).unwrap());
+        assert_eq!(today.previous_requests, 1);
+        assert_eq!(today.trend, Trend::Up);
+        assert_eq!(today.describe(), "+35% vs yesterday");
+
+        let period = data.period_comparison.unwrap();
+        assert_eq!(period.previous_spend_dollars, Money::from_dollars(2.0).unwrap());
+        assert_eq!(period.signed_delta(), "+$5.35");
+
+        let quiet = aggregate(billing_start, &[], now, &clock, &ModelRegistry::default());
+        let today = quiet.today.comparison.unwrap();
+        assert_eq!(today.trend, Trend::Flat);
+        assert_eq!(today.delta_fraction, None);
+        assert_eq!(today.describe(), "+$0.00 vs yesterday");
+    }
+
+    #[test]
+    fn breaks_spend_down_by_kind_and_model() {
+        assert_eq!(RequestKind::parse("USAGE_EVENT_KIND_BACKGROUND_AGENT"), RequestKind::BackgroundAgent);
+        assert_eq!(RequestKind::parse("Agent"), RequestKind::Agent);
+        assert_eq!(
+            RequestKind::parse("USAGE_EVENT_KIND_USAGE_BASED"),
+            RequestKind::Other("usage-based".to_string())
+        );
+
+        let at = utc(2026, 3, 10, 0);
+        let with_kind = |model, cents: i64, kind: Option<&str>| UsageEvent {
+            kind: kind.map(str::to_string),
+            ..event(model, cents * 1_000_000, at)
+        };
+        let events = vec![
+            with_kind(0, 300, Some("USAGE_EVENT_KIND_BACKGROUND_AGENT")),
+            with_kind(1, 200, Some("background-agent")),
+            with_kind(0, 50, Some("chat")),
+            with_kind(0, 10, None),
+        ];
+
+        let kinds = kind_breakdown(&events, &ModelRegistry::default());
+        let summary: Vec<_> = kinds.iter().map(|k| (k.kind.as_str(), k.requests, k.spend_dollars.to_string())).collect();
+        assert_eq!(
+            summary,
+            [("background-agent", 2, "$5.00".to_string()), ("chat", 1, "$0.50".to_string()), ("unknown", 1, "$0.10".to_string())]
+        );
+        let models: Vec<_> = kinds[0].models.iter().map(|m| m.model_name.as_str()).collect();
+        assert_eq!(models, ["model-0", "model-1"]);
+        assert_eq!(serde_json::to_value(&kinds[0].kind).unwrap(), "background-agent");
+    }
+
+    #[test]
+    fn grouped_line_items_keep_the_top_model() {
+        let at = utc(2026, 3, 10, 0);
+        let cents = |c: i64| c * 1_000_000;
+        let events = vec![
+            event(0, cents(100), at),
+            event(2, cents(300), at),
+            event(1, cents(200), at),
+            event(3, cents(200), at),
+        ];
+        let parity = |model: &str| {
+            let group = if model.ends_with(['0', '2']) { "even" } else { "odd" };
+            (group.to_string(), group.to_uppercase())
+        };
+
+        // Both groups spent $4.00, so they are ordered by group, and the odd
+        // models tie so the first by name is kept
+        let items = line_items_by(&events, parity);
+        let summary: Vec<_> = items
+            .iter()
+            .map(|i| (i.group.as_str(), i.model_name.as_str(), i.display_name.as_str(), i.request_count))
+            .collect();
+        assert_eq!(summary, [("even", "model-2", "EVEN", 2), ("odd", "model-1", "ODD", 2)]);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn period_total_is_exactly_the_sum_of_line_items(
+            costs in proptest::collection::vec((0usize..5, 0i64..100_000_000_000, 0i64..14 * 24), 0..200)
+        ) {
+            let billing_start = utc(2026, 3, 1, 0);
+            let now = utc(2026, 3, 15, 0);
+            let events: Vec<_> = costs
+                .iter()
+                .map(|&(model, micro, hours)| event(model, micro, billing_start + Duration::hours(hours)))
+                .collect();
+
+            let data = aggregate(billing_start, &events, now, &ReportingClock::default(), &ModelRegistry::default());
+            let items_total: Money = data.line_items.iter().map(|i| i.cost_dollars).sum();
+            let expected: Money = events.iter().map(UsageEvent::cost).sum();
+            proptest::prop_assert_eq!(data.total_spend_dollars, items_total);
+            proptest::prop_assert_eq!(data.total_spend_dollars, expected);
+            proptest::prop_assert_eq!(
+                data.total_requests,
+                data.line_items.iter().map(|i| i.request_count).sum::<i32>()
+            );
+        }
+
+        #[test]
+        fn invalid_costs_count_as_zero_and_never_panic(
+            cents in proptest::collection::vec(proptest::prop_oneof![
+                proptest::num::f64::ANY,
+                proptest::strategy::Just(f64::NAN),
+                -1e6f64..1e6,
+            ], 0..50)
+        ) {
+            let events: Vec<_> = cents
+                .iter()
+                .map(|&c| {
+                    let mut e = event(0, 0, utc(2026, 3, 2, 0));
+                    e.token_usage.as_mut().unwrap().total_cents = Some(c);
+                    e
+                })
+                .collect();
+            let items = line_items_for(&events);
+            for item in &items {
+                proptest::prop_assert!(item.cost_dollars >= Money::ZERO);
+            }
+        }
+    }
+
+    /// Record a fixture from the real API using the local Cursor login:
+    /// `cargo test record_live_fixture -- --ignored`, then rename
+    /// `tests/fixtures/recorded.json` and review it before committing.
+    #[tokio::test]
+    #[ignore]
+    async fn record_live_fixture() {
+        let info = crate::token_extractor::extract_token().expect("Cursor login");
+        let recorder = FixtureServer::record(DEFAULT_BASE_URL, &info.user_id).await;
+        let api = CursorApi::new(Client::new(), info.session_token, info.user_id).with_base_url(recorder.base_url());
+        let result = api.fetch_recent_events(None).await;
+
+        fixtures::save("recorded", &recorder.exchanges());
+        result.unwrap();
     }
 }
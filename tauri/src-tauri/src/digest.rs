@@ -0,0 +1,337 @@
+use crate::cursor_api::summarize_range;
+use crate::model_registry::ModelRegistry;
+use crate::models::{LineItem, PeriodComparison, PeriodSummary, UsageDisplayData, UsageEvent};
+use crate::money::Money;
+use crate::periods::PeriodRange;
+use crate::reporting::ReportingClock;
+use crate::settings::DigestSettings;
+use crate::template::tokens;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Models listed in a digest.
+const TOP_MODELS: usize = 5;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DigestFrequency {
+    /// Covers yesterday.
+    #[default]
+    Daily,
+    /// Covers the previous calendar week.
+    Weekly,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DigestFormat {
+    #[default]
+    Text,
+    Markdown,
+    Html,
+}
+
+impl DigestFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            DigestFormat::Text => "txt",
+            DigestFormat::Markdown => "md",
+            DigestFormat::Html => "html",
+        }
+    }
+}
+
+/// Input, output and cache token totals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenMix {
+    pub input: i64,
+    pub output: i64,
+    pub cache_write: i64,
+    pub cache_read: i64,
+}
+
+impl TokenMix {
+    fn total(&self) -> i64 {
+        self.input + self.output + self.cache_write + self.cache_read
+    }
+
+    /// "input 40%, output 10%, cache read 45%, cache write 5%".
+    fn describe(&self) -> String {
+        let total = self.total();
+        if total == 0 {
+            return "no tokens".to_string();
+        }
+        [
+            ("input", self.input),
+            ("output", self.output),
+            ("cache read", self.cache_read),
+            ("cache write", self.cache_write),
+        ]
+        .iter()
+        .map(|(name, n)| format!("{} {:.0}%", name, *n as f64 * 100.0 / total as f64))
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
+/// Figures for one digest, before rendering.
+#[derive(Debug, Clone)]
+pub struct Digest {
+    pub title: String,
+    /// First reporting day covered.
+    pub first_day: NaiveDate,
+    pub period: PeriodSummary,
+    pub top_models: Vec<LineItem>,
+    pub token_mix: TokenMix,
+    pub billing_spend: Money,
+    pub forecast: Option<Money>,
+    pub budget: Option<Money>,
+}
+
+/// Build the digest covering the day or week before `now`.
+pub fn build(
+    settings: &DigestSettings,
+    events: &[UsageEvent],
+    data: &UsageDisplayData,
+    now: DateTime<Utc>,
+    clock: &ReportingClock,
+    models: &ModelRegistry,
+    budget: Option<Money>,
+) -> Digest {
+    let billing_start = data.billing_period_start_utc().unwrap_or(now);
+    let (range, label, previous_label) = match settings.frequency {
+        DigestFrequency::Daily => (PeriodRange::Yesterday, "Yesterday", "the day before"),
+        DigestFrequency::Weekly => (PeriodRange::LastWeek, "Last week", "the week before"),
+    };
+    let (start, end) = range.bounds(now, clock, billing_start);
+    let in_range: Vec<&UsageEvent> = events
+        .iter()
+        .filter(|e| (start..end).contains(&e.time()))
+        .collect();
+
+    let mut period = summarize_range(label, events, start, end);
+    let previous = summarize_range(previous_label, events, start - (end - start), start);
+    period.comparison = Some(PeriodComparison::new(
+        previous_label,
+        period.spend_dollars,
+        previous.spend_dollars,
+        previous.requests,
+    ));
+
+    let mut token_mix = TokenMix::default();
+    for usage in in_range.iter().filter_map(|e| e.token_usage.as_ref()) {
+        token_mix.input += usage.input_tokens.unwrap_or(0);
+        token_mix.output += usage.output_tokens.unwrap_or(0);
+        token_mix.cache_write += usage.cache_write_tokens.unwrap_or(0);
+        token_mix.cache_read += usage.cache_read_tokens.unwrap_or(0);
+    }
+
+    let mut top_models = models.line_items(in_range.iter().copied());
+    top_models.truncate(TOP_MODELS);
+
+    let first_day = clock.day_of(start);
+    let title = match settings.frequency {
+        DigestFrequency::Daily => format!("Cursor daily digest — {}", first_day.format("%a %b %-d")),
+        DigestFrequency::Weekly => format!("Cursor weekly digest — week of {}", first_day.format("%b %-d")),
+    };
+
+    Digest {
+        title,
+        first_day,
+        period,
+        top_models,
+        token_mix,
+        billing_spend: data.total_spend_dollars,
+        forecast: data.forecast_period_spend(now),
+        budget,
+    }
+}
+
+impl Digest {
+    /// File name for a file delivery, e.g. `cursor-daily-2026-10-14.md`.
+    pub fn file_name(&self, key: &str, format: DigestFormat) -> String {
+        let key: String = key
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '-' })
+            .collect();
+        format!("cursor-{}-{}.{}", key, self.first_day, format.extension())
+    }
+
+    /// One line for notifications: "Yesterday: $12.40 (85 req), +35% vs the day before".
+    pub fn headline(&self) -> String {
+        let p = &self.period;
+        let mut line = format!("{}: {} ({} req)", p.label, p.spend_dollars, p.requests);
+        if let Some(c) = &p.comparison {
+            let _ = write!(line, ", {}", c.describe());
+        }
+        line
+    }
+
+    fn billing_line(&self) -> String {
+        let mut line = format!("Billing period: {} so far", self.billing_spend);
+        match (self.forecast, self.budget) {
+            (Some(f), Some(b)) => {
+                let _ = write!(line, ", forecast {} of {} budget", f, b);
+            }
+            (Some(f), None) => {
+                let _ = write!(line, ", forecast {}", f);
+            }
+            (None, Some(b)) => {
+                let _ = write!(line, " of {} budget", b);
+            }
+            (None, None) => {}
+        }
+        line
+    }
+
+    fn model_line(item: &LineItem) -> String {
+        format!(
+            "{} — {} ({} req, {} tokens)",
+            item.display_name,
+            item.cost_dollars,
+            item.request_count,
+            tokens(item.total_tokens)
+        )
+    }
+
+    /// The whole digest, title included.
+    pub fn render(&self, format: DigestFormat) -> String {
+        let heading = match format {
+            DigestFormat::Text => format!("{}\n\n", self.title),
+            DigestFormat::Markdown => format!("## {}\n\n", self.title),
+            DigestFormat::Html => format!("<h2>{}</h2>\n", escape_html(&self.title)),
+        };
+        heading + &self.render_body(format)
+    }
+
+    /// The digest without its title, for messages that carry the title separately.
+    pub fn render_body(&self, format: DigestFormat) -> String {
+        let mut lines = vec![
+            self.headline(),
+            format!("Tokens: {} ({})", tokens(self.period.tokens), self.token_mix.describe()),
+            self.billing_line(),
+        ];
+        let models: Vec<String> = self.top_models.iter().map(Self::model_line).collect();
+
+        let mut out = String::new();
+        match format {
+            DigestFormat::Text => {
+                for line in &lines {
+                    let _ = writeln!(out, "{}", line);
+                }
+                if !models.is_empty() {
+                    let _ = writeln!(out, "\nTop models:");
+                    for line in &models {
+                        let _ = writeln!(out, "  {}", line);
+                    }
+                }
+            }
+            DigestFormat::Markdown => {
+                lines[0] = format!("**{}**", lines[0]);
+                for line in &lines {
+                    let _ = writeln!(out, "- {}", line);
+                }
+                if !models.is_empty() {
+                    let _ = writeln!(out, "\n### Top models\n");
+                    for line in &models {
+                        let _ = writeln!(out, "- {}", line);
+                    }
+                }
+            }
+            DigestFormat::Html => {
+                let item = |line: &str| format!("<li>{}</li>", escape_html(line));
+                let _ = writeln!(out, "<ul>");
+                let _ = writeln!(out, "<li><strong>{}</strong></li>", escape_html(&lines[0]));
+                for line in &lines[1..] {
+                    let _ = writeln!(out, "{}", item(line));
+                }
+                let _ = writeln!(out, "</ul>");
+                if !models.is_empty() {
+                    let _ = writeln!(out, "<h3>Top models</h3>\n<ul>");
+                    for line in &models {
+                        let _ = writeln!(out, "{}", item(line));
+                    }
+                    let _ = writeln!(out, "</ul>");
+                }
+            }
+        }
+        out
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor_api::aggregate;
+    use chrono::Weekday;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn clock() -> ReportingClock {
+        ReportingClock::new(Some("Europe/Berlin"), Default::default(), Weekday::Mon, 0).unwrap()
+    }
+
+    fn settings(frequency: DigestFrequency) -> DigestSettings {
+        serde_json::from_value(serde_json::json!({
+            "frequency": frequency,
+            "time": "09:00",
+            "deliver": [{"type": "file", "directory": "/tmp"}]
+        }))
+        .unwrap()
+    }
+
+    fn event(model: &str, cents: f64, at: &str) -> UsageEvent {
+        UsageEvent::test(model, utc(at)).tokens(400, 100, 0, 500).cents(cents)
+    }
+
+    #[test]
+    fn schedules_in_the_reporting_timezone() {
+        let daily = settings(DigestFrequency::Daily);
+        let c = clock();
+        // 08:30 in Berlin (UTC+2): today's 09:00 has not come yet
+        let now = utc("2026-10-15T06:30:00Z");
+        assert_eq!(daily.last_due(now, &c), utc("2026-10-14T07:00:00Z"));
+        assert!(!daily.is_due(now, utc("2026-10-14T07:00:30Z"), &c));
+        assert!(daily.is_due(utc("2026-10-15T07:00:00Z"), utc("2026-10-14T07:00:30Z"), &c));
+
+        // Thursday; the weekly digest went out on Monday
+        let weekly = settings(DigestFrequency::Weekly);
+        assert_eq!(weekly.last_due(now, &c), utc("2026-10-12T07:00:00Z"));
+    }
+
+    #[test]
+    fn renders_yesterday_with_top_models_and_token_mix() {
+        let c = clock();
+        let now = utc("2026-10-15T07:00:00Z");
+        let events = vec![
+            event("gpt-5", 100.0, "2026-10-13T12:00:00Z"),
+            event("claude-4-opus", 800.0, "2026-10-14T10:00:00Z"),
+            event("gpt-5", 550.0, "2026-10-14T11:00:00Z"),
+            event("gpt-5", 999.0, "2026-10-15T06:00:00Z"),
+        ];
+        let models = ModelRegistry::default();
+        let data = aggregate(utc("2026-10-01T00:00:00Z"), &events, now, &c, &models);
+        let digest = build(&settings(DigestFrequency::Daily), &events, &data, now, &c, &models, Money::from_dollars(500.0));
+
+        assert_eq!(digest.headline(), "Yesterday: $13.50 (2 req), +1250% vs the day before");
+        let text = digest.render(DigestFormat::Text);
+        assert!(text.starts_with("Cursor daily digest — Wed Oct 14\n"));
+        assert!(text.contains("Tokens: 2.0K (input 40%, output 10%, cache read 50%, cache write 0%)"));
+        assert!(text.contains("  Claude 4 Opus — $8.00 (1 req, 1.0K tokens)\n  GPT-5 — $5.50"));
+
+        let html = digest.render(DigestFormat::Html);
+        assert!(html.contains("<h2>Cursor daily digest — Wed Oct 14</h2>"));
+        assert!(digest.render(DigestFormat::Markdown).contains("### Top models"));
+        assert_eq!(digest.file_name("team/weekly", DigestFormat::Html), "cursor-team-weekly-2026-10-14.html");
+    }
+}
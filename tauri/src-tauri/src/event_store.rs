@@ -0,0 +1,443 @@
+use crate::anomaly::{Anomaly, AnomalyKind};
+use crate::models::{TokenUsage, UsageEvent};
+use crate::money::Money;
+use crate::notifications::{Category, NotificationRecord, Outcome};
+use crate::APP_IDENTIFIER;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const STORE_FILE: &str = "usage.sqlite";
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("Cannot locate app data directory")]
+    NoDataDir,
+    #[error("Cannot create {0}: {1}")]
+    CannotCreate(String, String),
+    #[error("Event store error: {0}")]
+    Sql(#[from] rusqlite::Error),
+}
+
+/// Where an event came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventSource {
+    Api,
+    CsvImport,
+}
+
+impl EventSource {
+    fn as_str(self) -> &'static str {
+        match self {
+            EventSource::Api => "api",
+            EventSource::CsvImport => "csv",
+        }
+    }
+}
+
+/// Events and anomalies belong to the Cursor user id they were fetched or
+/// imported for.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS events (
+        account             TEXT NOT NULL,
+        timestamp_ms        INTEGER NOT NULL,
+        model               TEXT NOT NULL,
+        kind                TEXT,
+        usage_based_costs   TEXT,
+        is_token_based_call INTEGER,
+        is_chargeable       INTEGER,
+        input_tokens        INTEGER NOT NULL DEFAULT 0,
+        output_tokens       INTEGER NOT NULL DEFAULT 0,
+        cache_write_tokens  INTEGER NOT NULL DEFAULT 0,
+        cache_read_tokens   INTEGER NOT NULL DEFAULT 0,
+        total_cents         REAL,
+        source              TEXT NOT NULL,
+        occurrence          INTEGER NOT NULL DEFAULT 0,
+        UNIQUE (account, timestamp_ms, model, input_tokens, output_tokens,
+                cache_write_tokens, cache_read_tokens, occurrence)
+    );
+    CREATE INDEX IF NOT EXISTS events_account_timestamp ON events (account, timestamp_ms);
+    CREATE TABLE IF NOT EXISTS anomalies (
+        account         TEXT NOT NULL,
+        kind            TEXT NOT NULL,
+        at_ms           INTEGER NOT NULL,
+        model           TEXT NOT NULL,
+        spend_cents     REAL NOT NULL,
+        threshold_cents REAL NOT NULL,
+        requests        INTEGER NOT NULL,
+        UNIQUE (account, kind, at_ms, model)
+    );
+    CREATE TABLE IF NOT EXISTS digests_sent (
+        name    TEXT PRIMARY KEY,
+        sent_ms INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS notifications (
+        at_ms    INTEGER NOT NULL,
+        category TEXT NOT NULL,
+        key      TEXT NOT NULL,
+        title    TEXT NOT NULL,
+        body     TEXT NOT NULL,
+        outcome  TEXT NOT NULL
+    );";
+
+/// Local SQLite copy of usage events, so history survives beyond the
+/// ~30 days the remote API returns. Everything but sent digests and the
+/// notification history is kept per account, by Cursor user id, so switching accounts never mixes histories.
+///
+/// Events are de-duplicated on (account, timestamp, model, token counts,
+/// occurrence). `kind` and cost are left out of the key because the dashboard
+/// CSV and the API spell the kind differently and round the cost differently
+/// for the same event. `occurrence` numbers events that are otherwise
+/// identical within one insert, so two requests in the same millisecond with
+/// the same token counts are both kept, while fetching or importing them
+/// again adds nothing.
+pub struct EventStore {
+    conn: Connection,
+}
+
+impl EventStore {
+    /// Default store location in the platform data directory.
+    pub fn default_path() -> Result<PathBuf, StoreError> {
+        dirs::data_dir()
+            .map(|d| d.join(APP_IDENTIFIER).join(STORE_FILE))
+            .ok_or(StoreError::NoDataDir)
+    }
+
+    pub fn open_default() -> Result<EventStore, StoreError> {
+        Self::open(&Self::default_path()?)
+    }
+
+    pub fn open(path: &Path) -> Result<EventStore, StoreError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                StoreError::CannotCreate(parent.to_string_lossy().to_string(), e.to_string())
+            })?;
+        }
+
+        Self::init(Connection::open(path)?)
+    }
+
+    /// A store that lives only as long as it is open.
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<EventStore, StoreError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<EventStore, StoreError> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(EventStore { conn })
+    }
+
+    /// Insert events for `account`, skipping ones already stored. Returns how
+    /// many were new.
+    pub fn insert_events(
+        &mut self,
+        account: &str,
+        events: &[UsageEvent],
+        source: EventSource,
+    ) -> Result<usize, StoreError> {
+        let tx = self.conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO events (
+                    account, timestamp_ms, model, kind, usage_based_costs, is_token_based_call,
+                    is_chargeable, input_tokens, output_tokens, cache_write_tokens,
+                    cache_read_tokens, total_cents, source, occurrence
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            )?;
+
+            let mut seen: HashMap<(i64, &str, [i64; 4]), u32> = HashMap::new();
+            for event in events {
+                let usage = event.token_usage.as_ref();
+                let tokens = |f: fn(&TokenUsage) -> Option<i64>| usage.and_then(f).unwrap_or(0);
+                let timestamp_ms = event.time().timestamp_millis();
+                let model = event.model.as_deref().unwrap_or("unknown");
+                let counts = [
+                    tokens(|t| t.input_tokens),
+                    tokens(|t| t.output_tokens),
+                    tokens(|t| t.cache_write_tokens),
+                    tokens(|t| t.cache_read_tokens),
+                ];
+                let occurrence = seen.entry((timestamp_ms, model, counts)).or_insert(0);
+                inserted += stmt.execute(params![
+                    account,
+                    timestamp_ms,
+                    model,
+                    event.kind,
+                    event.usage_based_costs,
+                    event.is_token_based_call,
+                    event.is_chargeable,
+                    counts[0],
+                    counts[1],
+                    counts[2],
+                    counts[3],
+                    usage.and_then(|t| t.total_cents),
+                    source.as_str(),
+                    *occurrence,
+                ])?;
+                *occurrence += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// Stored events for `account` with `from <= time < to`, oldest first.
+    pub fn events_between(
+        &self,
+        account: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<UsageEvent>, StoreError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT timestamp_ms, model, kind, usage_based_costs, is_token_based_call,
+                    is_chargeable, input_tokens, output_tokens, cache_write_tokens,
+                    cache_read_tokens, total_cents
+             FROM events
+             WHERE account = ?1 AND timestamp_ms >= ?2 AND timestamp_ms < ?3
+             ORDER BY timestamp_ms, occurrence",
+        )?;
+
+        let rows = stmt.query_map(params![account, from.timestamp_millis(), to.timestamp_millis()], |row| {
+            Ok(UsageEvent {
+                timestamp: row.get::<_, i64>(0)?.to_string(),
+                model: Some(row.get(1)?),
+                kind: row.get(2)?,
+                usage_based_costs: row.get(3)?,
+                is_token_based_call: row.get(4)?,
+                is_chargeable: row.get(5)?,
+                token_usage: Some(TokenUsage {
+                    input_tokens: Some(row.get(6)?),
+                    output_tokens: Some(row.get(7)?),
+                    cache_write_tokens: Some(row.get(8)?),
+                    cache_read_tokens: Some(row.get(9)?),
+                    total_cents: row.get(10)?,
+                    estimated_cents: None,
+                }),
+            })
+        })?;
+
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Record anomalies for `account`, keeping the first sighting of each.
+    pub fn insert_anomalies(&mut self, account: &str, anomalies: &[Anomaly]) -> Result<usize, StoreError> {
+        let tx = self.conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO anomalies (
+                    account, kind, at_ms, model, spend_cents, threshold_cents, requests
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for a in anomalies {
+                inserted += stmt.execute(params![
+                    account,
+                    a.kind.as_str(),
+                    a.at.timestamp_millis(),
+                    a.model,
+                    a.spend_dollars.as_cents_f64(),
+                    a.threshold_dollars.as_cents_f64(),
+                    a.requests,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// Anomalies recorded for `account` at or after `from`, oldest first.
+    /// Rows of a kind this build does not know are left out.
+    pub fn anomalies_since(&self, account: &str, from: DateTime<Utc>) -> Result<Vec<Anomaly>, StoreError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT kind, at_ms, model, spend_cents, threshold_cents, requests
+             FROM anomalies
+             WHERE account = ?1 AND at_ms >= ?2
+             ORDER BY at_ms",
+        )?;
+
+        let rows = stmt.query_map(params![account, from.timestamp_millis()], |row| {
+            let kind: String = row.get(0)?;
+            let Some(kind) = AnomalyKind::parse(&kind) else {
+                return Ok(None);
+            };
+            let cents = |i| -> rusqlite::Result<Money> {
+                Ok(Money::from_cents(row.get(i)?).unwrap_or(Money::ZERO))
+            };
+            Ok(Some(Anomaly {
+                kind,
+                at: DateTime::from_timestamp_millis(row.get(1)?).unwrap_or(DateTime::UNIX_EPOCH),
+                model: row.get(2)?,
+                spend_dollars: cents(3)?,
+                threshold_dollars: cents(4)?,
+                requests: row.get(5)?,
+            }))
+        })?;
+
+        let rows = rows.collect::<Result<Vec<_>, _>>()?;
+        Ok(rows.into_iter().flatten().collect())
+    }
+
+    /// When each scheduled digest was last sent, by digest key.
+    pub fn digests_sent(&self) -> Result<HashMap<String, DateTime<Utc>>, StoreError> {
+        let mut stmt = self.conn.prepare("SELECT name, sent_ms FROM digests_sent")?;
+        let rows = stmt.query_map([], |row| {
+            let sent = DateTime::from_timestamp_millis(row.get(1)?).unwrap_or(DateTime::UNIX_EPOCH);
+            Ok((row.get(0)?, sent))
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    pub fn set_digest_sent(&mut self, name: &str, at: DateTime<Utc>) -> Result<(), StoreError> {
+        self.conn.execute(
+            "INSERT INTO digests_sent (name, sent_ms) VALUES (?1, ?2)
+             ON CONFLICT (name) DO UPDATE SET sent_ms = excluded.sent_ms",
+            params![name, at.timestamp_millis()],
+        )?;
+        Ok(())
+    }
+
+    /// Add to the notification history, keeping only the newest `keep`.
+    pub fn insert_notifications(&mut self, records: &[NotificationRecord], keep: usize) -> Result<(), StoreError> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO notifications (at_ms, category, key, title, body, outcome)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for r in records {
+                stmt.execute(params![
+                    r.at.timestamp_millis(),
+                    r.category.as_str(),
+                    r.key,
+                    r.title,
+                    r.body,
+                    r.outcome.as_str(),
+                ])?;
+            }
+        }
+        tx.execute(
+            "DELETE FROM notifications WHERE rowid NOT IN (
+                 SELECT rowid FROM notifications ORDER BY at_ms DESC, rowid DESC LIMIT ?1
+             )",
+            params![keep as i64],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The notification history, oldest first. Rows of a category or
+    /// outcome this build does not know are left out.
+    pub fn notifications(&self) -> Result<Vec<NotificationRecord>, StoreError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT at_ms, category, key, title, body, outcome
+             FROM notifications
+             ORDER BY at_ms, rowid",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let category: String = row.get(1)?;
+            let outcome: String = row.get(5)?;
+            let at = DateTime::from_timestamp_millis(row.get(0)?).unwrap_or(DateTime::UNIX_EPOCH);
+            let (key, title, body) = (row.get(2)?, row.get(3)?, row.get(4)?);
+            Ok(Category::parse(&category)
+                .zip(Outcome::parse(&outcome))
+                .map(|(category, outcome)| NotificationRecord {
+                    at,
+                    category,
+                    key,
+                    title,
+                    body,
+                    outcome,
+                }))
+        })?;
+
+        let rows = rows.collect::<Result<Vec<_>, _>>()?;
+        Ok(rows.into_iter().flatten().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn at(minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, 10, minute, 0).unwrap()
+    }
+
+    fn all(store: &EventStore, account: &str) -> Vec<UsageEvent> {
+        store.events_between(account, at(0), at(0) + Duration::days(1)).unwrap()
+    }
+
+    #[test]
+    fn identical_events_are_kept_once_per_occurrence() {
+        let mut store = EventStore::open_in_memory().unwrap();
+        let event = || UsageEvent::test("gpt-5", at(5)).tokens(100, 20, 0, 0).cents(1.0);
+
+        // Two requests in the same millisecond with the same tokens are both kept
+        assert_eq!(store.insert_events("user_a", &[event(), event()], EventSource::Api).unwrap(), 2);
+        assert_eq!(store.insert_events("user_a", &[event(), event()], EventSource::Api).unwrap(), 0);
+        // A third one fetched later is new
+        assert_eq!(store.insert_events("user_a", &[event(), event(), event()], EventSource::Api).unwrap(), 1);
+        // Different token counts are a different event
+        let other = UsageEvent::test("gpt-5", at(5)).tokens(100, 21, 0, 0);
+        assert_eq!(store.insert_events("user_a", &[other], EventSource::Api).unwrap(), 1);
+        assert_eq!(all(&store, "user_a").len(), 4);
+    }
+
+    #[test]
+    fn reimporting_the_same_csv_adds_nothing() {
+        let mut store = EventStore::open_in_memory().unwrap();
+        let fetched = [
+            UsageEvent::test("claude-4-sonnet", at(1)).tokens(1000, 300, 500, 200).cents(4.123),
+            UsageEvent::test("gpt-5", at(2)).tokens(10, 5, 0, 0).cents(0.0),
+        ];
+        assert_eq!(store.insert_events("user_a", &fetched, EventSource::Api).unwrap(), 2);
+
+        // The export spells the kind differently and rounds the cost
+        let exported = || {
+            let mut sonnet = UsageEvent::test("claude-4-sonnet", at(1)).tokens(1000, 300, 500, 200).cents(4.0);
+            sonnet.kind = Some("Usage-based".to_string());
+            let mut gpt = UsageEvent::test("gpt-5", at(2)).tokens(10, 5, 0, 0).cents(0.0);
+            gpt.kind = Some("Included in Pro".to_string());
+            [sonnet, gpt, UsageEvent::test("gpt-5", at(3)).tokens(1, 1, 0, 0)]
+        };
+        assert_eq!(store.insert_events("user_a", &exported(), EventSource::CsvImport).unwrap(), 1);
+        assert_eq!(store.insert_events("user_a", &exported(), EventSource::CsvImport).unwrap(), 0);
+
+        let events = all(&store, "user_a");
+        assert_eq!(events.len(), 3);
+        // The first copy stored wins
+        assert_eq!(events[0].kind, None);
+        assert_eq!(events[0].token_usage.as_ref().unwrap().total_cents, Some(4.123));
+    }
+
+    #[test]
+    fn accounts_keep_their_own_history() {
+        let mut store = EventStore::open_in_memory().unwrap();
+        let event = UsageEvent::test("gpt-5", at(5)).tokens(100, 20, 0, 0);
+        store.insert_events("user_a", std::slice::from_ref(&event), EventSource::Api).unwrap();
+        assert_eq!(store.insert_events("user_b", &[event], EventSource::Api).unwrap(), 1);
+        store
+            .insert_events("user_b", &[UsageEvent::test("gpt-5", at(6))], EventSource::Api)
+            .unwrap();
+        assert_eq!(all(&store, "user_a").len(), 1);
+        assert_eq!(all(&store, "user_b").len(), 2);
+        assert!(all(&store, "user_c").is_empty());
+
+        let anomaly = Anomaly {
+            kind: AnomalyKind::ExpensiveEvent,
+            at: at(5),
+            model: "gpt-5".to_string(),
+            spend_dollars: Money::from_dollars(3.0).unwrap(),
+            threshold_dollars: Money::from_dollars(1.0).unwrap(),
+            requests: 1,
+        };
+        store.insert_anomalies("user_a", std::slice::from_ref(&anomaly)).unwrap();
+        assert_eq!(store.insert_anomalies("user_a", std::slice::from_ref(&anomaly)).unwrap(), 0);
+        assert_eq!(store.anomalies_since("user_a", at(0)).unwrap().len(), 1);
+        assert!(store.anomalies_since("user_b", at(0)).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,432 @@
+use crate::cursor_api::{line_items_for, CursorApi};
+use crate::event_store::{EventSource, EventStore, StoreError};
+use crate::models::{LineItem, UsageEvent};
+use crate::reporting::ReportingClock;
+use crate::schema::SchemaDrift;
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use parquet::arrow::ArrowWriter;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("Unknown export format '{0}' (expected csv, jsonl or parquet)")]
+    UnknownFormat(String),
+    #[error("Invalid date '{0}' (expected YYYY-MM-DD)")]
+    InvalidDate(String),
+    #[error("Start date is after end date")]
+    EmptyRange,
+    #[error("API error: {0}")]
+    Api(String),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    #[error("Cannot write export file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Arrow error: {0}")]
+    Arrow(#[from] ArrowError),
+    #[error("Parquet error: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+
+    /// Guess the format from a file extension.
+    pub fn from_path(path: &Path) -> Option<ExportFormat> {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(|e| e.parse().ok())
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" | "ndjson" => Ok(ExportFormat::Jsonl),
+            "parquet" => Ok(ExportFormat::Parquet),
+            _ => Err(ExportError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+/// What to export: one row per raw event, or one row per model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportDataset {
+    Events,
+    LineItems,
+}
+
+/// Normalised row for a single usage event.
+#[derive(Debug, Serialize)]
+pub struct EventRow {
+    pub timestamp: String,
+    pub model: String,
+    pub kind: Option<String>,
+    pub chargeable: Option<bool>,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_write_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub total_tokens: i64,
+    pub cents: f64,
+    /// `cents` is a list-price estimate; Cursor reported no cost.
+    pub estimated: bool,
+}
+
+impl EventRow {
+    /// `clock` decides the offset the timestamp is written with.
+    pub fn new(event: &UsageEvent, clock: &ReportingClock) -> Self {
+        let usage = event.token_usage.as_ref();
+        let tokens = |f: fn(&crate::models::TokenUsage) -> Option<i64>| usage.and_then(f).unwrap_or(0);
+        EventRow {
+            timestamp: clock.format_timestamp(event.time()),
+            model: event.model.clone().unwrap_or_else(|| "unknown".to_string()),
+            kind: event.kind.clone(),
+            chargeable: event.is_chargeable,
+            input_tokens: tokens(|t| t.input_tokens),
+            output_tokens: tokens(|t| t.output_tokens),
+            cache_write_tokens: tokens(|t| t.cache_write_tokens),
+            cache_read_tokens: tokens(|t| t.cache_read_tokens),
+            total_tokens: usage.map(|t| t.total_tokens()).unwrap_or(0),
+            cents: event.cost().as_cents_f64(),
+            estimated: event.is_estimated(),
+        }
+    }
+}
+
+/// Normalised row for a per-model aggregate.
+#[derive(Debug, Serialize)]
+pub struct LineItemRow {
+    pub model: String,
+    pub requests: i64,
+    pub total_tokens: i64,
+    pub cents: f64,
+    /// Part of `cents` estimated from list prices.
+    pub estimated_cents: f64,
+}
+
+impl From<&LineItem> for LineItemRow {
+    fn from(item: &LineItem) -> Self {
+        LineItemRow {
+            model: item.model_name.clone(),
+            requests: item.request_count as i64,
+            total_tokens: item.total_tokens,
+            cents: item.cost_dollars.as_cents_f64(),
+            estimated_cents: item.estimated_dollars.as_cents_f64(),
+        }
+    }
+}
+
+/// Rows that can be laid out column-wise for Parquet.
+pub trait Columnar: Sized {
+    fn record_batch(rows: &[Self]) -> Result<RecordBatch, ArrowError>;
+}
+
+impl Columnar for EventRow {
+    fn record_batch(rows: &[Self]) -> Result<RecordBatch, ArrowError> {
+        let schema = Schema::new(vec![
+            Field::new("timestamp", DataType::Utf8, false),
+            Field::new("model", DataType::Utf8, false),
+            Field::new("kind", DataType::Utf8, true),
+            Field::new("chargeable", DataType::Boolean, true),
+            Field::new("input_tokens", DataType::Int64, false),
+            Field::new("output_tokens", DataType::Int64, false),
+            Field::new("cache_write_tokens", DataType::Int64, false),
+            Field::new("cache_read_tokens", DataType::Int64, false),
+            Field::new("total_tokens", DataType::Int64, false),
+            Field::new("cents", DataType::Float64, false),
+            Field::new("estimated", DataType::Boolean, false),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.timestamp.as_str()))),
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.model.as_str()))),
+            Arc::new(StringArray::from_iter(rows.iter().map(|r| r.kind.as_deref()))),
+            Arc::new(BooleanArray::from_iter(rows.iter().map(|r| r.chargeable))),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.input_tokens))),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.output_tokens))),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.cache_write_tokens))),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.cache_read_tokens))),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.total_tokens))),
+            Arc::new(Float64Array::from_iter_values(rows.iter().map(|r| r.cents))),
+            Arc::new(BooleanArray::from_iter(rows.iter().map(|r| Some(r.estimated)))),
+        ];
+        RecordBatch::try_new(Arc::new(schema), columns)
+    }
+}
+
+impl Columnar for LineItemRow {
+    fn record_batch(rows: &[Self]) -> Result<RecordBatch, ArrowError> {
+        let schema = Schema::new(vec![
+            Field::new("model", DataType::Utf8, false),
+            Field::new("requests", DataType::Int64, false),
+            Field::new("total_tokens", DataType::Int64, false),
+            Field::new("cents", DataType::Float64, false),
+            Field::new("estimated_cents", DataType::Float64, false),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.model.as_str()))),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.requests))),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.total_tokens))),
+            Arc::new(Float64Array::from_iter_values(rows.iter().map(|r| r.cents))),
+            Arc::new(Float64Array::from_iter_values(rows.iter().map(|r| r.estimated_cents))),
+        ];
+        RecordBatch::try_new(Arc::new(schema), columns)
+    }
+}
+
+/// Write rows to `path` in the given format.
+pub fn write_rows<T: Serialize + Columnar>(
+    rows: &[T],
+    format: ExportFormat,
+    path: &Path,
+) -> Result<(), ExportError> {
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_path(path)?;
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Jsonl => {
+            let mut writer = BufWriter::new(File::create(path)?);
+            for row in rows {
+                serde_json::to_writer(&mut writer, row)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Parquet => {
+            let batch = T::record_batch(rows)?;
+            let mut writer = ArrowWriter::try_new(File::create(path)?, batch.schema(), None)?;
+            writer.write(&batch)?;
+            writer.close()?;
+        }
+    }
+    Ok(())
+}
+
+/// Parse an inclusive `YYYY-MM-DD` range of reporting days into UTC bounds.
+/// The end bound is the start of the day after `to`, capped at the current time.
+pub fn parse_date_range(
+    from: &str,
+    to: &str,
+    clock: &ReportingClock,
+) -> Result<(DateTime<Utc>, DateTime<Utc>), ExportError> {
+    let parse = |s: &str| {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| ExportError::InvalidDate(s.to_string()))
+    };
+    let start = clock.day_start(parse(from)?);
+    let end = clock.day_start(parse(to)? + Duration::days(1)).min(Utc::now());
+
+    if start >= end {
+        return Err(ExportError::EmptyRange);
+    }
+    Ok((start, end))
+}
+
+/// Fetch events for the range from the API, with any format problems met on the way.
+pub async fn fetch_range(
+    api: &CursorApi,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<(Vec<UsageEvent>, SchemaDrift), ExportError> {
+    api.fetch_usage_events(from, to)
+        .await
+        .map_err(|e| ExportError::Api(e.to_string()))
+}
+
+/// Save freshly fetched events to the local store and return everything it
+/// holds for `account` in the range, so exports include imported history too.
+pub fn with_stored_history(
+    events: Vec<UsageEvent>,
+    store: &mut EventStore,
+    account: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<UsageEvent>, ExportError> {
+    store.insert_events(account, &events, EventSource::Api)?;
+    Ok(store.events_between(account, from, to)?)
+}
+
+/// Write the chosen dataset for `events` to `path`. Returns the number of rows written.
+pub fn write_dataset(
+    events: &[UsageEvent],
+    dataset: ExportDataset,
+    format: ExportFormat,
+    path: &Path,
+    clock: &ReportingClock,
+) -> Result<usize, ExportError> {
+    match dataset {
+        ExportDataset::Events => {
+            let rows: Vec<EventRow> = events.iter().map(|e| EventRow::new(e, clock)).collect();
+            write_rows(&rows, format, path)?;
+            Ok(rows.len())
+        }
+        ExportDataset::LineItems => {
+            let rows: Vec<LineItemRow> = line_items_for(events).iter().map(LineItemRow::from).collect();
+            write_rows(&rows, format, path)?;
+            Ok(rows.len())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Weekday};
+
+    fn clock(timezone: &str, day_start_hour: u32) -> ReportingClock {
+        ReportingClock::new(Some(timezone), Default::default(), Weekday::Mon, day_start_hour).unwrap()
+    }
+
+    fn utc(y: i32, m: u32, d: u32, h: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_inclusive_date_ranges() {
+        let range = parse_date_range("2026-03-01", "2026-03-31", &clock("UTC", 0)).unwrap();
+        assert_eq!(range, (utc(2026, 3, 1, 0), utc(2026, 4, 1, 0)));
+        // Days start at the cutoff hour in the reporting timezone
+        let range = parse_date_range("2026-03-02", "2026-03-02", &clock("Europe/Berlin", 4)).unwrap();
+        assert_eq!(range, (utc(2026, 3, 2, 3), utc(2026, 3, 3, 3)));
+        // A range running into today ends now
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+        let (_, end) = parse_date_range("2026-01-01", &today, &clock("UTC", 0)).unwrap();
+        assert!(end <= Utc::now());
+    }
+
+    #[test]
+    fn rejects_reversed_and_malformed_ranges() {
+        let clock = clock("UTC", 0);
+        assert!(matches!(parse_date_range("2026-03-10", "2026-03-01", &clock), Err(ExportError::EmptyRange)));
+        assert!(matches!(parse_date_range("2099-01-01", "2099-01-31", &clock), Err(ExportError::EmptyRange)));
+        for bad in ["03/01/2026", "2026-02-30", ""] {
+            assert!(
+                matches!(parse_date_range(bad, "2026-03-31", &clock), Err(ExportError::InvalidDate(ref d)) if d == bad),
+                "{}",
+                bad
+            );
+        }
+        assert!(matches!(parse_date_range("2026-03-01", "yesterday", &clock), Err(ExportError::InvalidDate(_))));
+    }
+
+    fn rows() -> Vec<EventRow> {
+        let at = Utc.with_ymd_and_hms(2026, 3, 2, 10, 15, 0).unwrap() + Duration::milliseconds(250);
+        let mut sonnet = UsageEvent::test("claude-4-sonnet", at).tokens(1000, 300, 500, 200).cents(4.25);
+        sonnet.kind = Some("Usage-based".to_string());
+        let mut gpt = UsageEvent::test("gpt-5", at + Duration::minutes(1)).tokens(10, 5, 0, 0).cents(0.0);
+        gpt.is_chargeable = Some(false);
+        let clock = clock("Europe/Berlin", 0);
+        [sonnet, gpt].iter().map(|e| EventRow::new(e, &clock)).collect()
+    }
+
+    fn temp_path(extension: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("cursor-status-bar-{}-export.{}", std::process::id(), extension))
+    }
+
+    #[test]
+    fn writes_csv_rows() {
+        let path = temp_path("csv");
+        write_rows(&rows(), ExportFormat::Csv, &path).unwrap();
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        let headers: Vec<String> = reader.headers().unwrap().iter().map(String::from).collect();
+        let records: Vec<csv::StringRecord> = reader.records().collect::<Result<_, _>>().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            headers,
+            [
+                "timestamp",
+                "model",
+                "kind",
+                "chargeable",
+                "input_tokens",
+                "output_tokens",
+                "cache_write_tokens",
+                "cache_read_tokens",
+                "total_tokens",
+                "cents",
+                "estimated"
+            ]
+        );
+        assert_eq!(
+            records[0].iter().collect::<Vec<_>>(),
+            [
+                "2026-03-02T11:15:00.250+01:00",
+                "claude-4-sonnet",
+                "Usage-based",
+                "true",
+                "1000",
+                "300",
+                "500",
+                "200",
+                "2000",
+                "4.25",
+                "false"
+            ]
+        );
+        assert_eq!(&records[1][2], "");
+        assert_eq!(&records[1][3], "false");
+        assert_eq!(&records[1][9], "0.0");
+    }
+
+    #[test]
+    fn writes_jsonl_rows() {
+        let path = temp_path("jsonl");
+        write_rows(&rows(), ExportFormat::Jsonl, &path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<serde_json::Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            serde_json::json!({
+                "timestamp": "2026-03-02T11:15:00.250+01:00",
+                "model": "claude-4-sonnet",
+                "kind": "Usage-based",
+                "chargeable": true,
+                "input_tokens": 1000,
+                "output_tokens": 300,
+                "cache_write_tokens": 500,
+                "cache_read_tokens": 200,
+                "total_tokens": 2000,
+                "cents": 4.25,
+                "estimated": false
+            })
+        );
+        assert_eq!(lines[1]["timestamp"], "2026-03-02T11:16:00.250+01:00");
+        assert_eq!(lines[1]["kind"], serde_json::Value::Null);
+        assert_eq!(lines[1]["chargeable"], false);
+    }
+}
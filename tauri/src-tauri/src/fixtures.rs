@@ -0,0 +1,185 @@
+use crate::secret;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+
+/// Stands in for the account's real user id in recorded fixtures.
+pub const FIXTURE_USER_ID: &str = "user_fixture";
+
+/// One request and the response served for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exchange {
+    pub method: String,
+    /// Path without the query string, which only carries the user id.
+    pub path: String,
+    /// `page` from the JSON request body, for paginated endpoints.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u64>,
+    pub status: u16,
+    /// JSON bodies are stored as-is, anything else as a string.
+    pub body: serde_json::Value,
+}
+
+impl Exchange {
+    fn matches(&self, method: &Method, path: &str, page: Option<u64>) -> bool {
+        self.method.eq_ignore_ascii_case(method.as_str()) && self.path == path && self.page == page
+    }
+}
+
+pub fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(format!("{}.json", name))
+}
+
+pub fn load(name: &str) -> Vec<Exchange> {
+    let path = fixture_path(name);
+    let text = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e));
+    serde_json::from_str(&text).unwrap_or_else(|e| panic!("invalid fixture {}: {}", path.display(), e))
+}
+
+pub fn save(name: &str, exchanges: &[Exchange]) {
+    let path = fixture_path(name);
+    let text = serde_json::to_string_pretty(exchanges).expect("fixtures serialize");
+    std::fs::write(&path, text + "\n").unwrap_or_else(|e| panic!("cannot write {}: {}", path.display(), e));
+}
+
+enum Mode {
+    Replay,
+    Record {
+        upstream: String,
+        client: reqwest::Client,
+        user_id: String,
+    },
+}
+
+struct Shared {
+    mode: Mode,
+    /// Fixture being served when replaying; responses seen so far when recording.
+    exchanges: Mutex<Vec<Exchange>>,
+}
+
+/// Local HTTP server that `CursorApi::with_base_url` can point at, serving
+/// a fixture from `tests/fixtures` or recording a new one. Stops when dropped.
+pub struct FixtureServer {
+    base_url: String,
+    shared: Arc<Shared>,
+    task: JoinHandle<()>,
+}
+
+impl FixtureServer {
+    /// Serve the exchanges in `tests/fixtures/<name>.json`. Requests with no
+    /// matching exchange get a 501 so a missing fixture fails loudly.
+    pub async fn replay(name: &str) -> Self {
+        Self::start(Mode::Replay, load(name)).await
+    }
+
+    /// Forward requests to `upstream` and keep each response, with tokens
+    /// redacted and `user_id` replaced by `FIXTURE_USER_ID`.
+    pub async fn record(upstream: &str, user_id: &str) -> Self {
+        let mode = Mode::Record {
+            upstream: upstream.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            user_id: user_id.to_string(),
+        };
+        Self::start(mode, Vec::new()).await
+    }
+
+    async fn start(mode: Mode, exchanges: Vec<Exchange>) -> Self {
+        let shared = Arc::new(Shared {
+            mode,
+            exchanges: Mutex::new(exchanges),
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind fixture server");
+        let base_url = format!("http://{}", listener.local_addr().expect("local addr"));
+        let router = axum::Router::new().fallback(handle).with_state(shared.clone());
+        let task = tokio::spawn(async move {
+            let _ = axum::serve(listener, router).await;
+        });
+        Self { base_url, shared, task }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Exchanges recorded so far (or the replayed fixture).
+    pub fn exchanges(&self) -> Vec<Exchange> {
+        self.shared.exchanges.lock().unwrap().clone()
+    }
+}
+
+impl Drop for FixtureServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn handle(State(shared): State<Arc<Shared>>, method: Method, uri: Uri, headers: HeaderMap, body: Bytes) -> Response {
+    let path = uri.path().to_string();
+    let page = serde_json::from_slice::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|v| v.get("page").and_then(|p| p.as_u64()));
+
+    match &shared.mode {
+        Mode::Replay => {
+            let found = shared
+                .exchanges
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|e| e.matches(&method, &path, page))
+                .cloned();
+            match found {
+                Some(exchange) => respond(&exchange),
+                None => (
+                    StatusCode::NOT_IMPLEMENTED,
+                    format!("no fixture for {} {} page {:?}", method, path, page),
+                )
+                    .into_response(),
+            }
+        }
+        Mode::Record {
+            upstream,
+            client,
+            user_id,
+        } => {
+            let url = format!("{}{}", upstream, uri.path_and_query().map(|p| p.as_str()).unwrap_or("/"));
+            let mut forward = headers.clone();
+            forward.remove(header::HOST);
+            forward.remove(header::CONTENT_LENGTH);
+
+            let resp = match client.request(method.clone(), &url).headers(forward).body(body).send().await {
+                Ok(resp) => resp,
+                Err(e) => return (StatusCode::BAD_GATEWAY, secret::sanitize_error(&e.to_string())).into_response(),
+            };
+            let status = resp.status().as_u16();
+            let text = resp.text().await.unwrap_or_default();
+            let scrubbed = secret::redact(&text).replace(user_id.as_str(), FIXTURE_USER_ID);
+
+            let exchange = Exchange {
+                method: method.to_string(),
+                path,
+                page,
+                status,
+                body: serde_json::from_str(&scrubbed).unwrap_or(serde_json::Value::String(scrubbed)),
+            };
+            let response = respond(&exchange);
+            shared.exchanges.lock().unwrap().push(exchange);
+            response
+        }
+    }
+}
+
+fn respond(exchange: &Exchange) -> Response {
+    let status = StatusCode::from_u16(exchange.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    match &exchange.body {
+        serde_json::Value::String(text) => (status, text.clone()).into_response(),
+        json => (status, [(header::CONTENT_TYPE, "application/json")], json.to_string()).into_response(),
+    }
+}
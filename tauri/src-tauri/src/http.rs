@@ -0,0 +1,129 @@
+use crate::settings::HttpSettings;
+use reqwest::{Certificate, Client, NoProxy, Proxy};
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum HttpError {
+    #[error("Cannot read CA certificates from {0}: {1}")]
+    CannotReadCa(String, String),
+    #[error("Invalid CA certificates in {0}: {1}")]
+    InvalidCa(String, reqwest::Error),
+    #[error("No CA certificates in {0}")]
+    NoCertificates(String),
+    #[error("Invalid proxy URL '{0}': {1}")]
+    InvalidProxy(String, reqwest::Error),
+    #[error("Cannot build HTTP client: {0}")]
+    Build(#[from] reqwest::Error),
+}
+
+/// Client with the default timeouts and no proxy, for when the configured
+/// one cannot be built.
+pub fn default_client() -> Client {
+    build_client(&HttpSettings::default()).expect("default HTTP settings are valid")
+}
+
+/// Build the HTTP client shared by every request the app makes.
+///
+/// Without an explicit proxy, reqwest picks up `HTTPS_PROXY` / `ALL_PROXY` /
+/// `NO_PROXY` from the environment; `settings.no_proxy` only applies to an
+/// explicit proxy. An explicit proxy may be `http://`, `https://` or
+/// `socks5://`.
+pub fn build_client(settings: &HttpSettings) -> Result<Client, HttpError> {
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_secs(settings.connect_timeout_secs))
+        .read_timeout(Duration::from_secs(settings.read_timeout_secs))
+        .timeout(Duration::from_secs(settings.request_timeout_secs));
+
+    if let Some(url) = &settings.proxy {
+        let proxy = Proxy::all(url)
+            .map_err(|e| HttpError::InvalidProxy(url.clone(), e))?
+            .no_proxy(
+                settings
+                    .no_proxy
+                    .as_deref()
+                    .and_then(NoProxy::from_string)
+                    .or_else(NoProxy::from_env),
+            );
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(path) = &settings.ca_certificates {
+        let display = path.to_string_lossy().to_string();
+        let pem = std::fs::read(path).map_err(|e| HttpError::CannotReadCa(display.clone(), e.to_string()))?;
+        let certs = Certificate::from_pem_bundle(&pem).map_err(|e| HttpError::InvalidCa(display.clone(), e))?;
+        // Text without any PEM blocks parses as an empty bundle
+        if certs.is_empty() {
+            return Err(HttpError::NoCertificates(display));
+        }
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    let client = builder.build()?;
+    tracing::debug!(
+        proxy = settings.proxy.is_some(),
+        extra_ca = settings.ca_certificates.is_some(),
+        "built HTTP client"
+    );
+    Ok(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    fn with_ca(path: &Path) -> HttpSettings {
+        HttpSettings {
+            ca_certificates: Some(path.to_path_buf()),
+            ..HttpSettings::default()
+        }
+    }
+
+    /// A file in the temp directory holding `contents`, removed by the caller.
+    fn temp_pem(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("cursor-status-bar-{}-{}.pem", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn rejects_an_invalid_proxy_url() {
+        let settings = HttpSettings {
+            proxy: Some("socks5://proxy.internal:99999".to_string()),
+            ..HttpSettings::default()
+        };
+        let err = build_client(&settings).unwrap_err();
+        assert!(matches!(err, HttpError::InvalidProxy(ref url, _) if url == "socks5://proxy.internal:99999"), "{}", err);
+
+        let settings = HttpSettings {
+            proxy: Some("http://proxy.internal:3128".to_string()),
+            no_proxy: Some("localhost,.corp".to_string()),
+            ..HttpSettings::default()
+        };
+        assert!(build_client(&settings).is_ok());
+    }
+
+    #[test]
+    fn rejects_unreadable_and_invalid_ca_files() {
+        let missing = std::env::temp_dir().join("cursor-status-bar-no-such-ca.pem");
+        assert!(matches!(build_client(&with_ca(&missing)), Err(HttpError::CannotReadCa(..))));
+
+        let corrupt = temp_pem("corrupt", "-----BEGIN CERTIFICATE-----\n!!not base64!!\n-----END CERTIFICATE-----\n");
+        let result = build_client(&with_ca(&corrupt));
+        std::fs::remove_file(&corrupt).unwrap();
+        assert!(matches!(result, Err(HttpError::InvalidCa(..))));
+
+        let empty = temp_pem("empty", "not a certificate\n");
+        let result = build_client(&with_ca(&empty));
+        std::fs::remove_file(&empty).unwrap();
+        assert!(matches!(result, Err(HttpError::NoCertificates(_))));
+    }
+
+    #[test]
+    fn trusts_a_ca_bundle() {
+        let ca = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/ca.pem");
+        assert!(build_client(&with_ca(&ca)).is_ok());
+    }
+}
@@ -1,21 +1,404 @@
+mod anomaly;
+mod cache;
+mod csv_import;
 mod cursor_api;
+mod diagnostics;
+mod digest;
+mod event_store;
+mod export;
+#[cfg(test)]
+mod fixtures;
+mod http;
+mod logging;
+mod mail;
+mod metrics;
+mod model_registry;
 mod models;
+mod money;
+mod notifications;
+mod periods;
+mod pricing;
+mod reporting;
+mod schema;
+mod secret;
+mod settings;
+mod template;
 mod token_extractor;
+mod tray_icon;
+mod tray_menu;
+mod webhooks;
 
-use cursor_api::CursorApi;
-use models::UsageDisplayData;
-use std::sync::Mutex;
-use tauri::{
-    menu::{MenuBuilder, MenuItemBuilder},
-    tray::TrayIconBuilder,
-    AppHandle, Emitter, Manager,
-};
+use anomaly::Anomaly;
+use csv_import::ImportReport;
+use cursor_api::{CursorApi, EarlierUsage, RecentEvents};
+use digest::{Digest, DigestFormat};
+use event_store::{EventSource, EventStore};
+use export::{ExportDataset, ExportFormat};
+use metrics::Metrics;
+use model_registry::ModelRegistry;
+use models::{ErrorKind, KindSummary, RefreshStatus, UsageDisplayData, UsageEvent};
+use money::Money;
+use notifications::{Category, Notification, NotificationRecord, Notifier, Outcome};
+use periods::PeriodRange;
+use pricing::PricingTable;
+use reporting::ReportingClock;
+use schema::SchemaDrift;
+use settings::{DigestDelivery, DigestSettings, Settings, TrayTemplates, WebhookSettings};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{tray::TrayIconBuilder, AppHandle, Emitter, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_notification::NotificationExt;
+use template::TemplateContext;
+use token_extractor::{TokenError, TokenInfo};
+use tray_icon::IconState;
+use webhooks::Retry;
+
+/// Matches `identifier` in tauri.conf.json so the CLI and the tray app
+/// share files without needing an `AppHandle`.
+const APP_IDENTIFIER: &str = "com.bhealy.cursor-status-bar";
+
+/// Anomalies are kept for as long as a refresh can still see their events.
+const ANOMALY_HISTORY_DAYS: i64 = 62;
+
+/// Anomalies older than this when first found, e.g. on the first refresh
+/// after install, are recorded without a notification.
+const ANOMALY_NOTIFY_MINUTES: i64 = 60;
+
+/// The event store, locked separately from `AppState` so reads and bulk
+/// writes do not hold up commands and the tray. Lock `AppState` first when
+/// both are needed.
+type SharedStore = Arc<Mutex<EventStore>>;
 
 /// Shared app state
 struct AppState {
     api: Option<CursorApi>,
     last_data: Option<UsageDisplayData>,
     error: Option<String>,
+    /// Problems found in the settings file at startup, each left at its
+    /// default. Unlike `error` they last until the app restarts.
+    settings_warnings: Vec<String>,
+    settings: Settings,
+    tray_templates: TrayTemplates,
+    /// Parsed from `settings.reporting`.
+    clock: ReportingClock,
+    /// Bundled prices plus `settings.pricing`.
+    pricing: PricingTable,
+    /// Aliases and line item grouping from `settings.models`.
+    models: ModelRegistry,
+    metrics: Metrics,
+    store: Option<SharedStore>,
+    /// Cursor user id of the active account, once its token has been read.
+    /// Stored history, anomalies and the disk cache are kept per account.
+    account_id: Option<String>,
+    last_updated: Option<chrono::DateTime<chrono::Utc>>,
+    /// Index into `account_names()`; 0 is the default Cursor install.
+    active_account: usize,
+    paused: bool,
+    /// True when `last_data` is from an earlier refresh or the disk cache.
+    stale: bool,
+    error_kind: Option<ErrorKind>,
+    log_dir: Option<PathBuf>,
+    /// Format problems in the last successful refresh.
+    schema_drift: SchemaDrift,
+    /// Built once from `settings.http` and reused by every request.
+    http_client: reqwest::Client,
+    /// Spending anomalies found so far, oldest first.
+    anomalies: Vec<Anomaly>,
+    /// Account and what its next refresh can reuse from the last one.
+    earlier_usage: Option<(String, EarlierUsage)>,
+    notifier: Notifier,
+    /// Billing period start and the highest budget threshold announced in it.
+    budget_alerted: Option<(String, u32)>,
+    /// Billing period start and the highest share of the spending limit
+    /// announced in it.
+    spend_limit_warned: Option<(String, u32)>,
+    /// Reporting day the last daily summary was sent on.
+    last_summary_day: Option<chrono::NaiveDate>,
+    /// When each scheduled digest was last sent, by `DigestSettings::key`.
+    digests_sent: HashMap<String, chrono::DateTime<chrono::Utc>>,
+    /// Digests being delivered, which are not due again until that ends.
+    digests_sending: HashSet<String>,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        AppState {
+            api: None,
+            last_data: None,
+            error: None,
+            settings: Settings::default(),
+            tray_templates: Settings::default().tray_templates().expect("default templates are valid"),
+            clock: ReportingClock::default(),
+            pricing: PricingTable::default(),
+            models: ModelRegistry::default(),
+            metrics: Metrics::default(),
+            store: None,
+            account_id: None,
+            last_updated: None,
+            active_account: 0,
+            paused: false,
+            stale: false,
+            error_kind: None,
+            settings_warnings: Vec::new(),
+            log_dir: None,
+            schema_drift: SchemaDrift::default(),
+            http_client: http::default_client(),
+            anomalies: Vec::new(),
+            earlier_usage: None,
+            notifier: Notifier::default(),
+            budget_alerted: None,
+            spend_limit_warned: None,
+            last_summary_day: None,
+            digests_sent: HashMap::new(),
+            digests_sending: HashSet::new(),
+        }
+    }
+}
+
+impl AppState {
+    fn account_names(&self) -> Vec<String> {
+        std::iter::once("Default".to_string())
+            .chain(self.settings.accounts.iter().map(|a| a.name.clone()))
+            .collect()
+    }
+
+    /// Extract the session token for the active account.
+    fn extract_token(&self) -> Result<TokenInfo, TokenError> {
+        match self.active_account.checked_sub(1).and_then(|i| self.settings.accounts.get(i)) {
+            Some(account) => token_extractor::extract_token_from(&account.database_path),
+            None => token_extractor::extract_token(),
+        }
+    }
+
+    /// Record an error for the UI, with tokens and response bodies scrubbed.
+    fn set_error(&mut self, message: String) {
+        self.error = Some(secret::sanitize_error(&message));
+    }
+
+    /// "Stale since HH:MM (offline)" while showing old data.
+    fn stale_text(&self) -> Option<String> {
+        if !self.stale {
+            return None;
+        }
+        let since = self.clock.format_wall_clock(self.last_updated?, "%H:%M");
+        Some(match self.error_kind {
+            Some(kind) => format!("Stale since {} ({})", since, kind.describe()),
+            None => format!("Stale since {}", since),
+        })
+    }
+
+    /// Add newly detected anomalies to the history and the event store.
+    /// Returns the new ones recent enough to notify about.
+    fn record_anomalies(&mut self, events: &[UsageEvent], now: chrono::DateTime<chrono::Utc>) -> Vec<Anomaly> {
+        let new: Vec<Anomaly> = anomaly::detect(events, &self.settings.anomalies)
+            .into_iter()
+            .filter(|a| !self.anomalies.iter().any(|seen| seen.same_as(a)))
+            .collect();
+        if new.is_empty() {
+            return new;
+        }
+        for a in &new {
+            tracing::warn!(
+                kind = a.kind.as_str(),
+                model = %a.model,
+                spend = %a.spend_dollars,
+                at = %a.at,
+                "spending anomaly"
+            );
+        }
+        if let (Some(store), Some(account)) = (&self.store, &self.account_id) {
+            if let Err(e) = store.lock().unwrap().insert_anomalies(account, &new) {
+                tracing::warn!(error = %e, "cannot store anomalies");
+            }
+        }
+
+        self.anomalies.extend(new.iter().cloned());
+        self.anomalies.sort_by_key(|a| a.at);
+        self.anomalies
+            .retain(|a| a.at >= now - chrono::Duration::days(ANOMALY_HISTORY_DAYS));
+        new.into_iter()
+            .filter(|a| a.at >= now - chrono::Duration::minutes(ANOMALY_NOTIFY_MINUTES))
+            .collect()
+    }
+
+    /// The configured budget, or failing that the spending limit from the
+    /// Cursor dashboard.
+    fn budget(&self) -> Option<Money> {
+        self.settings
+            .budget()
+            .or_else(|| self.last_data.as_ref()?.spend_limit_dollars)
+    }
+
+    /// True when a notification of `category` would reach the desktop or a
+    /// webhook. Budget alerts are only recorded as announced if so, so that
+    /// turning them on later still announces the current threshold.
+    fn delivers(&self, category: Category) -> bool {
+        let desktop = &self.settings.notifications;
+        (desktop.enabled && desktop.allows(category)) || self.settings.webhooks.iter().any(|h| h.wants(category))
+    }
+
+    /// Notification for a budget threshold newly reached this billing
+    /// period. Each threshold is announced once.
+    fn check_budget(&mut self) -> Option<Notification> {
+        if !self.delivers(Category::Budget) {
+            return None;
+        }
+        let budget = self.budget()?;
+        let data = self.last_data.as_ref()?;
+        let (threshold, notification) = notifications::budget_threshold(
+            data,
+            budget,
+            &self.settings.notifications.budget_thresholds,
+        )?;
+        if self
+            .budget_alerted
+            .as_ref()
+            .is_some_and(|(start, t)| *start == data.billing_period_start && *t >= threshold)
+        {
+            return None;
+        }
+        self.budget_alerted = Some((data.billing_period_start.clone(), threshold));
+        Some(notification)
+    }
+
+    /// Warning before Cursor cuts off requests at the spending limit, and
+    /// another when it does. Each is announced once per billing period.
+    fn check_spend_limit(&mut self) -> Option<Notification> {
+        if !self.delivers(Category::Budget) {
+            return None;
+        }
+        let data = self.last_data.as_ref()?;
+        let (threshold, notification) =
+            notifications::spend_limit_warning(data, self.settings.notifications.spend_limit_warning_percent)?;
+        if self
+            .spend_limit_warned
+            .as_ref()
+            .is_some_and(|(start, t)| *start == data.billing_period_start && *t >= threshold)
+        {
+            return None;
+        }
+        self.spend_limit_warned = Some((data.billing_period_start.clone(), threshold));
+        Some(notification)
+    }
+
+    /// Yesterday's summary, on the first refresh of each reporting day.
+    fn daily_summary(
+        &mut self,
+        events: &[UsageEvent],
+        data: &UsageDisplayData,
+        now: chrono::DateTime<chrono::Utc>,
+        billing_start: chrono::DateTime<chrono::Utc>,
+    ) -> Option<Notification> {
+        let today = self.clock.day_of(now);
+        if self.last_summary_day == Some(today) {
+            return None;
+        }
+        self.last_summary_day = Some(today);
+        let (start, end) = PeriodRange::Yesterday.bounds(now, &self.clock, billing_start);
+        let yesterday = cursor_api::summarize_range("Yesterday", events, start, end);
+        Some(notifications::daily_summary(today - chrono::Duration::days(1), &yesterday, data))
+    }
+
+    /// Digests whose scheduled time has passed since they were last sent,
+    /// built and marked as being sent; `digest_delivered` records them as
+    /// sent. A digest seen for the first time starts its schedule now rather
+    /// than sending one straight away.
+    fn due_digests(&mut self, events: &[UsageEvent], now: chrono::DateTime<chrono::Utc>) -> Vec<(DigestSettings, Digest)> {
+        let Some(data) = self.last_data.as_ref() else {
+            return Vec::new();
+        };
+        let budget = self.budget();
+        let mut due = Vec::new();
+        let mut first_seen = Vec::new();
+        for settings in &self.settings.digests {
+            let key = settings.key();
+            if self.digests_sending.contains(&key) {
+                continue;
+            }
+            if let Some(last_sent) = self.digests_sent.get(&key) {
+                if settings.is_due(now, *last_sent, &self.clock) {
+                    due.push((
+                        settings.clone(),
+                        digest::build(settings, events, data, now, &self.clock, &self.models, budget),
+                    ));
+                    self.digests_sending.insert(key);
+                }
+            } else {
+                first_seen.push(key);
+            }
+        }
+        for key in first_seen {
+            self.record_digest(key, now);
+        }
+        due
+    }
+
+    /// The end of a digest's delivery. It counts as sent as of `due_at`
+    /// when it reached at least one destination; otherwise it is due again
+    /// on the next refresh.
+    fn digest_delivered(&mut self, key: String, due_at: chrono::DateTime<chrono::Utc>, delivered: bool) {
+        self.digests_sending.remove(&key);
+        if delivered {
+            self.record_digest(key, due_at);
+        }
+    }
+
+    fn record_digest(&mut self, key: String, at: chrono::DateTime<chrono::Utc>) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.lock().unwrap().set_digest_sent(&key, at) {
+                tracing::warn!(digest = %key, error = %e, "cannot record digest");
+            }
+        }
+        self.digests_sent.insert(key, at);
+    }
+
+    fn api_for(&self, info: TokenInfo) -> CursorApi {
+        CursorApi::new(self.http_client.clone(), info.session_token, info.user_id)
+    }
+
+    /// Make `user_id` the account whose history is read and written, and load
+    /// the anomalies recorded for it.
+    fn set_account(&mut self, user_id: &str) {
+        if self.account_id.as_deref() == Some(user_id) {
+            return;
+        }
+        self.account_id = Some(user_id.to_string());
+        self.anomalies.clear();
+        let Some(store) = &self.store else {
+            return;
+        };
+        let store = store.lock().unwrap();
+        let since = chrono::Utc::now() - chrono::Duration::days(ANOMALY_HISTORY_DAYS);
+        match store.anomalies_since(user_id, since) {
+            Ok(anomalies) => self.anomalies = anomalies,
+            Err(e) => tracing::warn!(error = %e, "cannot read recorded anomalies"),
+        }
+    }
+
+    /// The store and active account, for reading or writing history
+    /// without holding the state lock.
+    fn history(&self) -> Option<(SharedStore, String)> {
+        Some((self.store.clone()?, self.account_id.clone()?))
+    }
+
+    /// (Re)initialise the API for the active account.
+    fn init_api(&mut self) {
+        match self.extract_token() {
+            Ok(info) => {
+                self.set_account(&info.user_id);
+                self.api = Some(self.api_for(info));
+            }
+            Err(e) => {
+                tracing::warn!(account = self.active_account, error = %secret::sanitize_error(&e.to_string()), "token extraction failed");
+                self.api = None;
+                self.set_error(format!("Token error: {}", e));
+                self.error_kind = Some(ErrorKind::Auth);
+            }
+        }
+    }
 }
 
 /// Tauri command: get the latest usage data
@@ -32,6 +415,135 @@ fn get_error(state: tauri::State<'_, Mutex<AppState>>) -> Result<Option<String>,
     Ok(state.error.clone())
 }
 
+/// Tauri command: whether the shown data is fresh, and why not
+#[tauri::command]
+fn get_status(state: tauri::State<'_, Mutex<AppState>>) -> Result<RefreshStatus, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    Ok(RefreshStatus {
+        fetched_at: state.last_updated.map(|t| t.to_rfc3339()),
+        stale: state.stale,
+        error_kind: state.error_kind,
+        warning: state.schema_drift.warning(),
+        settings_warnings: state.settings_warnings.clone(),
+    })
+}
+
+/// Tauri command: recorded spending anomalies, newest first, with display
+/// names for models
+#[tauri::command]
+fn get_anomalies(state: tauri::State<'_, Mutex<AppState>>) -> Result<Vec<Anomaly>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    Ok(state
+        .anomalies
+        .iter()
+        .rev()
+        .map(|a| Anomaly {
+            model: state.models.info(&a.model).display_name(),
+            ..a.clone()
+        })
+        .collect())
+}
+
+/// Tauri command: spend by request kind and model for `range`, a period
+/// such as `today` or `last14Days`; the billing period when omitted
+#[tauri::command]
+async fn get_kind_breakdown(
+    state: tauri::State<'_, Mutex<AppState>>,
+    range: Option<String>,
+) -> Result<Vec<KindSummary>, String> {
+    let (start, end, store, account, pricing, models) = {
+        let state = state.lock().map_err(|e| e.to_string())?;
+        let data = state.last_data.as_ref().ok_or("No usage data yet")?;
+        let Some(range) = range else {
+            return Ok(data.kinds.clone());
+        };
+        let range: PeriodRange = range.parse().map_err(|e: periods::PeriodRangeError| e.to_string())?;
+        let now = chrono::Utc::now();
+        let (start, end) = range.bounds(now, &state.clock, data.billing_period_start_utc().unwrap_or(now));
+        let account = state.account_id.clone().ok_or("No usage data yet")?;
+        (start, end, state.store.clone(), account, state.pricing.clone(), state.models.clone())
+    };
+
+    // Stored history reaches furthest back; without it, use the events from
+    // the last refresh, which only cover the last ~30 days
+    let stored = store.and_then(|store| {
+        store
+            .lock()
+            .unwrap()
+            .events_between(&account, start, end)
+            .map_err(|e| tracing::warn!(error = %e, "cannot read stored events"))
+            .ok()
+    });
+    let mut events = match stored {
+        Some(events) => events,
+        None => cache::load(&account)
+            .map_err(|e| e.to_string())?
+            .map(|cached| cached.events)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|e| (start..end).contains(&e.time()))
+            .collect(),
+    };
+    pricing.apply(&mut events);
+    Ok(cursor_api::kind_breakdown(&events, &models))
+}
+
+/// Tauri command: notifications sent or held back, newest first
+#[tauri::command]
+fn get_notification_history(state: tauri::State<'_, Mutex<AppState>>) -> Result<Vec<NotificationRecord>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    Ok(state.notifier.history().cloned().collect())
+}
+
+/// Tauri command: send a test message to the webhook at `index` in settings
+#[tauri::command]
+async fn send_test_webhook(app: AppHandle, index: usize) -> Result<(), String> {
+    test_webhook(&app, index).await
+}
+
+/// Send a test message to each webhook from the tray and report the result
+/// as a notification, since the tray has nowhere else to show it.
+async fn test_all_webhooks(app: &AppHandle) {
+    let hooks: Vec<String> = {
+        let state = app.state::<Mutex<AppState>>();
+        let s = state.lock().unwrap();
+        s.settings.webhooks.iter().map(|h| h.label()).collect()
+    };
+    let mut failures = Vec::new();
+    for (index, label) in hooks.iter().enumerate() {
+        if let Err(e) = test_webhook(app, index).await {
+            tracing::error!(webhook = %label, error = %secret::sanitize_error(&e.to_string()), "webhook test failed");
+            failures.push(format!("{}: {}", label, e));
+        }
+    }
+    let body = if failures.is_empty() {
+        format!("Test message sent to {} webhook(s)", hooks.len())
+    } else {
+        failures.join("\n")
+    };
+    if let Err(e) = app.notification().builder().title("Webhook test").body(body).show() {
+        tracing::warn!(error = %e, "cannot show notification");
+    }
+}
+
+async fn test_webhook(app: &AppHandle, index: usize) -> Result<(), String> {
+    let (client, url, payload) = {
+        let state = app.state::<Mutex<AppState>>();
+        let s = state.lock().map_err(|e| e.to_string())?;
+        let hook = s.settings.webhooks.get(index).ok_or("No webhook at that index")?;
+        let payload = webhooks::payload(
+            hook.format,
+            &notifications::test_notification(),
+            None,
+            s.last_data.as_ref(),
+        );
+        (s.http_client.clone(), hook.url.clone(), payload)
+    };
+    webhooks::deliver(&client, &url, &payload, Retry::once())
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// Tauri command: trigger a manual refresh
 #[tauri::command]
 async fn refresh(app: AppHandle) -> Result<(), String> {
@@ -47,6 +559,100 @@ fn open_dashboard() -> Result<(), String> {
     open::that("https://cursor.com/dashboard?tab=usage").map_err(|e| e.to_string())
 }
 
+/// Tauri command: export usage for an inclusive YYYY-MM-DD date range.
+/// Returns the number of rows written.
+#[tauri::command]
+async fn export_usage(
+    app: AppHandle,
+    from: String,
+    to: String,
+    dataset: ExportDataset,
+    format: ExportFormat,
+    path: String,
+) -> Result<usize, String> {
+    let clock = app.state::<Mutex<AppState>>().lock().map_err(|e| e.to_string())?.clock;
+    let (start, end) = export::parse_date_range(&from, &to, &clock).map_err(|e| e.to_string())?;
+    export_with_app(&app, start, end, dataset, format, &PathBuf::from(path)).await
+}
+
+/// Fetch a range from the API, merge it with the local event store and write it out.
+async fn export_with_app(
+    app: &AppHandle,
+    start: chrono::DateTime<chrono::Utc>,
+    end: chrono::DateTime<chrono::Utc>,
+    dataset: ExportDataset,
+    format: ExportFormat,
+    path: &std::path::Path,
+) -> Result<usize, String> {
+    let (api, account, store, clock, pricing) = {
+        let state = app.state::<Mutex<AppState>>();
+        let s = state.lock().map_err(|e| e.to_string())?;
+        let info = s.extract_token().map_err(|e| format!("Token error: {}", e))?;
+        let account = info.user_id.clone();
+        (s.api_for(info), account, s.store.clone(), s.clock, s.pricing.clone())
+    };
+    // Skipped events are already logged; the export still covers the rest
+    let (mut events, _drift) = export::fetch_range(&api, start, end)
+        .await
+        .map_err(|e| secret::sanitize_error(&e.to_string()))?;
+
+    if let Some(store) = store {
+        let mut store = store.lock().map_err(|e| e.to_string())?;
+        events = export::with_stored_history(events, &mut store, &account, start, end).map_err(|e| e.to_string())?;
+    }
+    pricing.apply(&mut events);
+
+    export::write_dataset(&events, dataset, format, path, &clock).map_err(|e| e.to_string())
+}
+
+/// Export the current billing period's events as CSV into the Downloads
+/// folder and reveal it. Used by the tray menu, which cannot ask for a range.
+async fn export_billing_period(app: &AppHandle) -> Result<PathBuf, String> {
+    let (start, clock) = {
+        let state = app.state::<Mutex<AppState>>();
+        let s = state.lock().unwrap();
+        let start = s
+            .last_data
+            .as_ref()
+            .and_then(|d| chrono::DateTime::parse_from_rfc3339(&d.billing_period_start).ok())
+            .map(|d| d.with_timezone(&chrono::Utc))
+            .ok_or("No billing period loaded yet")?;
+        (start, s.clock)
+    };
+    let end = chrono::Utc::now();
+
+    let dir = dirs::download_dir()
+        .or_else(dirs::home_dir)
+        .ok_or("Cannot locate Downloads folder")?;
+    let path = dir.join(format!(
+        "cursor-usage-{}.{}",
+        clock.day_of(end).format("%Y-%m-%d"),
+        ExportFormat::Csv.extension()
+    ));
+
+    export_with_app(app, start, end, ExportDataset::Events, ExportFormat::Csv, &path).await?;
+
+    let _ = open::that(&dir);
+    Ok(path)
+}
+
+/// Tauri command: import a Cursor dashboard CSV export into the local event store
+#[tauri::command]
+async fn import_usage_csv(state: tauri::State<'_, Mutex<AppState>>, path: String) -> Result<ImportReport, String> {
+    let (store, account) = state
+        .lock()
+        .map_err(|e| e.to_string())?
+        .history()
+        .ok_or("Local event store is unavailable")?;
+    // File and SQLite I/O, kept off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut store = store.lock().map_err(|e| e.to_string())?;
+        csv_import::import_csv(&PathBuf::from(path), &mut store, &account).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 /// Perform a data refresh: fetch from API and update tray + state.
 async fn do_refresh(app: &AppHandle) {
     let state = app.state::<Mutex<AppState>>();
@@ -61,51 +667,399 @@ async fn do_refresh(app: &AppHandle) {
 
     // Re-extract token each time (it may have refreshed) and create a new API
     // instance. This avoids holding the Mutex across the await point.
-    let api = match token_extractor::extract_token() {
-        Ok(info) => Some(CursorApi::new(info.session_token, info.user_id)),
+    let token = {
+        let mut s = state.lock().unwrap();
+        s.extract_token().map(|info| {
+            s.set_account(&info.user_id);
+            (info.user_id.clone(), s.api_for(info))
+        })
+    };
+    let (account, api) = match token {
+        Ok((account, api)) => (account, Some(api)),
         Err(e) => {
             let mut s = state.lock().unwrap();
-            s.error = Some(format!("Token error: {}", e));
+            s.set_error(format!("Token error: {}", e));
+            s.error_kind = Some(ErrorKind::Auth);
+            s.metrics.record_failure(None);
             s.last_data = None;
-            update_tray_tooltip(app, "Cursor Status Bar\nError: token extraction failed");
+            s.stale = false;
+            update_tray(app, &s);
+            drop(s);
+            rebuild_tray_menu(app);
             return;
         }
     };
 
     if let Some(api) = api {
-        match api.fetch_display_data().await {
-            Ok(data) => {
-                let today_spend = format!("${:.2}", data.today.spend_dollars);
-                let period_spend = format!("${:.2}", data.total_spend_dollars);
+        let earlier = state
+            .lock()
+            .unwrap()
+            .earlier_usage
+            .take()
+            .filter(|(id, _)| *id == account)
+            .map(|(_, earlier)| earlier);
+        let started = Instant::now();
+        let result = api.fetch_recent_events(earlier).await;
+        let latency = started.elapsed();
 
-                // macOS: show short text in the menu bar
-                #[cfg(target_os = "macos")]
-                if let Some(tray) = app.tray_by_id("main-tray") {
-                    let _ = tray.set_title(Some(&today_spend));
+        match result {
+            Ok(mut recent) => {
+                let fetched_at = chrono::Utc::now();
+                let (mut data, store, custom_periods, pricing, clock) = {
+                    let mut s = state.lock().unwrap();
+                    s.earlier_usage = recent.earlier.take().map(|earlier| (account.clone(), earlier));
+                    s.pricing.apply(&mut recent.events);
+                    let data = recent.display_data(fetched_at, &s.clock, &s.models);
+                    (data, s.store.clone(), s.settings.periods.clone(), s.pricing.clone(), s.clock)
+                };
+                let RecentEvents {
+                    billing_start,
+                    events,
+                    drift,
+                    ..
+                } = recent;
+
+                // Store I/O happens without the state lock
+                let mut store = store.as_ref().map(|store| store.lock().unwrap());
+                if let Some(store) = store.as_deref_mut() {
+                    if let Err(e) = store.insert_events(&account, &events, EventSource::Api) {
+                        tracing::warn!(error = %e, "cannot store events");
+                    }
                 }
+                data.custom_periods = periods::summarize(
+                    &custom_periods,
+                    &events,
+                    store.as_deref().map(|store| (store, account.as_str())),
+                    &pricing,
+                    fetched_at,
+                    &clock,
+                    billing_start,
+                );
+                drop(store);
 
-                // Tooltip for all platforms (hover on Windows/Linux)
-                let tooltip = format!(
-                    "Cursor Status Bar\nToday: {} ({} req)\nLast 7 Days: ${:.2} ({} req)\nBilling Period: {} ({} req)",
-                    today_spend, data.today.requests,
-                    data.last7_days.spend_dollars, data.last7_days.requests,
-                    period_spend, data.total_requests
+                let mut alerts: Vec<Notification> = {
+                    let mut s = state.lock().unwrap();
+                    let window_minutes = s.settings.anomalies.window_minutes;
+                    let mut alerts: Vec<Notification> = s
+                        .record_anomalies(&events, fetched_at)
+                        .iter()
+                        .map(|a| a.notification(window_minutes))
+                        .collect();
+                    alerts.extend(s.daily_summary(&events, &data, fetched_at, billing_start));
+                    alerts
+                };
+
+                let cached = cache::CachedUsage {
+                    version: schema::SCHEMA_VERSION,
+                    user_id: account,
+                    fetched_at,
+                    data,
+                    events,
+                };
+                if let Err(e) = cache::save(&cached) {
+                    tracing::warn!(error = %e, "cannot save usage cache");
+                }
+                tracing::info!(
+                    events = cached.events.len(),
+                    latency_ms = latency.as_millis() as u64,
+                    "refresh succeeded"
                 );
-                update_tray_tooltip(app, &tooltip);
 
                 let mut s = state.lock().unwrap();
-                s.last_data = Some(data);
+                s.metrics.record_success(latency);
+                s.last_data = Some(cached.data);
+                s.last_updated = Some(fetched_at);
+                s.stale = false;
                 s.error = None;
+                s.error_kind = None;
+                s.schema_drift = drift;
+                alerts.extend(s.check_budget());
+                alerts.extend(s.check_spend_limit());
+                let digests = s.due_digests(&cached.events, fetched_at);
+                update_tray(app, &s);
+                drop(s);
+                send_webhooks(app, &alerts);
+                notify(app, alerts);
+                deliver_digests(app, digests, fetched_at);
             }
             Err(e) => {
-                eprintln!("[CursorStatusBar] API error: {}", e);
-                update_tray_tooltip(app, &format!("Cursor Status Bar\nError: {}", e));
+                tracing::error!(error = %secret::sanitize_error(&e.to_string()), latency_ms = latency.as_millis() as u64, "refresh failed");
 
                 let mut s = state.lock().unwrap();
-                s.error = Some(format!("API error: {}", e));
+                s.metrics.record_failure(Some(latency));
+                s.set_error(format!("API error: {}", e));
+                s.error_kind = Some(cursor_api::classify_error(e.as_ref()));
+                s.stale = s.last_data.is_some();
+                update_tray(app, &s);
             }
         }
     }
+
+    rebuild_tray_menu(app);
+}
+
+/// Show the notifications the notifier lets through, along with any it held
+/// back earlier that can be shown now: enabled categories,
+/// outside quiet hours, not seen recently and under the hourly cap.
+fn notify(app: &AppHandle, notifications: Vec<Notification>) {
+    let (shown, records, store) = {
+        let state = app.state::<Mutex<AppState>>();
+        let mut s = state.lock().unwrap();
+        let now = chrono::Utc::now();
+        let time_of_day = s.clock.time_of_day(now);
+        let settings = s.settings.notifications.clone();
+        let mut shown = s.notifier.release(&settings, time_of_day, now);
+        shown.extend(notifications.into_iter().filter(|n| {
+            let outcome = s.notifier.admit(n, &settings, time_of_day, now);
+            tracing::debug!(key = %n.key, ?outcome, "notification");
+            outcome == Outcome::Shown
+        }));
+        (shown, s.notifier.take_unsaved(), s.store.clone())
+    };
+
+    // The history is kept across restarts; saved without the state lock
+    if let Some(store) = store.filter(|_| !records.is_empty()) {
+        if let Err(e) = store.lock().unwrap().insert_notifications(&records, notifications::HISTORY_LEN) {
+            tracing::warn!(error = %e, "cannot store notification history");
+        }
+    }
+
+    for n in shown {
+        if let Err(e) = app.notification().builder().title(&n.title).body(&n.body).show() {
+            tracing::warn!(error = %e, "cannot show notification");
+        }
+    }
+}
+
+/// Post alerts to every webhook that wants them, in the background.
+fn send_webhooks(app: &AppHandle, alerts: &[Notification]) {
+    let (client, deliveries) = {
+        let state = app.state::<Mutex<AppState>>();
+        let s = state.lock().unwrap();
+        let mut deliveries = Vec::new();
+        for hook in &s.settings.webhooks {
+            let extra = match (hook.parsed_template(), &s.last_data) {
+                (Ok(Some(template)), Some(data)) => Some(template.render(&TemplateContext {
+                    data,
+                    budget_dollars: s.budget(),
+                    now: chrono::Utc::now(),
+                    clock: &s.clock,
+                })),
+                _ => None,
+            };
+            for alert in alerts.iter().filter(|a| hook.wants(a.category)) {
+                let payload = webhooks::payload(hook.format, alert, extra.as_deref(), s.last_data.as_ref());
+                deliveries.push((hook.label(), hook.url.clone(), payload));
+            }
+        }
+        (s.http_client.clone(), deliveries)
+    };
+
+    for (label, url, payload) in deliveries {
+        let client = client.clone();
+        tauri::async_runtime::spawn(async move {
+            match webhooks::deliver(&client, &url, &payload, Retry::default()).await {
+                Ok(()) => tracing::info!(webhook = %label, "webhook delivered"),
+                Err(e) => tracing::error!(webhook = %label, error = %secret::sanitize_error(&e.to_string()), "webhook failed"),
+            }
+        });
+    }
+}
+
+/// Send digests to each of their destinations in the background, and
+/// record each as sent once it has reached at least one of them.
+fn deliver_digests(app: &AppHandle, digests: Vec<(DigestSettings, Digest)>, due_at: chrono::DateTime<chrono::Utc>) {
+    if digests.is_empty() {
+        return;
+    }
+    let (client, hooks, data) = {
+        let state = app.state::<Mutex<AppState>>();
+        let s = state.lock().unwrap();
+        (s.http_client.clone(), s.settings.webhooks.clone(), s.last_data.clone())
+    };
+
+    for (settings, digest) in digests {
+        let (app, client, hooks, data) = (app.clone(), client.clone(), hooks.clone(), data.clone());
+        tauri::async_runtime::spawn(async move {
+            let key = settings.key();
+            tracing::info!(digest = %key, first_day = %digest.first_day, "sending digest");
+            let mut delivered = false;
+            for delivery in &settings.deliver {
+                delivered |= deliver_digest(&app, &client, &hooks, data.as_ref(), &settings, &digest, delivery).await;
+            }
+            if !delivered {
+                tracing::error!(digest = %key, "digest not delivered anywhere, will retry");
+            }
+            let state = app.state::<Mutex<AppState>>();
+            state.lock().unwrap().digest_delivered(key, due_at, delivered);
+        });
+    }
+}
+
+/// Send a digest to one destination. True when it got there.
+async fn deliver_digest(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    hooks: &[WebhookSettings],
+    data: Option<&UsageDisplayData>,
+    settings: &DigestSettings,
+    digest: &Digest,
+    delivery: &DigestDelivery,
+) -> bool {
+    let key = settings.key();
+    match delivery {
+        // Held back by quiet hours or the rate limit, it is still shown later
+        DigestDelivery::Notification => {
+            notify(
+                app,
+                vec![Notification {
+                    category: Category::Digest,
+                    key: format!("digest:{}:{}", key, digest.first_day),
+                    title: digest.title.clone(),
+                    body: digest.headline(),
+                }],
+            );
+            true
+        }
+        DigestDelivery::File { directory } => {
+            let path = directory.join(digest.file_name(&key, settings.format));
+            let written = std::fs::create_dir_all(directory)
+                .and_then(|()| std::fs::write(&path, digest.render(settings.format)));
+            match written {
+                Ok(()) => tracing::info!(digest = %key, path = %path.display(), "digest written"),
+                Err(ref e) => tracing::error!(digest = %key, path = %path.display(), error = %e, "cannot write digest"),
+            }
+            written.is_ok()
+        }
+        DigestDelivery::Mail { to, from, smtp } => {
+            let message = mail::Message {
+                from: from.clone(),
+                to: to.clone(),
+                subject: digest.title.clone(),
+                body: digest.render(settings.format),
+                html: settings.format == DigestFormat::Html,
+            };
+            let sent = match smtp {
+                Some(addr) => mail::send_smtp(addr, &message).await,
+                None => mail::send_sendmail(&message).await,
+            };
+            match &sent {
+                Ok(()) => tracing::info!(digest = %key, to = %message.to, "digest mailed"),
+                Err(e) => tracing::error!(digest = %key, error = %secret::sanitize_error(&e.to_string()), "cannot mail digest"),
+            }
+            sent.is_ok()
+        }
+        DigestDelivery::Webhook { webhook } => {
+            let Some(hook) = hooks.iter().find(|h| h.name == *webhook) else {
+                tracing::error!(digest = %key, webhook = %webhook, "digest webhook not found in settings");
+                return false;
+            };
+            let notification = Notification {
+                category: Category::Digest,
+                key: format!("digest:{}:{}", key, digest.first_day),
+                title: digest.title.clone(),
+                body: digest.render_body(settings.format),
+            };
+            let payload = webhooks::payload(hook.format, &notification, None, data);
+            match webhooks::deliver(client, &hook.url, &payload, Retry::default()).await {
+                Ok(()) => {
+                    tracing::info!(webhook = %hook.label(), "digest delivered");
+                    true
+                }
+                Err(e) => {
+                    tracing::error!(webhook = %hook.label(), error = %secret::sanitize_error(&e.to_string()), "digest webhook failed");
+                    false
+                }
+            }
+        }
+    }
+}
+
+/// Rebuild the tray menu from the current state.
+fn rebuild_tray_menu(app: &AppHandle) {
+    let state = app.state::<Mutex<AppState>>();
+    let menu = {
+        let s = state.lock().unwrap();
+        tray_menu::build(app, &s)
+    };
+    match menu {
+        Ok(menu) => {
+            if let Some(tray) = app.tray_by_id("main-tray") {
+                let _ = tray.set_menu(Some(menu));
+            }
+        }
+        Err(e) => tracing::error!(error = %e, "cannot build tray menu"),
+    }
+}
+
+/// Switch the active account from the tray menu and refresh right away.
+fn switch_account(app: &AppHandle, index: usize) {
+    {
+        let state = app.state::<Mutex<AppState>>();
+        let mut s = state.lock().unwrap();
+        if index >= s.account_names().len() || index == s.active_account {
+            drop(s);
+            rebuild_tray_menu(app);
+            return;
+        }
+        s.active_account = index;
+        s.last_data = None;
+        s.last_updated = None;
+        s.stale = false;
+        s.error = None;
+        s.error_kind = None;
+        s.schema_drift = SchemaDrift::default();
+        s.init_api();
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        do_refresh(&app).await;
+        let _ = app.emit("usage-updated", ());
+    });
+}
+
+/// Update the tray title, tooltip and icon from the current state.
+fn update_tray(app: &AppHandle, s: &AppState) {
+    let Some(data) = &s.last_data else {
+        let error = s.error.as_deref().unwrap_or("no data");
+        update_tray_tooltip(app, &format!("Cursor Status Bar\nError: {}", error));
+        update_tray_icon(app, IconState::Error);
+        return;
+    };
+
+    let budget = s.budget();
+    let ctx = TemplateContext {
+        data,
+        budget_dollars: budget,
+        now: chrono::Utc::now(),
+        clock: &s.clock,
+    };
+
+    // macOS: show short text in the menu bar
+    #[cfg(target_os = "macos")]
+    if let Some(tray) = app.tray_by_id("main-tray") {
+        let _ = tray.set_title(Some(&s.tray_templates.title.render(&ctx)));
+    }
+
+    // Tooltip for all platforms (hover on Windows/Linux)
+    let mut tooltip = s.tray_templates.tooltip.render(&ctx);
+    let budget_fraction = budget.and_then(|b| data.total_spend_dollars.ratio(b));
+    match s.stale_text() {
+        Some(stale) => {
+            tooltip.push('\n');
+            tooltip.push_str(&stale);
+            update_tray_icon(app, IconState::Stale { budget_fraction });
+        }
+        None => update_tray_icon(
+            app,
+            IconState::Usage {
+                spend_dollars: data.today.spend_dollars.as_dollars_f64(),
+                budget_fraction,
+            },
+        ),
+    }
+    update_tray_tooltip(app, &tooltip);
 }
 
 /// Update the tray icon tooltip (shown on hover on all platforms).
@@ -115,6 +1069,15 @@ fn update_tray_tooltip(app: &AppHandle, text: &str) {
     }
 }
 
+/// Replace the tray icon with one rendered for the current state. The
+/// rendered icon is coloured, so it must not be drawn as a macOS template.
+fn update_tray_icon(app: &AppHandle, icon: IconState) {
+    if let Some(tray) = app.tray_by_id("main-tray") {
+        let _ = tray.set_icon(Some(tray_icon::render(icon)));
+        let _ = tray.set_icon_as_template(false);
+    }
+}
+
 /// Show or create the popup window, positioned near the tray area.
 fn show_popup(app: &AppHandle) {
     if let Some(window) = app.get_webview_window("popup") {
@@ -164,41 +1127,114 @@ pub fn run() {
     builder
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_process::init())
-        .manage(Mutex::new(AppState {
-            api: None,
-            last_data: None,
-            error: None,
-        }))
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_notification::init())
+        .manage(Mutex::new(AppState::default()))
         .invoke_handler(tauri::generate_handler![
             get_usage_data,
             get_error,
+            get_status,
+            get_anomalies,
+            get_kind_breakdown,
+            get_notification_history,
+            send_test_webhook,
             refresh,
             open_dashboard,
+            export_usage,
+            import_usage_csv,
         ])
         .setup(|app| {
-            // Extract token and initialize API
             let managed_state = app.state::<Mutex<AppState>>();
-            match token_extractor::extract_token() {
-                Ok(info) => {
-                    let mut state = managed_state.lock().unwrap();
-                    state.api = Some(CursorApi::new(info.session_token, info.user_id));
+
+            // Load user settings (defaults if the file is missing)
+            let (settings, settings_problems) = match app.path().app_config_dir() {
+                Ok(dir) => match Settings::load(&dir) {
+                    Ok(loaded) => loaded,
+                    Err(e) => (Settings::default(), vec![e]),
+                },
+                Err(_) => (Settings::default(), Vec::new()),
+            };
+
+            // Logging comes straight after settings, which choose the level
+            let log_dir = app.path().app_log_dir().ok();
+            if let Some(dir) = &log_dir {
+                logging::init(dir, &settings.log_level);
+            }
+            tracing::info!(version = %app.package_info().version, "starting");
+            for e in &settings_problems {
+                tracing::error!(error = %e, "invalid settings");
+            }
+            managed_state.lock().unwrap().settings_warnings = settings_problems
+                .iter()
+                .map(|e| secret::sanitize_error(&e.to_string()))
+                .collect();
+
+            {
+                let mut state = managed_state.lock().unwrap();
+                state.log_dir = log_dir;
+                match http::build_client(&settings.http) {
+                    Ok(client) => state.http_client = client,
+                    Err(e) => {
+                        tracing::error!(error = %e, "invalid HTTP settings, using defaults");
+                        state.http_client = http::default_client();
+                        let warning = format!("{}; using the default HTTP settings", e);
+                        state.settings_warnings.push(secret::sanitize_error(&warning));
+                    }
                 }
-                Err(e) => {
-                    eprintln!("[CursorStatusBar] Token extraction failed: {}", e);
+                if let Ok(templates) = settings.tray_templates() {
+                    state.tray_templates = templates;
+                }
+                if let Ok(clock) = settings.reporting_clock() {
+                    state.clock = clock;
+                }
+                state.pricing = settings.pricing_table();
+                state.models = ModelRegistry::new(&settings.models);
+                state.settings = settings.clone();
+            }
+
+            // Local event store for history beyond the API's ~30 day window
+            match EventStore::open_default() {
+                Ok(store) => {
                     let mut state = managed_state.lock().unwrap();
-                    state.error = Some(format!("Token error: {}", e));
+                    match store.digests_sent() {
+                        Ok(sent) => state.digests_sent = sent,
+                        Err(e) => tracing::warn!(error = %e, "cannot read sent digests"),
+                    }
+                    match store.notifications() {
+                        Ok(history) => state.notifier = Notifier::with_history(history),
+                        Err(e) => tracing::warn!(error = %e, "cannot read notification history"),
+                    }
+                    state.store = Some(Arc::new(Mutex::new(store)));
                 }
+                Err(e) => tracing::warn!(error = %e, "event store unavailable"),
             }
 
-            // Build tray menu (right-click on Windows, or fallback)
-            let refresh_item = MenuItemBuilder::with_id("refresh", "Refresh Now").build(app)?;
-            let dashboard_item =
-                MenuItemBuilder::with_id("dashboard", "Open Cursor Dashboard").build(app)?;
-            let quit_item = MenuItemBuilder::with_id("quit", "Quit").build(app)?;
+            // Extract token and initialize API. This also loads the account's
+            // recorded anomalies, which are not notified again
+            managed_state.lock().unwrap().init_api();
+
+            // Show the account's last successful refresh until the first fetch
+            // completes, so the popup is not empty when starting offline
+            let account = managed_state.lock().unwrap().account_id.clone();
+            match account.map(|account| cache::load(&account)).transpose().map(Option::flatten) {
+                Ok(Some(cached)) => {
+                    let mut state = managed_state.lock().unwrap();
+                    state.last_data = Some(cached.data);
+                    state.last_updated = Some(cached.fetched_at);
+                    state.stale = true;
+                    // Thresholds the cached spend already reached were announced
+                    // before, and so was the summary if it was fetched today
+                    state.check_budget();
+                    state.check_spend_limit();
+                    state.last_summary_day = Some(state.clock.day_of(cached.fetched_at));
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!(error = %e, "cannot load usage cache"),
+            }
 
-            let menu = MenuBuilder::new(app)
-                .items(&[&refresh_item, &dashboard_item, &quit_item])
-                .build()?;
+            // Build tray menu (right-click on Windows, or fallback).
+            // It is rebuilt with live figures after every refresh.
+            let menu = tray_menu::build(app.handle(), &managed_state.lock().unwrap())?;
 
             // Build tray icon
             let _tray = TrayIconBuilder::with_id("main-tray")
@@ -217,10 +1253,46 @@ pub fn run() {
                     "dashboard" => {
                         let _ = open::that("https://cursor.com/dashboard?tab=usage");
                     }
+                    "export" => {
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            if let Err(e) = export_billing_period(&app).await {
+                                tracing::error!(error = %e, "export failed");
+                                update_tray_tooltip(&app, &format!("Cursor Status Bar\nExport failed: {}", e));
+                            }
+                        });
+                    }
+                    "test-webhooks" => {
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            test_all_webhooks(&app).await;
+                        });
+                    }
+                    "diagnostics" => {
+                        let state = app.state::<Mutex<AppState>>();
+                        let bundle = diagnostics::bundle(app, &state.lock().unwrap());
+                        if let Err(e) = app.clipboard().write_text(bundle) {
+                            tracing::error!(error = %e, "cannot copy diagnostics");
+                        }
+                    }
+                    "pause" => {
+                        let state = app.state::<Mutex<AppState>>();
+                        let mut s = state.lock().unwrap();
+                        s.paused = !s.paused;
+                        drop(s);
+                        rebuild_tray_menu(app);
+                    }
                     "quit" => {
                         app.exit(0);
                     }
-                    _ => {}
+                    id => {
+                        if let Some(index) = id
+                            .strip_prefix(tray_menu::ACCOUNT_PREFIX)
+                            .and_then(|i| i.parse().ok())
+                        {
+                            switch_account(app, index);
+                        }
+                    }
                 })
                 .on_tray_icon_event(|tray, event| {
                     if let tauri::tray::TrayIconEvent::Click { button, .. } = event {
@@ -231,10 +1303,27 @@ pub fn run() {
                 })
                 .build(app)?;
 
-            // macOS: show short loading text in menu bar
-            #[cfg(target_os = "macos")]
-            if let Some(tray) = app.tray_by_id("main-tray") {
-                let _ = tray.set_title(Some("$..."));
+            // macOS: show short loading text in menu bar, or the cached figures
+            let state = managed_state.lock().unwrap();
+            if state.last_data.is_some() {
+                update_tray(app.handle(), &state);
+            } else {
+                #[cfg(target_os = "macos")]
+                if let Some(tray) = app.tray_by_id("main-tray") {
+                    let _ = tray.set_title(Some("$..."));
+                }
+            }
+            drop(state);
+
+            // Optional Prometheus endpoint
+            if settings.metrics.enabled {
+                let handle = app.handle().clone();
+                let listen_addr = settings.metrics.listen_addr.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = metrics::serve(handle, listen_addr.clone()).await {
+                        tracing::error!(addr = %listen_addr, error = %e, "metrics endpoint failed");
+                    }
+                });
             }
 
             // Initial refresh
@@ -243,12 +1332,18 @@ pub fn run() {
                 do_refresh(&handle).await;
             });
 
-            // Periodic refresh every 60 seconds
+            // Periodic refresh every 60 seconds. While paused, only the menu is
+            // rebuilt so "Updated N min ago" keeps counting.
             let handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
                 loop {
                     tokio::time::sleep(std::time::Duration::from_secs(60)).await;
-                    do_refresh(&handle).await;
+                    let paused = handle.state::<Mutex<AppState>>().lock().unwrap().paused;
+                    if paused {
+                        rebuild_tray_menu(&handle);
+                    } else {
+                        do_refresh(&handle).await;
+                    }
                 }
             });
 
@@ -257,3 +1352,172 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+/// Handle command-line subcommands. Returns `Some(exit_code)` when a
+/// subcommand ran, or `None` to start the tray app as usual.
+///
+/// ```text
+/// cursor-status-bar export [--from YYYY-MM-DD] [--to YYYY-MM-DD]
+///                          [--format csv|jsonl|parquet] [--line-items]
+///                          [--account NAME] --out FILE
+/// cursor-status-bar import [--account NAME] FILE.csv...
+/// ```
+pub fn run_cli() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("export") => Some(match cli_export(&args[1..]) {
+            Ok(rows) => {
+                println!("Exported {} rows", rows);
+                0
+            }
+            Err(e) => {
+                eprintln!("export: {}", e);
+                1
+            }
+        }),
+        Some("import") => Some(cli_import(&args[1..])),
+        _ => None,
+    }
+}
+
+fn cli_export(args: &[String]) -> Result<usize, String> {
+    let settings = cli_settings()?;
+    let clock = settings.reporting_clock().map_err(|e| e.to_string())?;
+    let today = clock.day_of(chrono::Utc::now());
+    let mut from = (today - chrono::Duration::days(30)).format("%Y-%m-%d").to_string();
+    let mut to = today.format("%Y-%m-%d").to_string();
+    let mut format: Option<ExportFormat> = None;
+    let mut dataset = ExportDataset::Events;
+    let mut out: Option<PathBuf> = None;
+    let mut account_name: Option<String> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().cloned().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--from" => from = value()?,
+            "--to" => to = value()?,
+            "--format" => format = Some(value()?.parse().map_err(|e: export::ExportError| e.to_string())?),
+            "--out" => out = Some(PathBuf::from(value()?)),
+            "--line-items" => dataset = ExportDataset::LineItems,
+            "--account" => account_name = Some(value()?),
+            other => return Err(format!("unknown argument '{}'", other)),
+        }
+    }
+
+    let out = out.ok_or("--out FILE is required")?;
+    let format = format
+        .or_else(|| ExportFormat::from_path(&out))
+        .unwrap_or(ExportFormat::Csv);
+    let (start, end) = export::parse_date_range(&from, &to, &clock).map_err(|e| e.to_string())?;
+
+    let client = http::build_client(&settings.http).map_err(|e| e.to_string())?;
+    let info = cli_account_token(&settings, account_name.as_deref())?;
+    let account = info.user_id.clone();
+    let api = CursorApi::new(client, info.session_token, info.user_id);
+
+    let (mut events, drift) = tauri::async_runtime::block_on(export::fetch_range(&api, start, end))
+        .map_err(|e| e.to_string())?;
+    if let Some(warning) = drift.warning() {
+        eprintln!("Warning: {}", warning);
+    }
+    if let Ok(mut store) = EventStore::open_default() {
+        events = export::with_stored_history(events, &mut store, &account, start, end).map_err(|e| e.to_string())?;
+    }
+    settings.pricing_table().apply(&mut events);
+
+    export::write_dataset(&events, dataset, format, &out, &clock).map_err(|e| e.to_string())
+}
+
+/// Token for the account called `name` in settings, as in the tray's
+/// account switcher, or the default Cursor install's when `None`.
+fn cli_account_token(settings: &Settings, name: Option<&str>) -> Result<TokenInfo, String> {
+    let token = match name {
+        None | Some("Default") => token_extractor::extract_token(),
+        Some(name) => {
+            let account = settings
+                .accounts
+                .iter()
+                .find(|a| a.name == name)
+                .ok_or(format!("no account named '{}' in settings", name))?;
+            token_extractor::extract_token_from(&account.database_path)
+        }
+    };
+    token.map_err(|e| format!("Token error: {}", e))
+}
+
+/// Settings for CLI subcommands, read from the same place as the tray app's
+/// (`app_config_dir` is the platform config dir plus the app identifier).
+fn cli_settings() -> Result<Settings, String> {
+    match dirs::config_dir() {
+        Some(dir) => {
+            let (settings, problems) = Settings::load(&dir.join(APP_IDENTIFIER)).map_err(|e| e.to_string())?;
+            for e in problems {
+                eprintln!("Warning: {}", e);
+            }
+            Ok(settings)
+        }
+        None => Ok(Settings::default()),
+    }
+}
+
+fn cli_import(args: &[String]) -> i32 {
+    let mut account_name: Option<&str> = None;
+    let mut files = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--account" => match iter.next() {
+                Some(name) => account_name = Some(name),
+                None => {
+                    eprintln!("import: --account needs a value");
+                    return 1;
+                }
+            },
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        eprintln!("import: expected one or more CSV files");
+        return 1;
+    }
+    // Rows are imported as the named account, or the default Cursor install's
+    let account = match cli_settings().and_then(|settings| cli_account_token(&settings, account_name)) {
+        Ok(info) => info.user_id,
+        Err(e) => {
+            eprintln!("import: {}", e);
+            return 1;
+        }
+    };
+    let mut store = match EventStore::open_default() {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("import: {}", e);
+            return 1;
+        }
+    };
+
+    let mut code = 0;
+    for file in files {
+        match csv_import::import_csv(&PathBuf::from(file), &mut store, &account) {
+            Ok(report) => {
+                println!(
+                    "{}: {} rows, {} imported, {} duplicates, {} errors",
+                    file,
+                    report.rows,
+                    report.imported,
+                    report.duplicates,
+                    report.errors.len()
+                );
+                for err in &report.errors {
+                    eprintln!("  line {}: {}", err.line, err.message);
+                }
+            }
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                code = 1;
+            }
+        }
+    }
+    code
+}
@@ -0,0 +1,277 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MailError {
+    #[error("Cannot reach mail server {0}: {1}")]
+    Connect(String, std::io::Error),
+    #[error("Mail server rejected {0}: {1}")]
+    Rejected(&'static str, String),
+    #[error("Mail server connection failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("sendmail exited with {0}")]
+    Sendmail(std::process::ExitStatus),
+}
+
+pub struct Message {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    pub html: bool,
+}
+
+impl Message {
+    /// The message with headers and CRLF line endings. It is plain ASCII
+    /// whatever the subject and body hold, so it needs neither `8BITMIME`
+    /// nor `SMTPUTF8`: a non-ASCII subject becomes RFC 2047 encoded-words
+    /// and the body is quoted-printable.
+    fn encode(&self, date: DateTime<Utc>) -> String {
+        let content_type = if self.html { "text/html" } else { "text/plain" };
+        let mut out = format!(
+            "From: {}\r\nTo: {}\r\n{}\r\nDate: {}\r\nMIME-Version: 1.0\r\n\
+             Content-Type: {}; charset=utf-8\r\nContent-Transfer-Encoding: quoted-printable\r\n\r\n",
+            self.from,
+            self.to,
+            encode_header("Subject", &self.subject),
+            date.to_rfc2822(),
+            content_type
+        );
+        for line in self.body.lines() {
+            out.push_str(&quoted_printable(line));
+            out.push_str("\r\n");
+        }
+        out
+    }
+}
+
+/// Longest encoded-word RFC 2047 allows.
+const ENCODED_WORD_LEN: usize = 75;
+/// Longest quoted-printable line, not counting the CRLF.
+const QP_LINE_LEN: usize = 76;
+
+/// The header `name: text`, with `text` as-is when it is printable ASCII
+/// and otherwise as base64 encoded-words. Words are split between
+/// characters and folded onto new lines, each line within `ENCODED_WORD_LEN`.
+fn encode_header(name: &str, text: &str) -> String {
+    if text.bytes().all(|b| (b' '..=b'~').contains(&b)) {
+        return format!("{}: {}", name, text);
+    }
+    // Bytes of text that fit in a word on a line already `used` long
+    let fits = |used: usize| (ENCODED_WORD_LEN - used - "=?UTF-8?B??=".len()) / 4 * 3;
+    let mut out = format!("{}: ", name);
+    let mut max_chunk = fits(out.len());
+    let mut chunk = String::new();
+    for c in text.chars() {
+        if chunk.len() + c.len_utf8() > max_chunk {
+            out.push_str(&format!("=?UTF-8?B?{}?=\r\n ", STANDARD.encode(&chunk)));
+            max_chunk = fits(1);
+            chunk.clear();
+        }
+        chunk.push(c);
+    }
+    out.push_str(&format!("=?UTF-8?B?{}?=", STANDARD.encode(&chunk)));
+    out
+}
+
+/// One line of the body as quoted-printable, with soft line breaks so no
+/// line is longer than `QP_LINE_LEN`.
+fn quoted_printable(line: &str) -> String {
+    let bytes = line.as_bytes();
+    let mut out = String::new();
+    let mut width = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        let last = i + 1 == bytes.len();
+        let literal = match b {
+            b'=' => false,
+            // Trailing whitespace would be stripped in transit
+            b' ' | b'\t' => !last,
+            b'!'..=b'~' => true,
+            _ => false,
+        };
+        let encoded = if literal { (b as char).to_string() } else { format!("={:02X}", b) };
+        // Leave room for the soft break's `=`, unless this ends the line
+        let room = if last { QP_LINE_LEN } else { QP_LINE_LEN - 1 };
+        if width + encoded.len() > room {
+            out.push_str("=\r\n");
+            width = 0;
+        }
+        width += encoded.len();
+        out.push_str(&encoded);
+    }
+    out
+}
+
+/// Send through an SMTP server at `addr` (`host:port`), without TLS or
+/// authentication, so meant for a relay on the same machine or network.
+pub async fn send_smtp(addr: &str, message: &Message) -> Result<(), MailError> {
+    let stream = TcpStream::connect(addr)
+        .await
+        .map_err(|e| MailError::Connect(addr.to_string(), e))?;
+    let (read, mut write) = stream.into_split();
+    let mut read = BufReader::new(read);
+
+    expect(&mut read, "greeting", 220).await?;
+    for (step, command, code) in [
+        ("EHLO", "EHLO localhost".to_string(), 250),
+        ("sender", format!("MAIL FROM:<{}>", message.from), 250),
+        ("recipient", format!("RCPT TO:<{}>", message.to), 250),
+        ("DATA", "DATA".to_string(), 354),
+    ] {
+        write.write_all(format!("{}\r\n", command).as_bytes()).await?;
+        expect(&mut read, step, code).await?;
+    }
+    // Dot-stuffing, so no line of the message ends DATA early
+    let data = message.encode(Utc::now()).replace("\r\n.", "\r\n..");
+    write.write_all(data.as_bytes()).await?;
+    write.write_all(b".\r\n").await?;
+    expect(&mut read, "message", 250).await?;
+    write.write_all(b"QUIT\r\n").await?;
+    Ok(())
+}
+
+/// Read a (possibly multi-line) reply and check its code.
+async fn expect<R: AsyncBufReadExt + Unpin>(read: &mut R, step: &'static str, code: u16) -> Result<(), MailError> {
+    loop {
+        let mut line = String::new();
+        if read.read_line(&mut line).await? == 0 {
+            return Err(MailError::Rejected(step, "connection closed".to_string()));
+        }
+        let line = line.trim_end();
+        // "250-..." continues, "250 ..." ends the reply
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+        return match line.get(..3).and_then(|c| c.parse::<u16>().ok()) {
+            Some(got) if got == code => Ok(()),
+            _ => Err(MailError::Rejected(step, line.to_string())),
+        };
+    }
+}
+
+/// Hand the message to the local `sendmail`, which reads recipients from
+/// the headers.
+pub async fn send_sendmail(message: &Message) -> Result<(), MailError> {
+    let mut child = tokio::process::Command::new("sendmail")
+        .args(["-t", "-i"])
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(message.encode(Utc::now()).as_bytes()).await?;
+    }
+    let status = child.wait().await?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(MailError::Sendmail(status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// A mail drop that accepts one message and returns its DATA section.
+    async fn mail_drop(reject_recipient: bool) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let task = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"220 mail.test ESMTP\r\n").await.unwrap();
+            let mut data = String::new();
+            let mut in_data = false;
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let reply: &[u8] = if in_data {
+                    if line == "." {
+                        in_data = false;
+                        b"250 queued\r\n"
+                    } else {
+                        data.push_str(&line);
+                        data.push('\n');
+                        continue;
+                    }
+                } else if line.starts_with("EHLO") {
+                    b"250-mail.test\r\n250 8BITMIME\r\n"
+                } else if line.starts_with("RCPT") && reject_recipient {
+                    b"550 no such user\r\n"
+                } else if line == "DATA" {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line == "QUIT" {
+                    write.write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                write.write_all(reply).await.unwrap();
+            }
+            data
+        });
+        (addr, task)
+    }
+
+    fn message() -> Message {
+        Message {
+            from: "cursor@localhost".to_string(),
+            to: "team@example.com".to_string(),
+            subject: "Cursor daily digest".to_string(),
+            body: "Spend: $12.40\n.hidden line".to_string(),
+            html: false,
+        }
+    }
+
+    #[test]
+    fn encodes_non_ascii_as_seven_bit() {
+        let message = Message {
+            subject: "Cursor digest · 12 € spent, up 40% on last week’s figures for the team".to_string(),
+            body: format!("Top model: claude-4-sonnet — 9.40 €\n1+1=2\ntrailing \n{}", "x".repeat(100)),
+            ..message()
+        };
+        let encoded = message.encode(DateTime::UNIX_EPOCH);
+        assert!(encoded.is_ascii());
+        assert!(encoded.lines().all(|line| line.trim_end().len() <= QP_LINE_LEN));
+        assert!(encoded.contains("Content-Transfer-Encoding: quoted-printable\r\n"));
+
+        let subject = encoded.split("\r\nDate:").next().unwrap().split("Subject: ").nth(1).unwrap();
+        let words: Vec<&str> = subject.split("\r\n ").collect();
+        assert!(words.len() > 1);
+        let decoded: String = words
+            .iter()
+            .map(|w| {
+                let b64 = w.strip_prefix("=?UTF-8?B?").unwrap().strip_suffix("?=").unwrap();
+                String::from_utf8(STANDARD.decode(b64).unwrap()).unwrap()
+            })
+            .collect();
+        assert_eq!(decoded, message.subject);
+
+        assert!(encoded.contains("Top model: claude-4-sonnet =E2=80=94 9.40 =E2=82=AC\r\n"));
+        assert!(encoded.contains("1+1=3D2\r\n"));
+        assert!(encoded.contains("trailing=20\r\n"));
+        assert!(encoded.contains(&format!("{}=\r\n{}\r\n", "x".repeat(75), "x".repeat(25))));
+        assert_eq!(encode_header("Subject", "Cursor daily digest"), "Subject: Cursor daily digest");
+    }
+
+    #[tokio::test]
+    async fn delivers_to_an_smtp_mail_drop() {
+        let (addr, received) = mail_drop(false).await;
+        send_smtp(&addr, &message()).await.unwrap();
+        let data = received.await.unwrap();
+        assert!(data.contains("Subject: Cursor daily digest\n"));
+        assert!(data.contains("Content-Type: text/plain; charset=utf-8\n"));
+        assert!(data.ends_with("Spend: $12.40\n..hidden line\n"));
+    }
+
+    #[tokio::test]
+    async fn reports_the_step_the_server_rejected() {
+        let (addr, _received) = mail_drop(true).await;
+        let err = send_smtp(&addr, &message()).await.unwrap_err();
+        assert_eq!(err.to_string(), "Mail server rejected recipient: 550 no such user");
+    }
+}
@@ -0,0 +1,292 @@
+use crate::models::{LineItem, PeriodSummary, UsageDisplayData};
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Upper bounds (seconds) of the API latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 8] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// App health counters collected by `do_refresh`.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    last_success_unix: Option<i64>,
+    consecutive_failures: u32,
+    refreshes_total: u64,
+    failures_total: u64,
+    latency_bucket_counts: [u64; LATENCY_BUCKETS.len()],
+    latency_count: u64,
+    latency_sum: f64,
+}
+
+impl Metrics {
+    pub fn record_success(&mut self, latency: Duration) {
+        self.observe_latency(latency);
+        self.refreshes_total += 1;
+        self.consecutive_failures = 0;
+        self.last_success_unix = Some(chrono::Utc::now().timestamp());
+    }
+
+    /// `latency` is `None` when the refresh failed before reaching the API,
+    /// e.g. without a session token.
+    pub fn record_failure(&mut self, latency: Option<Duration>) {
+        if let Some(latency) = latency {
+            self.observe_latency(latency);
+        }
+        self.refreshes_total += 1;
+        self.failures_total += 1;
+        self.consecutive_failures += 1;
+    }
+
+    fn observe_latency(&mut self, latency: Duration) {
+        let secs = latency.as_secs_f64();
+        for (i, bound) in LATENCY_BUCKETS.iter().enumerate() {
+            if secs <= *bound {
+                self.latency_bucket_counts[i] += 1;
+            }
+        }
+        self.latency_count += 1;
+        self.latency_sum += secs;
+    }
+
+    /// Render health gauges plus usage figures in the Prometheus text format.
+    pub fn render(&self, data: Option<&UsageDisplayData>) -> String {
+        let mut out = String::new();
+
+        header(&mut out, "cursor_last_success_timestamp_seconds", "gauge", "Unix time of the last successful refresh.");
+        let _ = writeln!(out, "cursor_last_success_timestamp_seconds {}", self.last_success_unix.unwrap_or(0));
+
+        header(&mut out, "cursor_consecutive_failures", "gauge", "Refreshes failed in a row since the last success.");
+        let _ = writeln!(out, "cursor_consecutive_failures {}", self.consecutive_failures);
+
+        header(&mut out, "cursor_refreshes_total", "counter", "Refresh attempts since startup.");
+        let _ = writeln!(out, "cursor_refreshes_total {}", self.refreshes_total);
+
+        header(&mut out, "cursor_refresh_failures_total", "counter", "Failed refresh attempts since startup.");
+        let _ = writeln!(out, "cursor_refresh_failures_total {}", self.failures_total);
+
+        header(&mut out, "cursor_api_latency_seconds", "histogram", "Time taken to fetch usage data from the Cursor API.");
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(self.latency_bucket_counts.iter()) {
+            let _ = writeln!(out, "cursor_api_latency_seconds_bucket{{le=\"{}\"}} {}", bound, count);
+        }
+        let _ = writeln!(out, "cursor_api_latency_seconds_bucket{{le=\"+Inf\"}} {}", self.latency_count);
+        let _ = writeln!(out, "cursor_api_latency_seconds_sum {}", self.latency_sum);
+        let _ = writeln!(out, "cursor_api_latency_seconds_count {}", self.latency_count);
+
+        if let Some(data) = data {
+            render_usage(&mut out, data);
+        }
+
+        out
+    }
+}
+
+fn render_usage(out: &mut String, data: &UsageDisplayData) {
+    let billing = PeriodSummary {
+        label: "Billing Period".to_string(),
+        requests: data.total_requests,
+        spend_dollars: data.total_spend_dollars,
+        tokens: data.total_tokens,
+        comparison: data.period_comparison.clone(),
+    };
+    let periods = [
+        ("today", &data.today),
+        ("last_7_days", &data.last7_days),
+        ("last_30_days", &data.last30_days),
+        ("billing_period", &billing),
+    ];
+
+    header(out, "cursor_period_spend_dollars", "gauge", "Spend in dollars per reporting period.");
+    for (name, p) in &periods {
+        let _ = writeln!(out, "cursor_period_spend_dollars{{period=\"{}\"}} {}", name, p.spend_dollars.as_dollars_f64());
+    }
+    header(out, "cursor_period_requests", "gauge", "Requests per reporting period.");
+    for (name, p) in &periods {
+        let _ = writeln!(out, "cursor_period_requests{{period=\"{}\"}} {}", name, p.requests);
+    }
+    header(out, "cursor_period_tokens", "gauge", "Tokens per reporting period.");
+    for (name, p) in &periods {
+        let _ = writeln!(out, "cursor_period_tokens{{period=\"{}\"}} {}", name, p.tokens);
+    }
+
+    // One series per line item: per model, or per model group when grouped
+    let labels = |item: &LineItem| {
+        format!(
+            "group=\"{}\",model=\"{}\",display_name=\"{}\"",
+            escape_label(&item.group),
+            escape_label(&item.model_name),
+            escape_label(&item.display_name)
+        )
+    };
+    header(out, "cursor_model_spend_dollars", "gauge", "Billing period spend in dollars per model.");
+    for item in &data.line_items {
+        let _ = writeln!(out, "cursor_model_spend_dollars{{{}}} {}", labels(item), item.cost_dollars.as_dollars_f64());
+    }
+    header(out, "cursor_model_requests", "gauge", "Billing period requests per model.");
+    for item in &data.line_items {
+        let _ = writeln!(out, "cursor_model_requests{{{}}} {}", labels(item), item.request_count);
+    }
+    header(out, "cursor_model_tokens", "gauge", "Billing period tokens per model.");
+    for item in &data.line_items {
+        let _ = writeln!(out, "cursor_model_tokens{{{}}} {}", labels(item), item.total_tokens);
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Escape a label value per the Prometheus text exposition format.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Serve `GET /metrics` on the given address until the app exits.
+/// Only a minimal subset of HTTP/1.1 is handled — enough for a Prometheus scraper.
+pub async fn serve(app: AppHandle, listen_addr: String) -> std::io::Result<()> {
+    let listener = TcpListener::bind(&listen_addr).await?;
+
+    loop {
+        let (mut socket, _) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                // One failed connection, e.g. when out of file descriptors,
+                // should not stop the endpoint; pause so it does not spin
+                tracing::warn!(error = %e, "cannot accept metrics connection");
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let app = app.clone();
+
+        tauri::async_runtime::spawn(async move {
+            let mut buf = [0u8; 1024];
+            let n = match socket.read(&mut buf).await {
+                Ok(n) => n,
+                Err(_) => return,
+            };
+            let request = String::from_utf8_lossy(&buf[..n]);
+            let path = request.split_whitespace().nth(1).unwrap_or("");
+
+            let response = if request.starts_with("GET ") && path == "/metrics" {
+                let body = {
+                    let state = app.state::<Mutex<crate::AppState>>();
+                    let s = state.lock().unwrap();
+                    s.metrics.render(s.last_data.as_ref())
+                };
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+            };
+
+            let _ = socket.write_all(response.as_bytes()).await;
+            let _ = socket.shutdown().await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor_api::aggregate;
+    use crate::models::UsageEvent;
+    use chrono::{TimeZone, Utc};
+
+    /// The value of `series` in the rendered output.
+    fn sample(out: &str, series: &str) -> f64 {
+        out.lines()
+            .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+            .unwrap_or_else(|| panic!("no {} in\n{}", series, out))
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn latency_buckets_are_cumulative() {
+        let mut metrics = Metrics::default();
+        metrics.record_success(Duration::from_millis(50));
+        metrics.record_success(Duration::from_millis(300));
+        metrics.record_failure(Some(Duration::from_secs(3)));
+        metrics.record_failure(Some(Duration::from_secs(60)));
+        metrics.record_failure(None);
+        let out = metrics.render(None);
+
+        let bucket = |le: &str| sample(&out, &format!("cursor_api_latency_seconds_bucket{{le=\"{}\"}}", le));
+        assert_eq!(bucket("0.1"), 1.0);
+        assert_eq!(bucket("0.25"), 1.0);
+        assert_eq!(bucket("0.5"), 2.0);
+        assert_eq!(bucket("2.5"), 2.0);
+        assert_eq!(bucket("5"), 3.0);
+        assert_eq!(bucket("30"), 3.0);
+        // +Inf also counts the request slower than every bound
+        assert_eq!(bucket("+Inf"), 4.0);
+        assert_eq!(sample(&out, "cursor_api_latency_seconds_count"), 4.0);
+        assert!((sample(&out, "cursor_api_latency_seconds_sum") - 63.35).abs() < 1e-9);
+        let buckets: Vec<f64> = out
+            .lines()
+            .filter(|line| line.starts_with("cursor_api_latency_seconds_bucket"))
+            .map(|line| line.rsplit(' ').next().unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(buckets.len(), LATENCY_BUCKETS.len() + 1);
+        assert!(buckets.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        // A failure without an API call still counts as a failed refresh
+        assert_eq!(sample(&out, "cursor_refreshes_total"), 5.0);
+        assert_eq!(sample(&out, "cursor_refresh_failures_total"), 3.0);
+        assert_eq!(sample(&out, "cursor_consecutive_failures"), 3.0);
+        assert!(!out.contains("cursor_period_spend_dollars"));
+    }
+
+    #[test]
+    fn renders_period_and_model_series() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 15, 12, 0, 0).unwrap();
+        let events = [
+            UsageEvent::test("claude-4-sonnet", now - chrono::Duration::minutes(5)).tokens(1000, 500, 0, 0).cents(150.0),
+            UsageEvent::test("gpt-5", now - chrono::Duration::days(10)).tokens(100, 50, 0, 0).cents(50.0),
+        ];
+        let start = Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap();
+        let data = aggregate(start, &events, now, &Default::default(), &Default::default());
+        let out = Metrics::default().render(Some(&data));
+
+        let period = |name: &str| sample(&out, &format!("cursor_period_spend_dollars{{period=\"{}\"}}", name));
+        assert_eq!(period("today"), 1.5);
+        assert_eq!(period("last_7_days"), 1.5);
+        assert_eq!(period("last_30_days"), 2.0);
+        assert_eq!(period("billing_period"), 2.0);
+        assert_eq!(sample(&out, "cursor_period_requests{period=\"billing_period\"}"), 2.0);
+        assert_eq!(sample(&out, "cursor_period_tokens{period=\"billing_period\"}"), 1650.0);
+
+        for (model, spend, tokens) in [("claude-4-sonnet", 1.5, 1500.0), ("gpt-5", 0.5, 150.0)] {
+            let labels = out
+                .lines()
+                .filter_map(|line| line.strip_prefix("cursor_model_spend_dollars{")?.split_once("} "))
+                .map(|(labels, _)| labels.to_string())
+                .find(|labels| labels.contains(&format!("model=\"{}\"", model)))
+                .unwrap_or_else(|| panic!("no spend series for {} in\n{}", model, out));
+            assert!(labels.starts_with(&format!("group=\"{}\"", model)), "{}", labels);
+            assert!(labels.contains("display_name=\""), "{}", labels);
+            assert_eq!(sample(&out, &format!("cursor_model_spend_dollars{{{}}}", labels)), spend);
+            assert_eq!(sample(&out, &format!("cursor_model_requests{{{}}}", labels)), 1.0);
+            assert_eq!(sample(&out, &format!("cursor_model_tokens{{{}}}", labels)), tokens);
+        }
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(escape_label("plain-model"), "plain-model");
+        assert_eq!(
+            escape_label("say \"hi\"\\n\nnext"),
+            "say \\\"hi\\\"\\\\n\\nnext"
+        );
+    }
+}
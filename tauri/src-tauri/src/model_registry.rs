@@ -0,0 +1,317 @@
+use crate::cursor_api::line_items_by;
+use crate::models::{LineItem, UsageEvent};
+use crate::settings::ModelSettings;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Reasoning effort levels some models are offered at.
+const EFFORTS: &[&str] = &["minimal", "low", "medium", "high"];
+
+/// Release tags that do not make a different model.
+const RELEASE_TAGS: &[&str] = &["preview", "latest", "exp", "experimental"];
+
+const CLAUDE_TIERS: &[&str] = &["opus", "sonnet", "haiku"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Vendor {
+    Anthropic,
+    OpenAi,
+    Google,
+    XAi,
+    Cursor,
+    Other,
+}
+
+impl Vendor {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Vendor::Anthropic => "anthropic",
+            Vendor::OpenAi => "openai",
+            Vendor::Google => "google",
+            Vendor::XAi => "xai",
+            Vendor::Cursor => "cursor",
+            Vendor::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for Vendor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Vendor::Anthropic => "Anthropic",
+            Vendor::OpenAi => "OpenAI",
+            Vendor::Google => "Google",
+            Vendor::XAi => "xAI",
+            Vendor::Cursor => "Cursor",
+            Vendor::Other => "Other",
+        })
+    }
+}
+
+/// What line items are grouped by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ModelGrouping {
+    /// Model names exactly as Cursor reports them.
+    Exact,
+    /// Canonical model, with modes and release tags merged.
+    #[default]
+    Model,
+    /// e.g. all Claude Sonnet versions together.
+    Family,
+    Vendor,
+}
+
+/// A reported model name, taken apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelInfo {
+    /// e.g. `claude-4.5-sonnet`.
+    pub canonical: String,
+    pub vendor: Vendor,
+    /// e.g. `claude-sonnet`, `gpt-5`, `gemini-flash`.
+    pub family: String,
+    pub thinking: bool,
+    pub max_mode: bool,
+    /// Reasoning effort, e.g. `high`.
+    pub effort: Option<String>,
+}
+
+impl ModelInfo {
+    /// e.g. "Claude 4 Sonnet (high, thinking, MAX)".
+    pub fn display_name(&self) -> String {
+        let mut modes: Vec<&str> = self.effort.iter().map(String::as_str).collect();
+        if self.thinking {
+            modes.push("thinking");
+        }
+        if self.max_mode {
+            modes.push("MAX");
+        }
+        let name = pretty(&self.canonical);
+        if modes.is_empty() {
+            name
+        } else {
+            format!("{} ({})", name, modes.join(", "))
+        }
+    }
+}
+
+/// Reduces reported model names such as `claude-4-sonnet-high-thinking` to
+/// one canonical name per model, with the aliases and grouping from settings.
+#[derive(Debug, Clone, Default)]
+pub struct ModelRegistry {
+    /// Lower-cased reported name to the name to use instead.
+    aliases: HashMap<String, String>,
+    grouping: ModelGrouping,
+}
+
+impl ModelRegistry {
+    pub fn new(settings: &ModelSettings) -> Self {
+        Self {
+            aliases: settings
+                .aliases
+                .iter()
+                .map(|(from, to)| (normalise(from), to.clone()))
+                .collect(),
+            grouping: settings.grouping,
+        }
+    }
+
+    pub fn info(&self, model: &str) -> ModelInfo {
+        let normalised = normalise(model);
+        let name = self.aliases.get(&normalised).map(|to| normalise(to)).unwrap_or(normalised);
+        parse(&name)
+    }
+
+    /// Key and display name of the group `model` falls in.
+    pub fn group(&self, model: &str) -> (String, String) {
+        let info = self.info(model);
+        match self.grouping {
+            ModelGrouping::Exact => (model.to_string(), info.display_name()),
+            ModelGrouping::Model => {
+                let display = pretty(&info.canonical);
+                (info.canonical, display)
+            }
+            ModelGrouping::Family => {
+                let display = pretty(&info.family);
+                (info.family, display)
+            }
+            ModelGrouping::Vendor => (info.vendor.as_str().to_string(), info.vendor.to_string()),
+        }
+    }
+
+    /// Line items for `events`, one per group.
+    pub fn line_items<'a>(&self, events: impl IntoIterator<Item = &'a UsageEvent>) -> Vec<LineItem> {
+        line_items_by(events, |model| self.group(model))
+    }
+}
+
+fn normalise(model: &str) -> String {
+    model.trim().to_lowercase().replace(['_', ' '], "-")
+}
+
+fn is_version(token: &str) -> bool {
+    !token.is_empty() && token.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+fn parse(name: &str) -> ModelInfo {
+    let mut thinking = false;
+    let mut max_mode = false;
+    let mut effort = None;
+    let mut tokens: Vec<&str> = Vec::new();
+    // Dates follow a release tag or a year: `preview-05-06`, `2025-04-14`
+    let mut in_date = false;
+    let digits = |token: &str, len: usize| token.len() == len && token.chars().all(|c| c.is_ascii_digit());
+    for token in name.split('-').filter(|t| !t.is_empty()) {
+        if digits(token, 8) || (digits(token, 4) && token.starts_with("20")) || RELEASE_TAGS.contains(&token) {
+            in_date = true;
+            continue;
+        }
+        if in_date && digits(token, 2) {
+            continue;
+        }
+        in_date = false;
+        match token {
+            "thinking" => thinking = true,
+            "max" => max_mode = true,
+            t if EFFORTS.contains(&t) => effort = Some(t.to_string()),
+            t => tokens.push(t),
+        }
+    }
+    if tokens.is_empty() {
+        tokens.push("unknown");
+    }
+
+    let first = tokens[0];
+    let (canonical, vendor, family) = if first == "claude" {
+        // `claude-sonnet-4-5` and `claude-3-5-sonnet` become `claude-4.5-sonnet`, `claude-3.5-sonnet`
+        let tier = tokens.iter().copied().find(|t| CLAUDE_TIERS.contains(t));
+        let version: Vec<&str> = tokens.iter().copied().filter(|t| is_version(t)).collect();
+        let rest = tokens[1..].iter().copied().filter(|t| !is_version(t) && Some(*t) != tier);
+        let mut parts = vec!["claude".to_string()];
+        if !version.is_empty() {
+            parts.push(version.join("."));
+        }
+        parts.extend(tier.map(str::to_string));
+        parts.extend(rest.map(str::to_string));
+        let family = tier.map_or("claude".to_string(), |t| format!("claude-{}", t));
+        (parts.join("-"), Vendor::Anthropic, family)
+    } else {
+        let canonical = tokens.join("-");
+        let is_o_series = first.len() > 1 && first.starts_with('o') && first[1..].chars().all(|c| c.is_ascii_digit());
+        let (vendor, family) = match first {
+            "gpt" => {
+                let major: String = tokens
+                    .get(1)
+                    .map(|t| t.chars().take_while(|c| c.is_ascii_digit()).collect())
+                    .unwrap_or_default();
+                (Vendor::OpenAi, if major.is_empty() { "gpt".to_string() } else { format!("gpt-{}", major) })
+            }
+            _ if is_o_series => (Vendor::OpenAi, "o-series".to_string()),
+            "codex" => (Vendor::OpenAi, "codex".to_string()),
+            "gemini" => {
+                let tier = tokens.iter().copied().find(|t| matches!(*t, "pro" | "flash"));
+                (Vendor::Google, tier.map_or("gemini".to_string(), |t| format!("gemini-{}", t)))
+            }
+            "grok" => (Vendor::XAi, "grok".to_string()),
+            "cursor" | "auto" | "default" | "composer" => (Vendor::Cursor, "cursor".to_string()),
+            other => (Vendor::Other, other.to_string()),
+        };
+        (canonical, vendor, family)
+    };
+
+    ModelInfo {
+        canonical,
+        vendor,
+        family,
+        thinking,
+        max_mode,
+        effort,
+    }
+}
+
+/// "claude-4.5-sonnet" → "Claude 4.5 Sonnet", "gpt-5-mini" → "GPT-5 Mini".
+fn pretty(name: &str) -> String {
+    if name == "o-series" {
+        return name.to_string();
+    }
+    let mut words: Vec<String> = Vec::new();
+    let mut tokens = name.split('-').peekable();
+    while let Some(token) = tokens.next() {
+        if token == "gpt" {
+            match tokens.next() {
+                Some(version) => words.push(format!("GPT-{}", version)),
+                None => words.push("GPT".to_string()),
+            }
+        } else if token.chars().all(|c| c.is_ascii_alphabetic()) {
+            let mut chars = token.chars();
+            words.extend(chars.next().map(|c| c.to_ascii_uppercase().to_string() + chars.as_str()));
+        } else {
+            words.push(token.to_string());
+        }
+    }
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry(grouping: ModelGrouping) -> ModelRegistry {
+        ModelRegistry::new(&ModelSettings {
+            grouping,
+            aliases: HashMap::from([("Default".to_string(), "claude-4-sonnet".to_string())]),
+        })
+    }
+
+    #[test]
+    fn canonicalises_modes_tags_and_claude_naming() {
+        let models = registry(ModelGrouping::Model);
+        let info = models.info("claude-4-sonnet-high-thinking");
+        assert_eq!(info.canonical, "claude-4-sonnet");
+        assert_eq!(info.family, "claude-sonnet");
+        assert_eq!(info.vendor, Vendor::Anthropic);
+        assert!(info.thinking && !info.max_mode);
+        assert_eq!(info.display_name(), "Claude 4 Sonnet (high, thinking)");
+
+        for (raw, canonical) in [
+            ("claude-sonnet-4-5", "claude-4.5-sonnet"),
+            ("claude-3-5-sonnet-20241022", "claude-3.5-sonnet"),
+            ("claude-opus-4-1-max", "claude-4.1-opus"),
+            ("gpt-5-preview", "gpt-5"),
+            ("gpt-4.1-2025-04-14", "gpt-4.1"),
+            ("gemini-2.5-pro-preview-05-06", "gemini-2.5-pro"),
+            ("o4-mini-high", "o4-mini"),
+            ("default", "claude-4-sonnet"),
+        ] {
+            assert_eq!(models.info(raw).canonical, canonical, "{}", raw);
+        }
+        assert_eq!(models.info("o3-pro").family, "o-series");
+        assert_eq!(models.info("gpt-4o").family, "gpt-4");
+        assert_eq!(models.info("gemini-2.5-flash").family, "gemini-flash");
+        assert_eq!(models.info("deepseek-v3.1").vendor, Vendor::Other);
+    }
+
+    #[test]
+    fn groups_by_each_level() {
+        let model = "claude-sonnet-4-5-thinking";
+        assert_eq!(
+            registry(ModelGrouping::Exact).group(model),
+            (model.to_string(), "Claude 4.5 Sonnet (thinking)".to_string())
+        );
+        assert_eq!(
+            registry(ModelGrouping::Model).group(model),
+            ("claude-4.5-sonnet".to_string(), "Claude 4.5 Sonnet".to_string())
+        );
+        assert_eq!(
+            registry(ModelGrouping::Family).group(model),
+            ("claude-sonnet".to_string(), "Claude Sonnet".to_string())
+        );
+        assert_eq!(
+            registry(ModelGrouping::Vendor).group("gpt-5-mini"),
+            ("openai".to_string(), "OpenAI".to_string())
+        );
+        assert_eq!(registry(ModelGrouping::Model).group("gpt-5-mini").1, "GPT-5 Mini");
+    }
+}
@@ -1,35 +1,50 @@
+use crate::money::Money;
+use crate::reporting::ReportingClock;
+use crate::schema;
+use chrono::{DateTime, Months, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 
 // ── API Response Models ──
 
 /// Response from POST https://cursor.com/api/dashboard/get-filtered-usage-events
+/// Events are kept raw and parsed one by one in `schema::parse_events_page`.
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UsageEventsResponse {
-    pub usage_events_display: Option<Vec<UsageEvent>>,
+    pub total_usage_events_count: Option<i64>,
+    pub usage_events_display: Option<Vec<serde_json::Value>>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UsageEvent {
+    #[serde(deserialize_with = "schema::string_or_number")]
     pub timestamp: String,
     pub model: Option<String>,
     pub kind: Option<String>,
+    #[serde(default, deserialize_with = "schema::opt_string_or_number")]
     pub usage_based_costs: Option<String>,
     pub is_token_based_call: Option<bool>,
     pub token_usage: Option<TokenUsage>,
     pub is_chargeable: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TokenUsage {
     pub input_tokens: Option<i64>,
     pub output_tokens: Option<i64>,
     pub cache_write_tokens: Option<i64>,
     pub cache_read_tokens: Option<i64>,
+    #[serde(default, deserialize_with = "schema::opt_number_or_string")]
     pub total_cents: Option<f64>,
+    /// Cost at list price from `PricingTable::apply`; not sent by the API.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_cents: Option<f64>,
 }
 
 impl TokenUsage {
@@ -42,20 +57,90 @@ impl TokenUsage {
 }
 
 impl UsageEvent {
-    pub fn cost_cents(&self) -> f64 {
-        self.token_usage
-            .as_ref()
-            .and_then(|t| t.total_cents)
-            .unwrap_or(0.0)
+    /// What the event cost: the reported charge, or the list-price estimate
+    /// when there is none. Missing, NaN and negative costs count as zero.
+    pub fn cost(&self) -> Money {
+        self.reported_cost()
+            .or_else(|| self.estimated_cost())
+            .unwrap_or(Money::ZERO)
+    }
+
+    /// What Cursor charged, if it said.
+    pub fn reported_cost(&self) -> Option<Money> {
+        self.token_usage.as_ref()?.total_cents.and_then(Money::from_cents)
+    }
+
+    pub fn estimated_cost(&self) -> Option<Money> {
+        self.token_usage.as_ref()?.estimated_cents.and_then(Money::from_cents)
     }
 
-    pub fn cost_dollars(&self) -> f64 {
-        self.cost_cents() / 100.0
+    pub fn request_kind(&self) -> RequestKind {
+        self.kind.as_deref().map(RequestKind::parse).unwrap_or_default()
+    }
+
+    /// True when `cost` is an estimate rather than a reported charge.
+    pub fn is_estimated(&self) -> bool {
+        self.reported_cost().is_none() && self.estimated_cost().is_some()
+    }
+
+    /// False for events Cursor does not bill beyond the plan: those marked
+    /// not chargeable, included in the plan, free or not charged because
+    /// they failed. Only usage-based events count against the spending limit.
+    pub fn is_usage_based(&self) -> bool {
+        if self.is_chargeable == Some(false) {
+            return false;
+        }
+        let kind = self.kind.as_deref().unwrap_or_default().to_uppercase();
+        !["INCLUDED", "NOT_CHARGED", "FREE"].iter().any(|k| kind.contains(k))
+    }
+
+    /// Event time; `timestamp` is milliseconds since epoch as a string.
+    pub fn time(&self) -> DateTime<Utc> {
+        let timestamp_ms: f64 = self.timestamp.parse().unwrap_or(0.0);
+        DateTime::from_timestamp_millis(timestamp_ms as i64).unwrap_or(DateTime::UNIX_EPOCH)
+    }
+}
+
+#[cfg(test)]
+impl UsageEvent {
+    /// A chargeable, token-based event for tests, with no tokens or cost
+    /// until `tokens` and `cents` add them.
+    pub fn test(model: &str, at: DateTime<Utc>) -> Self {
+        UsageEvent {
+            timestamp: at.timestamp_millis().to_string(),
+            model: Some(model.to_string()),
+            kind: None,
+            usage_based_costs: None,
+            is_token_based_call: Some(true),
+            token_usage: Some(TokenUsage {
+                input_tokens: None,
+                output_tokens: None,
+                cache_write_tokens: None,
+                cache_read_tokens: None,
+                total_cents: None,
+                estimated_cents: None,
+            }),
+            is_chargeable: Some(true),
+        }
+    }
+
+    pub fn tokens(mut self, input: i64, output: i64, cache_write: i64, cache_read: i64) -> Self {
+        let usage = self.token_usage.as_mut().expect("test events have token usage");
+        usage.input_tokens = Some(input);
+        usage.output_tokens = Some(output);
+        usage.cache_write_tokens = Some(cache_write);
+        usage.cache_read_tokens = Some(cache_read);
+        self
+    }
+
+    pub fn cents(mut self, cents: f64) -> Self {
+        self.token_usage.as_mut().expect("test events have token usage").total_cents = Some(cents);
+        self
     }
 }
 
 /// Response from GET https://cursor.com/api/usage?user={userId}
-/// Uses dynamic keys — we only care about startOfMonth.
+/// Uses dynamic keys: `startOfMonth` plus one `LegacyModelUsage` per model.
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LegacyUsageResponse {
@@ -69,37 +154,439 @@ impl LegacyUsageResponse {
             .get("startOfMonth")
             .and_then(|v| v.as_str())
     }
+
+    /// Per-model counters, by model name. Entries that are not in the
+    /// expected shape are left out.
+    pub fn models(&self) -> Vec<(String, LegacyModelUsage)> {
+        let mut models: Vec<_> = self
+            .extra
+            .iter()
+            .filter(|(_, v)| v.is_object())
+            .filter_map(|(name, v)| Some((name.clone(), serde_json::from_value(v.clone()).ok()?)))
+            .collect();
+        models.sort_by(|a, b| a.0.cmp(&b.0));
+        models
+    }
 }
 
-// ── Display Models (sent to frontend) ──
+/// Request counts for one model in the legacy usage response.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyModelUsage {
+    /// Requests counted against the quota this billing period.
+    #[serde(default)]
+    pub num_requests: i64,
+    /// All requests, including ones beyond the quota.
+    pub num_requests_total: Option<i64>,
+    pub num_tokens: Option<i64>,
+    /// Requests included in the plan; `null` for models without a quota.
+    pub max_request_usage: Option<i64>,
+    pub max_token_usage: Option<i64>,
+}
 
-#[derive(Debug, Clone, Serialize)]
+/// Legacy name for the bucket that premium model requests count against.
+const PREMIUM_QUOTA_KEY: &str = "gpt-4";
+
+/// Included requests for one quota, e.g. 412 of 500 premium requests.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestQuota {
+    /// Model key in the legacy response.
+    pub model: String,
+    /// "premium" for the main quota, otherwise the model key.
+    pub label: String,
+    pub used: i64,
+    pub limit: i64,
+    /// When the quota runs out at the average rate so far this billing
+    /// period, and usage-based pricing starts. `None` when already used up
+    /// or nothing has been used yet.
+    pub exhausted_at: Option<DateTime<Utc>>,
+}
+
+impl RequestQuota {
+    /// `None` for models without a quota.
+    pub fn new(model: &str, usage: &LegacyModelUsage, billing_start: DateTime<Utc>, now: DateTime<Utc>) -> Option<Self> {
+        let limit = usage.max_request_usage.filter(|l| *l > 0)?;
+        let used = usage.num_requests.max(0);
+        let elapsed = (now - billing_start).num_seconds() as f64;
+        let exhausted_at = if used == 0 || used >= limit || elapsed <= 0.0 {
+            None
+        } else {
+            let seconds = (limit - used) as f64 * elapsed / used as f64;
+            now.checked_add_signed(chrono::Duration::seconds(seconds as i64))
+        };
+        Some(Self {
+            model: model.to_string(),
+            label: if model == PREMIUM_QUOTA_KEY { "premium" } else { model }.to_string(),
+            used,
+            limit,
+            exhausted_at,
+        })
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.used >= self.limit
+    }
+
+    /// e.g. "412 / 500 premium requests used".
+    pub fn describe(&self) -> String {
+        format!("{} / {} {} requests used", self.used, self.limit, self.label)
+    }
+
+    /// When usage-based charges start, or that they will not before
+    /// `period_end`, with times shown in `clock`'s timezone.
+    pub fn outlook(&self, period_end: Option<DateTime<Utc>>, clock: &ReportingClock) -> String {
+        match self.exhausted_at {
+            _ if self.is_exhausted() => "Quota used up; usage-based pricing applies".to_string(),
+            Some(at) if period_end.is_none_or(|end| at < end) => format!(
+                "Runs out around {} at the current rate; usage-based after that",
+                clock.format_wall_clock(at, "%b %-d %H:%M")
+            ),
+            Some(_) => "Lasts the billing period at the current rate".to_string(),
+            None => "No requests used yet".to_string(),
+        }
+    }
+}
+
+/// Response from POST https://cursor.com/api/dashboard/get-hard-limit: the
+/// usage-based spending limit set in the Cursor dashboard.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HardLimitResponse {
+    /// Dollars; absent when no limit is set.
+    pub hard_limit: Option<f64>,
+    /// True when usage-based pricing is turned off altogether.
+    #[serde(default)]
+    pub no_usage_based_allowed: bool,
+}
+
+impl HardLimitResponse {
+    /// The limit, ignoring zero, negative or non-finite values and accounts
+    /// without usage-based pricing.
+    pub fn spend_limit(&self) -> Option<Money> {
+        if self.no_usage_based_allowed {
+            return None;
+        }
+        self.hard_limit
+            .and_then(Money::from_dollars)
+            .filter(|l| *l > Money::ZERO)
+    }
+}
+
+// ── Display Models (sent to frontend, cached on disk) ──
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UsageDisplayData {
     pub total_requests: i32,
-    pub total_spend_dollars: f64,
+    pub total_spend_dollars: Money,
     pub total_tokens: i64,
     pub line_items: Vec<LineItem>,
     pub billing_period_start: String,
     pub today: PeriodSummary,
     pub last7_days: PeriodSummary,
     pub last30_days: PeriodSummary,
+    /// User-defined periods from settings, in settings order.
+    #[serde(default)]
+    pub custom_periods: Vec<PeriodSummary>,
+    /// Billing period so far against the same stretch of the previous one.
+    #[serde(default)]
+    pub period_comparison: Option<PeriodComparison>,
+    /// Billing period split by request kind, most spend first.
+    #[serde(default)]
+    pub kinds: Vec<KindSummary>,
+    /// Included request quotas from the legacy endpoint.
+    #[serde(default)]
+    pub quotas: Vec<RequestQuota>,
+    /// Usage-based spending limit from the Cursor dashboard, past which
+    /// Cursor stops serving requests.
+    #[serde(default)]
+    pub spend_limit_dollars: Option<Money>,
+    /// Reported charges this billing period for usage beyond the plan, which
+    /// is what the spending limit caps. Leaves out included requests and
+    /// list-price estimates.
+    #[serde(default)]
+    pub usage_based_spend_dollars: Money,
 }
 
-#[derive(Debug, Clone, Serialize)]
+impl UsageDisplayData {
+    pub fn billing_period_start_utc(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.billing_period_start)
+            .ok()
+            .map(|d| d.with_timezone(&Utc))
+    }
+
+    /// Billing periods run for one calendar month from their start.
+    pub fn billing_period_end_utc(&self) -> Option<DateTime<Utc>> {
+        self.billing_period_start_utc()
+            .and_then(|start| start.checked_add_months(Months::new(1)))
+    }
+
+    /// The premium request quota, or failing that the first one.
+    pub fn main_quota(&self) -> Option<&RequestQuota> {
+        self.quotas
+            .iter()
+            .find(|q| q.model == PREMIUM_QUOTA_KEY)
+            .or_else(|| self.quotas.first())
+    }
+
+    /// What can still be spent this billing period before Cursor cuts off
+    /// requests, if the account has a spending limit.
+    pub fn spend_limit_headroom(&self) -> Option<Money> {
+        self.spend_limit_dollars
+            .map(|limit| limit.saturating_sub(self.usage_based_spend_dollars))
+    }
+
+    /// Linear projection of spend at the end of the billing period, based on
+    /// the average rate so far. `None` in the first hour, when it is just noise.
+    pub fn forecast_period_spend(&self, now: DateTime<Utc>) -> Option<Money> {
+        let start = self.billing_period_start_utc()?;
+        let end = self.billing_period_end_utc()?;
+        let elapsed = (now - start).num_seconds() as f64;
+        if elapsed < 3600.0 {
+            return None;
+        }
+        let length = (end - start).num_seconds() as f64;
+        Some(self.total_spend_dollars.scale((length / elapsed).max(1.0)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PeriodSummary {
     pub label: String,
     pub requests: i32,
-    pub spend_dollars: f64,
+    pub spend_dollars: Money,
     pub tokens: i64,
+    /// Against the equivalent previous window, for the built-in periods.
+    #[serde(default)]
+    pub comparison: Option<PeriodComparison>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// Changes smaller than this share of the previous spend count as flat.
+const FLAT_CHANGE: f64 = 0.05;
+
+/// A period compared with the same length of time one period earlier, so a
+/// partial today is measured against yesterday up to the same hour.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodComparison {
+    /// What the previous window is called, e.g. "yesterday".
+    pub label: String,
+    pub previous_spend_dollars: Money,
+    pub previous_requests: i32,
+    /// Current minus previous spend; negative when spend fell.
+    pub delta_dollars: Money,
+    /// `delta / previous`, or `None` when there was no previous spend.
+    pub delta_fraction: Option<f64>,
+    pub trend: Trend,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Trend {
+    Up,
+    Down,
+    Flat,
+}
+
+impl PeriodComparison {
+    pub fn new(label: &str, current: Money, previous: Money, previous_requests: i32) -> Self {
+        let delta = current - previous;
+        let delta_fraction = delta.ratio(previous);
+        let trend = match delta_fraction {
+            Some(f) if f.abs() < FLAT_CHANGE => Trend::Flat,
+            _ if delta > Money::ZERO => Trend::Up,
+            _ if delta < Money::ZERO => Trend::Down,
+            _ => Trend::Flat,
+        };
+        Self {
+            label: label.to_string(),
+            previous_spend_dollars: previous,
+            previous_requests,
+            delta_dollars: delta,
+            delta_fraction,
+            trend,
+        }
+    }
+
+    /// "+35% vs yesterday", or "+$4.20 vs yesterday" when there is no
+    /// previous spend to take a percentage of.
+    pub fn describe(&self) -> String {
+        format!("{} vs {}", self.change(), self.label)
+    }
+
+    /// "+35%", falling back to the signed dollar delta.
+    pub fn change(&self) -> String {
+        match self.delta_fraction {
+            Some(f) => format!("{:+.0}%", f * 100.0),
+            None => self.signed_delta(),
+        }
+    }
+
+    /// "+$1.20" or "-$0.50".
+    pub fn signed_delta(&self) -> String {
+        if self.delta_dollars < Money::ZERO {
+            self.delta_dollars.to_string()
+        } else {
+            format!("+{}", self.delta_dollars)
+        }
+    }
+
+    pub fn arrow(&self) -> &'static str {
+        match self.trend {
+            Trend::Up => "↑",
+            Trend::Down => "↓",
+            Trend::Flat => "→",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LineItem {
+    /// Reported model name; the one with the most spend when the line item
+    /// groups several.
     pub model_name: String,
+    /// Key of the group the line item covers, per `models.grouping` in
+    /// settings; the reported model name when not grouped.
+    #[serde(default)]
+    pub group: String,
+    /// e.g. "Claude 4 Sonnet"; see `ModelRegistry`.
+    #[serde(default)]
+    pub display_name: String,
     pub request_count: i32,
-    pub cost_dollars: f64,
+    pub cost_dollars: Money,
     pub total_tokens: i64,
+    /// Part of `cost_dollars` estimated from list prices.
+    #[serde(default)]
+    pub estimated_dollars: Money,
+    #[serde(default)]
+    pub estimated_requests: i32,
+    /// How far reported charges are from list price, e.g. 0.25 for 25%
+    /// over, when more than `PRICE_DISCREPANCY` either way.
+    #[serde(default)]
+    pub price_discrepancy: Option<f64>,
+}
+
+/// Reported charges within this share of list price are not flagged.
+pub const PRICE_DISCREPANCY: f64 = 0.1;
+
+/// What sort of request an event was, from `UsageEvent.kind`. The usage
+/// API mostly sends billing kinds, which end up as `Other`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum RequestKind {
+    Chat,
+    Agent,
+    /// Tab completions.
+    Tab,
+    BackgroundAgent,
+    /// Any other kind, normalised to lower-kebab-case without the
+    /// `USAGE_EVENT_KIND_` prefix, e.g. `usage-based`; `unknown` when missing.
+    Other(String),
+}
+
+impl Default for RequestKind {
+    fn default() -> Self {
+        RequestKind::Other("unknown".to_string())
+    }
+}
+
+impl RequestKind {
+    /// Accepts Cursor's enum names (`USAGE_EVENT_KIND_BACKGROUND_AGENT`),
+    /// dashboard labels (`Background Agent`) and this type's own names.
+    pub fn parse(kind: &str) -> Self {
+        let upper = kind.trim().to_uppercase();
+        let normalised = upper
+            .strip_prefix("USAGE_EVENT_KIND_")
+            .unwrap_or(&upper)
+            .to_lowercase()
+            .replace(['_', ' '], "-");
+        match normalised.as_str() {
+            "" => RequestKind::default(),
+            "chat" | "ask" | "cmd-k" | "inline-edit" => RequestKind::Chat,
+            "agent" | "composer" => RequestKind::Agent,
+            "tab" | "cpp" | "cursor-tab" | "completion" => RequestKind::Tab,
+            "background-agent" | "bg-agent" => RequestKind::BackgroundAgent,
+            _ => RequestKind::Other(normalised),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            RequestKind::Chat => "chat",
+            RequestKind::Agent => "agent",
+            RequestKind::Tab => "tab",
+            RequestKind::BackgroundAgent => "background-agent",
+            RequestKind::Other(kind) => kind,
+        }
+    }
+}
+
+impl fmt::Display for RequestKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<String> for RequestKind {
+    fn from(kind: String) -> Self {
+        RequestKind::parse(&kind)
+    }
+}
+
+impl From<RequestKind> for String {
+    fn from(kind: RequestKind) -> String {
+        kind.as_str().to_string()
+    }
+}
+
+/// Spend for one request kind, with its models.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KindSummary {
+    pub kind: RequestKind,
+    pub requests: i32,
+    pub spend_dollars: Money,
+    pub tokens: i64,
+    /// One row of the kind × model matrix, sorted by cost descending.
+    pub models: Vec<LineItem>,
+}
+
+/// Broad cause of a failed refresh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    /// Could not reach cursor.com (no network, DNS, timeout).
+    Offline,
+    /// Missing or rejected session token.
+    Auth,
+    /// Anything else: server errors, unexpected responses.
+    Api,
+}
+
+impl ErrorKind {
+    pub fn describe(self) -> &'static str {
+        match self {
+            ErrorKind::Offline => "offline",
+            ErrorKind::Auth => "signed out",
+            ErrorKind::Api => "API error",
+        }
+    }
+}
+
+/// Freshness of the data currently shown.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshStatus {
+    /// When the shown data was fetched (RFC 3339).
+    pub fetched_at: Option<String>,
+    /// True when the shown data is from an earlier refresh or the disk cache.
+    pub stale: bool,
+    pub error_kind: Option<ErrorKind>,
+    /// Set when the last refresh had to skip events it could not parse.
+    pub warning: Option<String>,
+    /// Problems in the settings file; the affected settings use defaults.
+    pub settings_warnings: Vec<String>,
 }
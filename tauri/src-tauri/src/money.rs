@@ -0,0 +1,202 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Sub};
+
+/// The API reports fractional cents; a millionth of a cent keeps every
+/// digit it sends.
+const MICRO_CENTS_PER_CENT: i64 = 1_000_000;
+const MICRO_CENTS_PER_DOLLAR: i64 = 100 * MICRO_CENTS_PER_CENT;
+
+/// An exact amount of money, in millionths of a cent.
+///
+/// Sums are exact and order-independent, and amounts have a total order, so
+/// sorting never has to deal with NaN. Serialised as a number of dollars,
+/// which is what the frontend and the cache file expect.
+///
+/// Rounding rules: conversions from floating point round to the nearest
+/// micro-cent; `round_to_cents` and `Display` round half away from zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    /// A cost in (fractional) cents, as the API reports it. `None` for NaN,
+    /// infinite, negative or out-of-range values, which are not valid costs.
+    pub fn from_cents(cents: f64) -> Option<Self> {
+        Self::from_scaled(cents, MICRO_CENTS_PER_CENT)
+    }
+
+    /// A dollar amount, e.g. from settings. Same rules as `from_cents`.
+    pub fn from_dollars(dollars: f64) -> Option<Self> {
+        Self::from_scaled(dollars, MICRO_CENTS_PER_DOLLAR)
+    }
+
+    fn from_scaled(value: f64, scale: i64) -> Option<Self> {
+        if !value.is_finite() || value < 0.0 {
+            return None;
+        }
+        let scaled = (value * scale as f64).round();
+        (scaled < i64::MAX as f64).then_some(Money(scaled as i64))
+    }
+
+    /// Approximate dollars, for ratios, colours and metrics. Never sum these.
+    pub fn as_dollars_f64(self) -> f64 {
+        self.0 as f64 / MICRO_CENTS_PER_DOLLAR as f64
+    }
+
+    /// Approximate cents, for export columns that have always been in cents.
+    pub fn as_cents_f64(self) -> f64 {
+        self.0 as f64 / MICRO_CENTS_PER_CENT as f64
+    }
+
+    /// Whole cents, rounding half away from zero.
+    pub fn round_to_cents(self) -> i64 {
+        let cents = self.0 / MICRO_CENTS_PER_CENT;
+        let rest = self.0 % MICRO_CENTS_PER_CENT;
+        if rest.abs() * 2 >= MICRO_CENTS_PER_CENT {
+            cents + self.0.signum()
+        } else {
+            cents
+        }
+    }
+
+    /// `self / other`, or `None` when `other` is zero.
+    pub fn ratio(self, other: Money) -> Option<f64> {
+        (other.0 != 0).then(|| self.0 as f64 / other.0 as f64)
+    }
+
+    /// `self * factor` rounded to the nearest micro-cent; for projections,
+    /// which are estimates anyway. Saturates instead of overflowing.
+    pub fn scale(self, factor: f64) -> Money {
+        let scaled = (self.0 as f64 * factor).round();
+        if scaled.is_nan() {
+            Money::ZERO
+        } else {
+            // `as` saturates at the i64 bounds
+            Money(scaled as i64)
+        }
+    }
+
+    /// `self - other`, but never below zero.
+    pub fn saturating_sub(self, other: Money) -> Money {
+        Money(self.0.saturating_sub(other.0).max(0))
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    /// Saturates rather than wrapping; no real bill gets near `i64::MAX`.
+    fn add(self, rhs: Money) -> Money {
+        Money(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    /// May go negative, e.g. for a drop in spend; saturates like `add`.
+    fn sub(self, rhs: Money) -> Money {
+        Money(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Money) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+/// `$12.35`, rounded to the cent.
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cents = self.round_to_cents();
+        let sign = if cents < 0 { "-" } else { "" };
+        let cents = cents.unsigned_abs();
+        write!(f, "{}${}.{:02}", sign, cents / 100, cents % 100)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.as_dollars_f64())
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let dollars = f64::deserialize(deserializer)?;
+        if !dollars.is_finite() {
+            return Err(serde::de::Error::custom("amount is not a finite number"));
+        }
+        Ok(Money((dollars * MICRO_CENTS_PER_DOLLAR as f64).round() as i64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn rejects_invalid_costs() {
+        assert_eq!(Money::from_cents(f64::NAN), None);
+        assert_eq!(Money::from_cents(f64::INFINITY), None);
+        assert_eq!(Money::from_cents(-0.5), None);
+        assert_eq!(Money::from_cents(1e30), None);
+        assert_eq!(Money::from_cents(-0.0), Some(Money::ZERO));
+    }
+
+    #[test]
+    fn displays_rounded_half_away_from_zero() {
+        assert_eq!(Money::from_cents(1234.5).unwrap().to_string(), "$12.35");
+        assert_eq!(Money::from_cents(1_234.499_999).unwrap().to_string(), "$12.34");
+        assert_eq!(Money::from_cents(0.5).unwrap().to_string(), "$0.01");
+        assert_eq!(Money::ZERO.to_string(), "$0.00");
+        assert_eq!(Money(-1_500_000).to_string(), "-$0.02");
+    }
+
+    #[test]
+    fn ratio_of_zero_is_none() {
+        assert_eq!(Money::from_dollars(5.0).unwrap().ratio(Money::ZERO), None);
+        assert_eq!(Money::from_dollars(5.0).unwrap().ratio(Money::from_dollars(20.0).unwrap()), Some(0.25));
+    }
+
+    proptest! {
+        #[test]
+        fn micro_cents_survive_the_api_float(micro in 0i64..1_000_000_000_000) {
+            let cents = micro as f64 / MICRO_CENTS_PER_CENT as f64;
+            prop_assert_eq!(Money::from_cents(cents), Some(Money(micro)));
+        }
+
+        #[test]
+        fn serde_round_trips(micro in -1_000_000_000_000i64..1_000_000_000_000) {
+            let money = Money(micro);
+            let json = serde_json::to_string(&money).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Money>(&json).unwrap(), money);
+        }
+
+        #[test]
+        fn sum_is_order_independent(mut amounts in prop::collection::vec(0i64..1_000_000_000, 0..50)) {
+            let forward: Money = amounts.iter().copied().map(Money).sum();
+            amounts.reverse();
+            let backward: Money = amounts.iter().copied().map(Money).sum();
+            prop_assert_eq!(forward, backward);
+            prop_assert_eq!(forward.0, amounts.iter().sum::<i64>());
+        }
+
+        #[test]
+        fn rounding_is_within_half_a_cent(micro in -1_000_000_000_000i64..1_000_000_000_000) {
+            let rounded = Money(micro).round_to_cents() * MICRO_CENTS_PER_CENT;
+            prop_assert!((rounded - micro).abs() * 2 <= MICRO_CENTS_PER_CENT);
+        }
+    }
+}
@@ -0,0 +1,467 @@
+use crate::models::{PeriodSummary, UsageDisplayData};
+use crate::money::Money;
+use crate::settings::NotificationSettings;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Notifications kept for the popup's history list, and most held back
+/// at once.
+pub const HISTORY_LEN: usize = 50;
+
+/// What an alert is about; each can be switched off in settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Category {
+    /// Spending bursts and expensive events.
+    Spike,
+    /// Budget thresholds crossed.
+    Budget,
+    /// Yesterday's spend, once a day.
+    Summary,
+    /// Scheduled digests with a notification delivery.
+    Digest,
+    /// The "send test" action for webhooks; never shown on the desktop.
+    Test,
+}
+
+impl Category {
+    /// What a webhook can list under `categories`; `Test` always goes through.
+    pub const SUBSCRIBABLE: [Category; 4] = [Category::Spike, Category::Budget, Category::Summary, Category::Digest];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Category::Spike => "spike",
+            Category::Budget => "budget",
+            Category::Summary => "summary",
+            Category::Digest => "digest",
+            Category::Test => "test",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "spike" => Some(Category::Spike),
+            "budget" => Some(Category::Budget),
+            "summary" => Some(Category::Summary),
+            "digest" => Some(Category::Digest),
+            "test" => Some(Category::Test),
+            _ => None,
+        }
+    }
+}
+
+/// A daily window, in the reporting timezone, in which nothing is shown.
+/// May wrap past midnight, e.g. 22:00 to 08:00.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// An alert on its way to the desktop and any webhooks.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub category: Category,
+    /// Notifications with the same key within `dedupMinutes` are dropped.
+    pub key: String,
+    pub title: String,
+    pub body: String,
+}
+
+/// What the notifier did with a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Outcome {
+    Shown,
+    Disabled,
+    Duplicate,
+    QuietHours,
+    RateLimited,
+}
+
+impl Outcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Shown => "shown",
+            Outcome::Disabled => "disabled",
+            Outcome::Duplicate => "duplicate",
+            Outcome::QuietHours => "quietHours",
+            Outcome::RateLimited => "rateLimited",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "shown" => Some(Outcome::Shown),
+            "disabled" => Some(Outcome::Disabled),
+            "duplicate" => Some(Outcome::Duplicate),
+            "quietHours" => Some(Outcome::QuietHours),
+            "rateLimited" => Some(Outcome::RateLimited),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationRecord {
+    pub at: DateTime<Utc>,
+    pub category: Category,
+    #[serde(skip)]
+    pub key: String,
+    pub title: String,
+    pub body: String,
+    pub outcome: Outcome,
+}
+
+/// Decides which notifications are shown, and remembers them for the
+/// history list and for de-duplication and rate limiting. Notifications
+/// held back by quiet hours or the rate limit wait until they can be shown.
+#[derive(Debug, Default)]
+pub struct Notifier {
+    history: VecDeque<NotificationRecord>,
+    /// Held back, oldest first; at most one per key.
+    held: VecDeque<Notification>,
+    /// Records added since the last `take_unsaved`.
+    unsaved: Vec<NotificationRecord>,
+}
+
+impl Notifier {
+    /// A notifier that carries on from `history`, oldest first, as kept by
+    /// an earlier run.
+    pub fn with_history(history: Vec<NotificationRecord>) -> Self {
+        let skip = history.len().saturating_sub(HISTORY_LEN);
+        Notifier {
+            history: history.into_iter().skip(skip).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether to show `notification` now, given the time of day in the
+    /// reporting timezone. Disabled categories and duplicates are dropped
+    /// silently; anything else is added to the history, shown or not, and
+    /// held back until `release` can show it if not shown now.
+    pub fn admit(
+        &mut self,
+        notification: &Notification,
+        settings: &NotificationSettings,
+        time_of_day: NaiveTime,
+        now: DateTime<Utc>,
+    ) -> Outcome {
+        let outcome = self.decide(notification, settings, time_of_day, now);
+        match outcome {
+            Outcome::Disabled | Outcome::Duplicate => {}
+            Outcome::Shown => self.record(notification, outcome, now),
+            Outcome::QuietHours | Outcome::RateLimited => {
+                self.record(notification, outcome, now);
+                if self.held.len() == HISTORY_LEN {
+                    self.held.pop_front();
+                }
+                self.held.push_back(notification.clone());
+            }
+        }
+        outcome
+    }
+
+    /// Held-back notifications that can now be shown, oldest first. The
+    /// rest stay held; any whose category has since been disabled, or that
+    /// were shown meanwhile, are dropped.
+    pub fn release(&mut self, settings: &NotificationSettings, time_of_day: NaiveTime, now: DateTime<Utc>) -> Vec<Notification> {
+        let mut shown = Vec::new();
+        for notification in std::mem::take(&mut self.held) {
+            match self.decide(&notification, settings, time_of_day, now) {
+                Outcome::Shown => {
+                    self.record(&notification, Outcome::Shown, now);
+                    shown.push(notification);
+                }
+                Outcome::QuietHours | Outcome::RateLimited => self.held.push_back(notification),
+                Outcome::Disabled | Outcome::Duplicate => {}
+            }
+        }
+        shown
+    }
+
+    /// Newest first.
+    pub fn history(&self) -> impl Iterator<Item = &NotificationRecord> {
+        self.history.iter().rev()
+    }
+
+    /// History records added since the last call, oldest first, for saving.
+    pub fn take_unsaved(&mut self) -> Vec<NotificationRecord> {
+        std::mem::take(&mut self.unsaved)
+    }
+
+    /// Duplicates are notifications with the key of one shown within
+    /// `dedupMinutes`, or of one already held back.
+    fn decide(
+        &self,
+        notification: &Notification,
+        settings: &NotificationSettings,
+        time_of_day: NaiveTime,
+        now: DateTime<Utc>,
+    ) -> Outcome {
+        let dedup_since = now - Duration::minutes(settings.dedup_minutes as i64);
+        if !settings.enabled || !settings.allows(notification.category) {
+            Outcome::Disabled
+        } else if self
+            .history
+            .iter()
+            .any(|r| r.key == notification.key && r.outcome == Outcome::Shown && r.at >= dedup_since)
+            || self.held.iter().any(|h| h.key == notification.key)
+        {
+            Outcome::Duplicate
+        } else if settings.quiet_hours.as_ref().is_some_and(|q| q.contains(time_of_day)) {
+            Outcome::QuietHours
+        } else if settings.max_per_hour > 0 && self.shown_since(now - Duration::hours(1)) >= settings.max_per_hour as usize {
+            Outcome::RateLimited
+        } else {
+            Outcome::Shown
+        }
+    }
+
+    fn record(&mut self, notification: &Notification, outcome: Outcome, now: DateTime<Utc>) {
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        let record = NotificationRecord {
+            at: now,
+            category: notification.category,
+            key: notification.key.clone(),
+            title: notification.title.clone(),
+            body: notification.body.clone(),
+            outcome,
+        };
+        self.unsaved.push(record.clone());
+        self.history.push_back(record);
+    }
+
+    fn shown_since(&self, since: DateTime<Utc>) -> usize {
+        self.history
+            .iter()
+            .filter(|r| r.outcome == Outcome::Shown && r.at >= since)
+            .count()
+    }
+}
+
+/// The highest of `thresholds` (percent of `budget`) that period spend has
+/// reached, with a notification saying so.
+pub fn budget_threshold(data: &UsageDisplayData, budget: Money, thresholds: &[u32]) -> Option<(u32, Notification)> {
+    let percent = data.total_spend_dollars.ratio(budget)? * 100.0;
+    let reached = thresholds.iter().copied().filter(|t| percent >= *t as f64).max()?;
+    let title = if reached >= 100 {
+        "Cursor budget exceeded".to_string()
+    } else {
+        format!("Cursor budget {}% used", reached)
+    };
+    Some((
+        reached,
+        Notification {
+            category: Category::Budget,
+            key: format!("budget:{}:{}", data.billing_period_start, reached),
+            title,
+            body: format!("{} of {} spent this billing period", data.total_spend_dollars, budget),
+        },
+    ))
+}
+
+/// The highest of `warning_percent` and 100 that usage-based spend has
+/// reached of the Cursor spending limit, past which Cursor stops serving
+/// requests, with a notification saying so. A `warning_percent` of 0 only
+/// notifies when the limit is reached.
+pub fn spend_limit_warning(data: &UsageDisplayData, warning_percent: u32) -> Option<(u32, Notification)> {
+    let limit = data.spend_limit_dollars?;
+    let used = data.usage_based_spend_dollars.ratio(limit)? * 100.0;
+    let reached = [warning_percent, 100]
+        .into_iter()
+        .filter(|t| *t > 0 && used >= *t as f64)
+        .max()?;
+    let (title, body) = if reached >= 100 {
+        (
+            "Cursor spending limit reached".to_string(),
+            format!(
+                "{} of {} spent; Cursor stops usage-based requests until the limit is raised",
+                data.usage_based_spend_dollars, limit
+            ),
+        )
+    } else {
+        (
+            format!("Cursor spending limit {:.0}% used", used),
+            format!("{} left of the {} limit this billing period", data.spend_limit_headroom()?, limit),
+        )
+    };
+    Some((
+        reached,
+        Notification {
+            category: Category::Budget,
+            key: format!("spend-limit:{}:{}", data.billing_period_start, reached),
+            title,
+            body,
+        },
+    ))
+}
+
+/// Once-a-day summary of `yesterday`, the reporting day `day`, and the
+/// billing period so far.
+pub fn daily_summary(day: NaiveDate, yesterday: &PeriodSummary, data: &UsageDisplayData) -> Notification {
+    let mut body = format!(
+        "Yesterday: {} ({} req) · Billing period: {} ({} req)",
+        yesterday.spend_dollars, yesterday.requests, data.total_spend_dollars, data.total_requests
+    );
+    if let Some(top) = data.line_items.first() {
+        body.push_str(&format!(" · Top model: {}", top.display_name));
+    }
+    Notification {
+        category: Category::Summary,
+        key: format!("summary:{}", day),
+        title: "Cursor usage yesterday".to_string(),
+        body,
+    }
+}
+
+/// Sent by the "send test" action.
+pub fn test_notification() -> Notification {
+    Notification {
+        category: Category::Test,
+        key: "test".to_string(),
+        title: "Cursor Status Bar test".to_string(),
+        body: "This webhook is set up correctly.".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2026-10-15T12:00:00Z").unwrap().with_timezone(&Utc) + Duration::minutes(minutes)
+    }
+
+    fn time(s: &str) -> NaiveTime {
+        s.parse().unwrap()
+    }
+
+    fn spike(key: &str) -> Notification {
+        Notification {
+            category: Category::Spike,
+            key: key.to_string(),
+            title: "Cursor spending spike".to_string(),
+            body: String::new(),
+        }
+    }
+
+    #[test]
+    fn drops_duplicates_and_disabled_categories() {
+        let mut notifier = Notifier::default();
+        let settings = NotificationSettings::default();
+        let noon = time("12:00");
+        assert_eq!(notifier.admit(&spike("a"), &settings, noon, at(0)), Outcome::Shown);
+        assert_eq!(notifier.admit(&spike("a"), &settings, noon, at(30)), Outcome::Duplicate);
+        assert_eq!(notifier.admit(&spike("a"), &settings, noon, at(90)), Outcome::Shown);
+
+        let muted = NotificationSettings {
+            spikes: false,
+            ..Default::default()
+        };
+        assert_eq!(notifier.admit(&spike("b"), &muted, noon, at(91)), Outcome::Disabled);
+        assert_eq!(notifier.history().count(), 2);
+    }
+
+    #[test]
+    fn caps_notifications_per_hour() {
+        let mut notifier = Notifier::default();
+        let settings = NotificationSettings {
+            max_per_hour: 2,
+            ..Default::default()
+        };
+        let noon = time("12:00");
+        let outcomes: Vec<_> = ["a", "b", "c"]
+            .iter()
+            .enumerate()
+            .map(|(i, key)| notifier.admit(&spike(key), &settings, noon, at(i as i64)))
+            .collect();
+        assert_eq!(outcomes, [Outcome::Shown, Outcome::Shown, Outcome::RateLimited]);
+        assert_eq!(notifier.admit(&spike("d"), &settings, noon, at(61)), Outcome::Shown);
+    }
+
+    #[test]
+    fn quiet_hours_wrap_past_midnight() {
+        let quiet = QuietHours {
+            start: time("22:00"),
+            end: time("08:00"),
+        };
+        assert!(quiet.contains(time("23:30")));
+        assert!(quiet.contains(time("07:59")));
+        assert!(!quiet.contains(time("08:00")));
+        assert!(!quiet.contains(time("12:00")));
+
+        let mut notifier = Notifier::default();
+        let settings = NotificationSettings {
+            quiet_hours: Some(quiet),
+            ..Default::default()
+        };
+        assert_eq!(notifier.admit(&spike("a"), &settings, time("02:00"), at(0)), Outcome::QuietHours);
+        assert_eq!(notifier.history().next().unwrap().outcome, Outcome::QuietHours);
+    }
+
+    #[test]
+    fn holds_back_notifications_until_they_can_be_shown() {
+        let mut notifier = Notifier::default();
+        let settings = NotificationSettings {
+            quiet_hours: Some(QuietHours {
+                start: time("22:00"),
+                end: time("08:00"),
+            }),
+            ..Default::default()
+        };
+        assert_eq!(notifier.admit(&spike("a"), &settings, time("02:00"), at(0)), Outcome::QuietHours);
+        // Raised again by the next refresh, still in quiet hours
+        assert_eq!(notifier.admit(&spike("a"), &settings, time("02:05"), at(5)), Outcome::Duplicate);
+        assert!(notifier.release(&settings, time("07:00"), at(300)).is_empty());
+
+        let released = notifier.release(&settings, time("08:10"), at(370));
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].key, "a");
+        assert!(notifier.release(&settings, time("08:20"), at(380)).is_empty());
+
+        let outcomes: Vec<_> = notifier.take_unsaved().iter().map(|r| r.outcome).collect();
+        assert_eq!(outcomes, [Outcome::QuietHours, Outcome::Shown]);
+        assert!(notifier.take_unsaved().is_empty());
+    }
+
+    #[test]
+    fn warns_before_the_spend_limit_and_when_it_is_reached() {
+        let start = at(0) - Duration::days(10);
+        let mut data = crate::cursor_api::aggregate(start, &[], at(0), &Default::default(), &Default::default());
+        data.usage_based_spend_dollars = Money::from_dollars(9.0).unwrap();
+        assert!(spend_limit_warning(&data, 90).is_none());
+
+        // The early warning and the cutoff are separate thresholds
+        data.spend_limit_dollars = Money::from_dollars(10.0);
+        let (reached, warning) = spend_limit_warning(&data, 90).unwrap();
+        assert_eq!(reached, 90);
+        assert_eq!(warning.title, "Cursor spending limit 90% used");
+        assert_eq!(warning.body, "$1.00 left of the $10.00 limit this billing period");
+
+        data.spend_limit_dollars = Money::from_dollars(9.0);
+        let (reached, cutoff) = spend_limit_warning(&data, 90).unwrap();
+        assert_eq!(reached, 100);
+        assert_eq!(cutoff.title, "Cursor spending limit reached");
+        assert_ne!(cutoff.key, warning.key);
+
+        // 0 turns the early warning off
+        data.spend_limit_dollars = Money::from_dollars(10.0);
+        assert!(spend_limit_warning(&data, 0).is_none());
+    }
+}
@@ -0,0 +1,235 @@
+use crate::cursor_api::summarize_range;
+use crate::event_store::EventStore;
+use crate::models::{PeriodSummary, UsageEvent};
+use crate::pricing::PricingTable;
+use crate::reporting::ReportingClock;
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Longest `lastNDays` range, about ten years.
+const MAX_LAST_DAYS: u32 = 3660;
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown period '{0}' (expected e.g. yesterday, thisMonth, previousBillingPeriod, last14Days or 2026-10-06..2026-10-19)")]
+pub struct PeriodRangeError(String);
+
+/// A user-defined period from settings, listed in the popup after the
+/// built-in ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomPeriod {
+    pub name: String,
+    pub range: PeriodRange,
+}
+
+/// Which span of time a custom period covers. Day, week and month
+/// boundaries come from the reporting clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum PeriodRange {
+    Today,
+    Yesterday,
+    /// The calendar week so far, whatever `reporting.week` is set to.
+    ThisWeek,
+    LastWeek,
+    ThisMonth,
+    LastMonth,
+    BillingPeriod,
+    PreviousBillingPeriod,
+    /// The last N reporting days, including today, up to `MAX_LAST_DAYS`.
+    LastDays(u32),
+    /// An inclusive range of reporting days.
+    Dates(NaiveDate, NaiveDate),
+}
+
+impl PeriodRange {
+    /// UTC bounds `[start, end)` of the range as of `now`.
+    pub fn bounds(
+        self,
+        now: DateTime<Utc>,
+        clock: &ReportingClock,
+        billing_start: DateTime<Utc>,
+    ) -> (DateTime<Utc>, DateTime<Utc>) {
+        let today = clock.day_of(now);
+        let days = |from: NaiveDate, until: NaiveDate| (clock.day_start(from), clock.day_start(until));
+        let month_start = today.with_day(1).unwrap();
+
+        match self {
+            PeriodRange::Today => days(today, today + Duration::days(1)),
+            PeriodRange::Yesterday => days(today - Duration::days(1), today),
+            PeriodRange::ThisWeek => {
+                let week = clock.calendar_week_of(today);
+                days(week, week + Duration::days(7))
+            }
+            PeriodRange::LastWeek => {
+                let week = clock.calendar_week_of(today);
+                days(week - Duration::days(7), week)
+            }
+            PeriodRange::ThisMonth => days(month_start, month_start + Months::new(1)),
+            PeriodRange::LastMonth => days(month_start - Months::new(1), month_start),
+            PeriodRange::BillingPeriod => (billing_start, billing_start + Months::new(1)),
+            PeriodRange::PreviousBillingPeriod => (billing_start - Months::new(1), billing_start),
+            PeriodRange::LastDays(n) => {
+                let back = Duration::days(n.clamp(1, MAX_LAST_DAYS) as i64 - 1);
+                days(today.checked_sub_signed(back).unwrap_or(today), today + Duration::days(1))
+            }
+            PeriodRange::Dates(from, to) => days(from, to.succ_opt().unwrap_or(to)),
+        }
+    }
+}
+
+impl FromStr for PeriodRange {
+    type Err = PeriodRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || PeriodRangeError(s.to_string());
+        let range = match s {
+            "today" => PeriodRange::Today,
+            "yesterday" => PeriodRange::Yesterday,
+            "thisWeek" => PeriodRange::ThisWeek,
+            "lastWeek" => PeriodRange::LastWeek,
+            "thisMonth" => PeriodRange::ThisMonth,
+            "lastMonth" => PeriodRange::LastMonth,
+            "billingPeriod" => PeriodRange::BillingPeriod,
+            "previousBillingPeriod" => PeriodRange::PreviousBillingPeriod,
+            _ => {
+                if let Some(n) = s.strip_prefix("last").and_then(|r| r.strip_suffix("Days")) {
+                    let n = n.parse().ok().filter(|n| (1..=MAX_LAST_DAYS).contains(n));
+                    PeriodRange::LastDays(n.ok_or_else(err)?)
+                } else {
+                    let (from, to) = s.split_once("..").ok_or_else(err)?;
+                    let date = |d: &str| NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d").map_err(|_| err());
+                    let (from, to) = (date(from)?, date(to)?);
+                    if from > to {
+                        return Err(err());
+                    }
+                    PeriodRange::Dates(from, to)
+                }
+            }
+        };
+        Ok(range)
+    }
+}
+
+impl fmt::Display for PeriodRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeriodRange::Today => f.write_str("today"),
+            PeriodRange::Yesterday => f.write_str("yesterday"),
+            PeriodRange::ThisWeek => f.write_str("thisWeek"),
+            PeriodRange::LastWeek => f.write_str("lastWeek"),
+            PeriodRange::ThisMonth => f.write_str("thisMonth"),
+            PeriodRange::LastMonth => f.write_str("lastMonth"),
+            PeriodRange::BillingPeriod => f.write_str("billingPeriod"),
+            PeriodRange::PreviousBillingPeriod => f.write_str("previousBillingPeriod"),
+            PeriodRange::LastDays(n) => write!(f, "last{}Days", n),
+            PeriodRange::Dates(from, to) => write!(f, "{}..{}", from, to),
+        }
+    }
+}
+
+impl TryFrom<String> for PeriodRange {
+    type Error = PeriodRangeError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<PeriodRange> for String {
+    fn from(range: PeriodRange) -> String {
+        range.to_string()
+    }
+}
+
+/// Summaries for the custom periods. Events come from the local store, for
+/// the given account, when there is one, since it keeps history past the
+/// API's window; otherwise from `fetched`, which only covers the last ~30 days.
+pub fn summarize(
+    periods: &[CustomPeriod],
+    fetched: &[UsageEvent],
+    store: Option<(&EventStore, &str)>,
+    pricing: &PricingTable,
+    now: DateTime<Utc>,
+    clock: &ReportingClock,
+    billing_start: DateTime<Utc>,
+) -> Vec<PeriodSummary> {
+    periods
+        .iter()
+        .map(|period| {
+            let (start, end) = period.range.bounds(now, clock, billing_start);
+            let stored = store.and_then(|(s, account)| {
+                s.events_between(account, start, end)
+                    .map_err(|e| tracing::warn!(error = %e, period = %period.name, "cannot read stored events"))
+                    .ok()
+            });
+            match stored {
+                Some(mut events) => {
+                    pricing.apply(&mut events);
+                    summarize_range(&period.name, &events, start, end)
+                }
+                None => summarize_range(&period.name, fetched, start, end),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Weekday;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn parses_and_prints_every_form() {
+        for text in [
+            "today",
+            "yesterday",
+            "thisWeek",
+            "lastWeek",
+            "thisMonth",
+            "lastMonth",
+            "billingPeriod",
+            "previousBillingPeriod",
+            "last14Days",
+            "2026-10-06..2026-10-19",
+        ] {
+            let range: PeriodRange = text.parse().unwrap();
+            assert_eq!(range.to_string(), text);
+        }
+        assert!("last0Days".parse::<PeriodRange>().is_err());
+        assert!("last100000000Days".parse::<PeriodRange>().is_err());
+        assert!("2026-10-19..2026-10-06".parse::<PeriodRange>().is_err());
+        assert!("fortnight".parse::<PeriodRange>().is_err());
+    }
+
+    #[test]
+    fn bounds_follow_the_reporting_clock() {
+        let clock = ReportingClock::new(Some("UTC"), Default::default(), Weekday::Mon, 0).unwrap();
+        // Thursday
+        let now = utc("2026-10-15T12:00:00Z");
+        let billing_start = utc("2026-10-03T00:00:00Z");
+        let bounds = |r: &str| r.parse::<PeriodRange>().unwrap().bounds(now, &clock, billing_start);
+
+        assert_eq!(bounds("yesterday"), (utc("2026-10-14T00:00:00Z"), utc("2026-10-15T00:00:00Z")));
+        assert_eq!(bounds("lastWeek"), (utc("2026-10-05T00:00:00Z"), utc("2026-10-12T00:00:00Z")));
+        assert_eq!(bounds("thisMonth"), (utc("2026-10-01T00:00:00Z"), utc("2026-11-01T00:00:00Z")));
+        assert_eq!(
+            bounds("previousBillingPeriod"),
+            (utc("2026-09-03T00:00:00Z"), utc("2026-10-03T00:00:00Z"))
+        );
+        assert_eq!(bounds("last3Days"), (utc("2026-10-13T00:00:00Z"), utc("2026-10-16T00:00:00Z")));
+        // Built directly rather than parsed: clamped instead of panicking
+        let (start, _) = PeriodRange::LastDays(100_000_000).bounds(now, &clock, billing_start);
+        assert!(start < utc("2017-01-01T00:00:00Z"));
+        assert_eq!(
+            bounds("2026-10-06..2026-10-19"),
+            (utc("2026-10-06T00:00:00Z"), utc("2026-10-20T00:00:00Z"))
+        );
+    }
+}
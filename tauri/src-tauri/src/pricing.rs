@@ -0,0 +1,212 @@
+use crate::model_registry::ModelRegistry;
+use crate::models::{TokenUsage, UsageEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Dollars per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    #[serde(default)]
+    pub cache_read: f64,
+    #[serde(default)]
+    pub cache_write: f64,
+}
+
+impl ModelPrice {
+    const fn new(input: f64, output: f64, cache_read: f64, cache_write: f64) -> Self {
+        Self {
+            input,
+            output,
+            cache_read,
+            cache_write,
+        }
+    }
+
+    pub fn cents(&self, usage: &TokenUsage) -> f64 {
+        let cost = |tokens: Option<i64>, price: f64| tokens.unwrap_or(0) as f64 * price;
+        let dollars = cost(usage.input_tokens, self.input)
+            + cost(usage.output_tokens, self.output)
+            + cost(usage.cache_read_tokens, self.cache_read)
+            + cost(usage.cache_write_tokens, self.cache_write);
+        dollars / 1_000_000.0 * 100.0
+    }
+}
+
+const BUNDLED: &[(&str, ModelPrice)] = &[
+    ("claude-4-opus", ModelPrice::new(15.0, 75.0, 1.5, 18.75)),
+    ("claude-4.1-opus", ModelPrice::new(15.0, 75.0, 1.5, 18.75)),
+    ("claude-4-sonnet", ModelPrice::new(3.0, 15.0, 0.3, 3.75)),
+    ("claude-4.5-sonnet", ModelPrice::new(3.0, 15.0, 0.3, 3.75)),
+    ("claude-3.7-sonnet", ModelPrice::new(3.0, 15.0, 0.3, 3.75)),
+    ("claude-3.5-sonnet", ModelPrice::new(3.0, 15.0, 0.3, 3.75)),
+    ("claude-3.5-haiku", ModelPrice::new(0.8, 4.0, 0.08, 1.0)),
+    ("gpt-5", ModelPrice::new(1.25, 10.0, 0.125, 1.25)),
+    ("gpt-5-mini", ModelPrice::new(0.25, 2.0, 0.025, 0.25)),
+    ("gpt-5-nano", ModelPrice::new(0.05, 0.4, 0.005, 0.05)),
+    ("gpt-4.1", ModelPrice::new(2.0, 8.0, 0.5, 2.0)),
+    ("gpt-4.1-mini", ModelPrice::new(0.4, 1.6, 0.1, 0.4)),
+    ("gpt-4.1-nano", ModelPrice::new(0.1, 0.4, 0.025, 0.1)),
+    ("gpt-4o", ModelPrice::new(2.5, 10.0, 1.25, 2.5)),
+    ("gpt-4o-mini", ModelPrice::new(0.15, 0.6, 0.075, 0.15)),
+    ("o3", ModelPrice::new(2.0, 8.0, 0.5, 2.0)),
+    ("o3-mini", ModelPrice::new(1.1, 4.4, 0.55, 1.1)),
+    ("o3-pro", ModelPrice::new(20.0, 80.0, 20.0, 20.0)),
+    ("o4-mini", ModelPrice::new(1.1, 4.4, 0.275, 1.1)),
+    ("gemini-2.5-pro", ModelPrice::new(1.25, 10.0, 0.31, 1.25)),
+    ("gemini-2.5-flash", ModelPrice::new(0.3, 2.5, 0.075, 0.3)),
+    ("gemini-2.5-flash-lite", ModelPrice::new(0.1, 0.4, 0.025, 0.1)),
+    ("grok-4", ModelPrice::new(3.0, 15.0, 0.75, 3.0)),
+    ("grok-code-fast-1", ModelPrice::new(0.2, 1.5, 0.02, 0.2)),
+];
+
+/// Modes that are billed at the base model's price.
+const MODE_SUFFIXES: &[&str] = &["-thinking", "-max", "-minimal", "-low", "-medium", "-high"];
+
+/// The bundled list prices with the overrides from settings applied, for
+/// events Cursor reports without a cost.
+#[derive(Debug, Clone)]
+pub struct PricingTable {
+    prices: HashMap<String, ModelPrice>,
+    /// Canonical names for models not listed as reported.
+    models: ModelRegistry,
+}
+
+impl Default for PricingTable {
+    fn default() -> Self {
+        Self::new(&HashMap::new(), ModelRegistry::default())
+    }
+}
+
+impl PricingTable {
+    pub fn new(overrides: &HashMap<String, ModelPrice>, models: ModelRegistry) -> Self {
+        let mut prices: HashMap<String, ModelPrice> =
+            BUNDLED.iter().map(|(model, price)| (model.to_string(), *price)).collect();
+        prices.extend(overrides.iter().map(|(model, price)| (model.to_lowercase(), *price)));
+        Self { prices, models }
+    }
+
+    /// Price for `model`, with mode suffixes removed, so
+    /// `claude-4-sonnet-thinking` is priced as `claude-4-sonnet`, or failing
+    /// that for its canonical name, so `claude-sonnet-4-5` is found as
+    /// `claude-4.5-sonnet`. Other variants such as `gpt-4o-mini` are not
+    /// priced as the model they extend.
+    pub fn price(&self, model: &str) -> Option<&ModelPrice> {
+        let model = model.to_lowercase();
+        let mut name = model.as_str();
+        loop {
+            if let Some(price) = self.prices.get(name) {
+                return Some(price);
+            }
+            match MODE_SUFFIXES.iter().find_map(|suffix| name.strip_suffix(suffix)) {
+                Some(base) => name = base,
+                None => break,
+            }
+        }
+        self.prices.get(&self.models.info(&model).canonical)
+    }
+
+    /// What the table says `event` should cost, in cents. None for request-
+    /// based calls, events without tokens, models the table does not know
+    /// and events that are not billed as usage, such as included requests.
+    pub fn estimate_cents(&self, event: &UsageEvent) -> Option<f64> {
+        if event.is_token_based_call == Some(false) || !event.is_usage_based() {
+            return None;
+        }
+        let usage = event.token_usage.as_ref().filter(|u| u.total_tokens() > 0)?;
+        Some(self.price(event.model.as_deref()?)?.cents(usage))
+    }
+
+    /// Fill in `estimated_cents` on every event the table can price. The
+    /// estimate is only used as the cost when Cursor reports none, and
+    /// otherwise to spot charges that differ from list price.
+    pub fn apply(&self, events: &mut [UsageEvent]) {
+        for event in events {
+            let estimate = self.estimate_cents(event);
+            if let Some(usage) = event.token_usage.as_mut() {
+                usage.estimated_cents = estimate;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor_api::line_items_for;
+    use crate::money::Money;
+
+    fn event(model: &str, input: i64, output: i64, cents: Option<f64>) -> UsageEvent {
+        let at = chrono::DateTime::from_timestamp_millis(1_760_000_000_000).unwrap();
+        let event = UsageEvent::test(model, at).tokens(input, output, 0, 0);
+        match cents {
+            Some(cents) => event.cents(cents),
+            None => event,
+        }
+    }
+
+    #[test]
+    fn matches_variants_and_applies_overrides() {
+        let overrides = HashMap::from([("My-Model".to_string(), ModelPrice::new(1.0, 2.0, 0.0, 0.0))]);
+        let table = PricingTable::new(&overrides, ModelRegistry::default());
+        assert_eq!(table.price("claude-4-sonnet-thinking"), table.price("claude-4-sonnet"));
+        assert_eq!(table.price("claude-4-opus-high-max"), table.price("claude-4-opus"));
+        assert_eq!(table.price("gpt-5-mini-high").unwrap().input, 0.25);
+        assert_eq!(table.price("o3-pro").unwrap().input, 20.0);
+        assert_eq!(table.price("my-model").unwrap().output, 2.0);
+        assert!(table.price("gpt-50").is_none());
+        assert!(table.price("auto").is_none());
+
+        // Smaller tiers have their own price, not the larger model's
+        for (model, input) in [
+            ("gpt-4o-mini", 0.15),
+            ("gpt-4.1-mini", 0.4),
+            ("gpt-4.1-nano", 0.1),
+            ("o3-mini", 1.1),
+            ("gemini-2.5-flash-lite", 0.1),
+        ] {
+            assert_eq!(table.price(model).unwrap().input, input, "{}", model);
+        }
+        assert!(table.price("gpt-4o-mini-audio").is_none());
+        assert!(table.price("gemini-2.5-flash-image").is_none());
+
+        // Found by canonical name
+        assert_eq!(table.price("claude-sonnet-4-5"), table.price("claude-4.5-sonnet"));
+        assert_eq!(table.price("claude-3-5-sonnet-20241022"), table.price("claude-3.5-sonnet"));
+        assert_eq!(table.price("gpt-5-preview"), table.price("gpt-5"));
+    }
+
+    #[test]
+    fn estimates_only_missing_costs_and_flags_discrepancies() {
+        let mut events = vec![
+            // 1M input + 100K output on claude-4-sonnet: $3.00 + $1.50
+            event("claude-4-sonnet", 1_000_000, 100_000, None),
+            event("gpt-5", 1_000_000, 0, Some(125.0)),
+            // Charged double the list price
+            event("gpt-5", 1_000_000, 0, Some(250.0)),
+            event("auto", 1_000, 1_000, None),
+            event("claude-4-sonnet", 1_000_000, 0, None),
+        ];
+        // Included in the plan: no charge to estimate
+        events[4].kind = Some("USAGE_EVENT_KIND_INCLUDED_IN_PRO".to_string());
+        PricingTable::default().apply(&mut events);
+
+        assert_eq!(events[0].cost(), Money::from_dollars(4.5).unwrap());
+        assert!(events[0].is_estimated());
+        assert_eq!(events[1].cost(), Money::from_cents(125.0).unwrap());
+        assert!(!events[1].is_estimated());
+        assert_eq!(events[3].cost(), Money::ZERO);
+        assert_eq!(events[4].cost(), Money::ZERO);
+        assert!(!events[4].is_estimated());
+
+        let items = line_items_for(&events);
+        let sonnet = items.iter().find(|i| i.model_name == "claude-4-sonnet").unwrap();
+        assert_eq!(sonnet.estimated_requests, 1);
+        assert_eq!(sonnet.estimated_dollars, sonnet.cost_dollars);
+        let gpt = items.iter().find(|i| i.model_name == "gpt-5").unwrap();
+        assert_eq!(gpt.estimated_requests, 0);
+        assert_eq!(gpt.price_discrepancy, Some(0.5));
+    }
+}
@@ -0,0 +1,264 @@
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReportingError {
+    #[error("unknown timezone '{0}' (expected an IANA name such as Europe/Berlin)")]
+    UnknownTimezone(String),
+    #[error("day start hour {0} is not between 0 and 23")]
+    InvalidDayStartHour(u32),
+}
+
+/// How the "week" bucket is measured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WeekMode {
+    /// The 7 × 24 hours before now.
+    #[default]
+    Rolling,
+    /// Since the start of the current calendar week.
+    Calendar,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Zone {
+    System,
+    Named(Tz),
+}
+
+/// Day and week boundaries in the reporting timezone, used for period
+/// buckets and export date ranges.
+#[derive(Debug, Clone, Copy)]
+pub struct ReportingClock {
+    zone: Zone,
+    week: WeekMode,
+    week_start: Weekday,
+    day_start_hour: u32,
+}
+
+impl Default for ReportingClock {
+    /// System timezone, midnight days, rolling week.
+    fn default() -> Self {
+        Self {
+            zone: Zone::System,
+            week: WeekMode::Rolling,
+            week_start: Weekday::Mon,
+            day_start_hour: 0,
+        }
+    }
+}
+
+impl ReportingClock {
+    pub fn new(
+        timezone: Option<&str>,
+        week: WeekMode,
+        week_start: Weekday,
+        day_start_hour: u32,
+    ) -> Result<Self, ReportingError> {
+        let zone = match timezone {
+            None => Zone::System,
+            Some(name) => Zone::Named(
+                name.parse::<Tz>()
+                    .map_err(|_| ReportingError::UnknownTimezone(name.to_string()))?,
+            ),
+        };
+        if day_start_hour > 23 {
+            return Err(ReportingError::InvalidDayStartHour(day_start_hour));
+        }
+        Ok(Self {
+            zone,
+            week,
+            week_start,
+            day_start_hour,
+        })
+    }
+
+    /// The reporting day `t` falls in. Before `day_start_hour` it is still
+    /// the previous day.
+    pub fn day_of(&self, t: DateTime<Utc>) -> NaiveDate {
+        (self.wall_clock(t) - Duration::hours(self.day_start_hour as i64)).date()
+    }
+
+    /// Wall-clock time of `t` in the reporting timezone.
+    pub fn time_of_day(&self, t: DateTime<Utc>) -> NaiveTime {
+        self.wall_clock(t).time()
+    }
+
+    /// `t` on the wall clock of the reporting timezone, formatted with
+    /// `format`; like `local_date` it ignores `day_start_hour`.
+    pub fn format_wall_clock(&self, t: DateTime<Utc>, format: &str) -> String {
+        self.wall_clock(t).format(format).to_string()
+    }
+
+    /// Calendar date of `t` in the reporting timezone, ignoring `day_start_hour`.
+    pub fn local_date(&self, t: DateTime<Utc>) -> NaiveDate {
+        self.wall_clock(t).date()
+    }
+
+    /// UTC instant of a wall-clock time in the reporting timezone.
+    pub fn at(&self, date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+        match self.zone {
+            Zone::System => resolve(&Local, date.and_time(time)),
+            Zone::Named(tz) => resolve(&tz, date.and_time(time)),
+        }
+    }
+
+    /// When the reporting day `date` begins.
+    pub fn day_start(&self, date: NaiveDate) -> DateTime<Utc> {
+        self.at(date, NaiveTime::from_hms_opt(self.day_start_hour, 0, 0).unwrap())
+    }
+
+    /// Start of the "Today" bucket.
+    pub fn today_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        self.day_start(self.day_of(now))
+    }
+
+    /// Start of the week bucket: 7 days ago, or the start of this calendar week.
+    pub fn week_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self.week {
+            WeekMode::Rolling => now - Duration::days(7),
+            WeekMode::Calendar => self.day_start(self.calendar_week_of(self.day_of(now))),
+        }
+    }
+
+    /// Start of the week bucket before the current one.
+    pub fn previous_week_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self.week {
+            WeekMode::Rolling => now - Duration::days(14),
+            WeekMode::Calendar => self.day_start(self.calendar_week_of(self.day_of(now)) - Duration::days(7)),
+        }
+    }
+
+    /// First day of the calendar week containing `day`.
+    pub fn calendar_week_of(&self, day: NaiveDate) -> NaiveDate {
+        day - Duration::days(day.weekday().days_since(self.week_start) as i64)
+    }
+
+    pub fn week_label(&self) -> &'static str {
+        match self.week {
+            WeekMode::Rolling => "Last 7 Days",
+            WeekMode::Calendar => "This Week",
+        }
+    }
+
+    /// What the week before the week bucket is called in comparisons.
+    pub fn previous_week_label(&self) -> &'static str {
+        match self.week {
+            WeekMode::Rolling => "previous 7 days",
+            WeekMode::Calendar => "last week",
+        }
+    }
+
+    /// `t` for exports: with the reporting timezone's offset when one is
+    /// configured, otherwise in UTC as before.
+    pub fn format_timestamp(&self, t: DateTime<Utc>) -> String {
+        match self.zone {
+            Zone::System => t.to_rfc3339_opts(SecondsFormat::Millis, true),
+            Zone::Named(tz) => t.with_timezone(&tz).to_rfc3339_opts(SecondsFormat::Millis, true),
+        }
+    }
+
+    fn wall_clock(&self, t: DateTime<Utc>) -> NaiveDateTime {
+        match self.zone {
+            Zone::System => t.with_timezone(&Local).naive_local(),
+            Zone::Named(tz) => t.with_timezone(&tz).naive_local(),
+        }
+    }
+}
+
+/// UTC instant of a wall-clock time. Ambiguous times (DST fall-back) take
+/// the earlier instant; times skipped by DST spring-forward move an hour on.
+fn resolve<Z: TimeZone>(zone: &Z, wall: NaiveDateTime) -> DateTime<Utc> {
+    zone.from_local_datetime(&wall)
+        .earliest()
+        .or_else(|| zone.from_local_datetime(&(wall + Duration::hours(1))).earliest())
+        .map(|d| d.with_timezone(&Utc))
+        .unwrap_or_else(|| wall.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn clock(tz: &str, week: WeekMode, week_start: Weekday, hour: u32) -> ReportingClock {
+        ReportingClock::new(Some(tz), week, week_start, hour).unwrap()
+    }
+
+    #[test]
+    fn today_starts_at_local_midnight_in_the_reporting_zone() {
+        let c = clock("America/New_York", WeekMode::Rolling, Weekday::Mon, 0);
+        // 02:00 UTC on the 11th is still the 10th in New York (UTC-4 in June)
+        assert_eq!(c.today_start(utc("2026-06-11T02:00:00Z")), utc("2026-06-10T04:00:00Z"));
+    }
+
+    #[test]
+    fn day_cutoff_keeps_early_hours_on_the_previous_day() {
+        let c = clock("Europe/Berlin", WeekMode::Rolling, Weekday::Mon, 4);
+        // 03:00 Berlin on the 11th belongs to the 10th
+        assert_eq!(c.day_of(utc("2026-06-11T01:00:00Z")), NaiveDate::from_ymd_opt(2026, 6, 10).unwrap());
+        assert_eq!(c.today_start(utc("2026-06-11T01:00:00Z")), utc("2026-06-10T02:00:00Z"));
+        assert_eq!(c.today_start(utc("2026-06-11T03:00:00Z")), utc("2026-06-11T02:00:00Z"));
+    }
+
+    #[test]
+    fn calendar_week_starts_on_the_configured_day() {
+        // Thursday 2026-06-11
+        let now = utc("2026-06-11T12:00:00Z");
+        let monday = clock("UTC", WeekMode::Calendar, Weekday::Mon, 0);
+        assert_eq!(monday.week_start(now), utc("2026-06-08T00:00:00Z"));
+        let sunday = clock("UTC", WeekMode::Calendar, Weekday::Sun, 0);
+        assert_eq!(sunday.week_start(now), utc("2026-06-07T00:00:00Z"));
+        let thursday = clock("UTC", WeekMode::Calendar, Weekday::Thu, 0);
+        assert_eq!(thursday.week_start(now), utc("2026-06-11T00:00:00Z"));
+
+        let rolling = clock("UTC", WeekMode::Rolling, Weekday::Mon, 0);
+        assert_eq!(rolling.week_start(now), utc("2026-06-04T12:00:00Z"));
+    }
+
+    #[test]
+    fn day_start_inside_a_dst_gap_moves_forward() {
+        // Clocks in Berlin jump from 02:00 to 03:00 on 2026-03-29
+        let c = clock("Europe/Berlin", WeekMode::Rolling, Weekday::Mon, 2);
+        assert_eq!(
+            c.day_start(NaiveDate::from_ymd_opt(2026, 3, 29).unwrap()),
+            utc("2026-03-29T01:00:00Z")
+        );
+    }
+
+    #[test]
+    fn rejects_bad_settings() {
+        assert!(matches!(
+            ReportingClock::new(Some("Mars/Olympus"), WeekMode::Rolling, Weekday::Mon, 0),
+            Err(ReportingError::UnknownTimezone(_))
+        ));
+        assert!(matches!(
+            ReportingClock::new(None, WeekMode::Rolling, Weekday::Mon, 24),
+            Err(ReportingError::InvalidDayStartHour(24))
+        ));
+    }
+
+    #[test]
+    fn exports_use_the_configured_offset() {
+        let c = clock("Asia/Kolkata", WeekMode::Rolling, Weekday::Mon, 0);
+        assert_eq!(c.format_timestamp(utc("2026-06-11T00:00:00Z")), "2026-06-11T05:30:00.000+05:30");
+        assert_eq!(
+            ReportingClock::default().format_timestamp(utc("2026-06-11T00:00:00Z")),
+            "2026-06-11T00:00:00.000Z"
+        );
+    }
+
+    #[test]
+    fn wall_clock_times_ignore_the_day_cutoff() {
+        let c = clock("Europe/Berlin", WeekMode::Rolling, Weekday::Mon, 4);
+        let t = utc("2026-06-11T01:30:00Z");
+        assert_eq!(c.format_wall_clock(t, "%b %-d %H:%M"), "Jun 11 03:30");
+        assert_eq!(c.day_of(t).format("%b %-d").to_string(), "Jun 10");
+    }
+}
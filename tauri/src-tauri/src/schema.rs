@@ -0,0 +1,191 @@
+use crate::models::{UsageEvent, UsageEventsResponse};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeSet;
+
+/// Version of the usage event shape this build understands. Stored alongside
+/// persisted events so files written by another version are discarded
+/// instead of misread.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Event fields `UsageEvent` reads; anything else is reported as unknown.
+const KNOWN_EVENT_FIELDS: &[&str] = &[
+    "timestamp",
+    "model",
+    "kind",
+    "usageBasedCosts",
+    "isTokenBasedCall",
+    "tokenUsage",
+    "isChargeable",
+];
+
+/// How a response differed from the shape this build expects.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaDrift {
+    /// Events that could not be parsed and were left out.
+    pub skipped_events: usize,
+    /// Field names seen in the response that this build does not read.
+    pub unknown_fields: BTreeSet<String>,
+}
+
+impl SchemaDrift {
+    pub fn merge(&mut self, other: SchemaDrift) {
+        self.skipped_events += other.skipped_events;
+        self.unknown_fields.extend(other.unknown_fields);
+    }
+
+    /// Warning for the UI. Unknown fields alone are not worth one; they only
+    /// show up in diagnostics.
+    pub fn warning(&self) -> Option<String> {
+        match self.skipped_events {
+            0 => None,
+            1 => Some("API format changed — 1 event skipped".to_string()),
+            n => Some(format!("API format changed — {} events skipped", n)),
+        }
+    }
+}
+
+/// One page of `get-filtered-usage-events`, parsed event by event.
+#[derive(Debug, Default)]
+pub struct EventsPage {
+    pub total: Option<i64>,
+    /// Raw events in the page, parsed or not; used to decide when to stop paging.
+    pub raw_count: usize,
+    pub events: Vec<UsageEvent>,
+    pub drift: SchemaDrift,
+}
+
+/// Parse each event on its own, so one malformed event costs that event
+/// rather than the whole refresh.
+pub fn parse_events_page(response: UsageEventsResponse) -> EventsPage {
+    let mut page = EventsPage {
+        total: response.total_usage_events_count,
+        ..Default::default()
+    };
+    page.drift.unknown_fields.extend(response.extra.into_keys());
+
+    let Some(raw_events) = response.usage_events_display else {
+        // The list itself was renamed or removed: everything reported is lost.
+        page.drift.skipped_events = page.total.unwrap_or(0).max(0) as usize;
+        return page;
+    };
+    page.raw_count = raw_events.len();
+
+    for raw in raw_events {
+        if let Some(object) = raw.as_object() {
+            page.drift.unknown_fields.extend(
+                object
+                    .keys()
+                    .filter(|k| !KNOWN_EVENT_FIELDS.contains(&k.as_str()))
+                    .map(|k| format!("usageEventsDisplay[].{}", k)),
+            );
+        }
+        match serde_json::from_value::<UsageEvent>(raw) {
+            Ok(event) => page.events.push(event),
+            Err(e) => {
+                tracing::debug!(error = %e, "skipping malformed usage event");
+                page.drift.skipped_events += 1;
+            }
+        }
+    }
+    page
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrNumber {
+    String(String),
+    Number(serde_json::Number),
+}
+
+impl StringOrNumber {
+    fn into_string(self) -> String {
+        match self {
+            StringOrNumber::String(s) => s,
+            StringOrNumber::Number(n) => n.to_string(),
+        }
+    }
+}
+
+/// Accept `"1700000000000"` or `1700000000000`, keeping the text.
+pub fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    StringOrNumber::deserialize(deserializer).map(StringOrNumber::into_string)
+}
+
+/// Optional variant of `string_or_number`; pair with `#[serde(default)]`.
+pub fn opt_string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Ok(Option::<StringOrNumber>::deserialize(deserializer)?.map(StringOrNumber::into_string))
+}
+
+/// Accept `12.5` or `"12.5"`; pair with `#[serde(default)]`.
+pub fn opt_number_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    match Option::<StringOrNumber>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(StringOrNumber::Number(n)) => Ok(n.as_f64()),
+        Some(StringOrNumber::String(s)) => s.trim().parse().map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::Money;
+    use serde_json::json;
+
+    fn page(value: serde_json::Value) -> EventsPage {
+        parse_events_page(serde_json::from_value(value).unwrap())
+    }
+
+    #[test]
+    fn accepts_numeric_timestamps_and_string_costs() {
+        let parsed = page(json!({
+            "usageEventsDisplay": [
+                {"timestamp": 1773482400000u64, "usageBasedCosts": 0.13, "tokenUsage": {"totalCents": "12.5"}},
+                {"timestamp": "1773482400000", "usageBasedCosts": "$0.04", "tokenUsage": {"totalCents": 4}}
+            ]
+        }));
+        assert_eq!(parsed.drift, SchemaDrift::default());
+        assert_eq!(parsed.events.len(), 2);
+        assert_eq!(parsed.events[0].timestamp, "1773482400000");
+        assert_eq!(parsed.events[0].usage_based_costs.as_deref(), Some("0.13"));
+        assert_eq!(parsed.events[0].cost(), Money::from_cents(12.5).unwrap());
+        assert_eq!(parsed.events[1].cost(), Money::from_cents(4.0).unwrap());
+    }
+
+    #[test]
+    fn skips_and_counts_malformed_events() {
+        let parsed = page(json!({
+            "totalUsageEventsCount": 3,
+            "usageEventsDisplay": [
+                {"timestamp": "1773482400000", "model": "gpt-5"},
+                {"time": "1773482400000", "model": "gpt-5"},
+                {"timestamp": "1773482400000", "tokenUsage": "n/a"}
+            ]
+        }));
+        assert_eq!(parsed.events.len(), 1);
+        assert_eq!(parsed.raw_count, 3);
+        assert_eq!(parsed.drift.skipped_events, 2);
+        assert_eq!(parsed.drift.warning().unwrap(), "API format changed — 2 events skipped");
+    }
+
+    #[test]
+    fn records_unknown_fields_without_warning() {
+        let parsed = page(json!({
+            "totalUsageEventsCount": 1,
+            "nextCursor": null,
+            "usageEventsDisplay": [{"timestamp": "1", "maxMode": true}]
+        }));
+        assert_eq!(parsed.events.len(), 1);
+        let fields: Vec<_> = parsed.drift.unknown_fields.iter().map(String::as_str).collect();
+        assert_eq!(fields, ["nextCursor", "usageEventsDisplay[].maxMode"]);
+        assert_eq!(parsed.drift.warning(), None);
+    }
+
+    #[test]
+    fn renamed_event_list_counts_every_event_as_skipped() {
+        let parsed = page(json!({"totalUsageEventsCount": 4, "events": []}));
+        assert!(parsed.events.is_empty());
+        assert_eq!(parsed.drift.skipped_events, 4);
+        assert!(parsed.drift.unknown_fields.contains("events"));
+    }
+}
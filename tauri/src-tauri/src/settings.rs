@@ -0,0 +1,680 @@
+use crate::digest::{DigestFormat, DigestFrequency};
+use crate::model_registry::{ModelGrouping, ModelRegistry};
+use crate::money::Money;
+use crate::notifications::{Category, QuietHours};
+use crate::periods::CustomPeriod;
+use crate::pricing::{ModelPrice, PricingTable};
+use crate::reporting::{ReportingClock, ReportingError, WeekMode};
+use crate::template::{Template, TemplateError};
+use crate::webhooks::WebhookFormat;
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SettingsError {
+    #[error("Cannot read settings file {0}: {1}")]
+    CannotRead(String, String),
+    #[error("Invalid settings file {0}: {1}")]
+    Invalid(String, String),
+    #[error("Invalid {0} in settings, using the default: {1}")]
+    InvalidField(String, String),
+    #[error("Invalid {0} in settings, using the default: {1}")]
+    InvalidTemplate(&'static str, TemplateError),
+    #[error("Invalid reporting settings, using the defaults: {0}")]
+    InvalidReporting(#[from] ReportingError),
+    #[error("Invalid HTTP settings: {0} must be at least 1, using the default")]
+    InvalidHttp(&'static str),
+    #[error("Unknown category {1} in {0}, ignoring it (expected {expected})", expected = subscribable_categories())]
+    UnknownCategory(String, String),
+    #[error("Digest \"{0}\" sends to webhook \"{1}\", which is not in settings; skipping it")]
+    UnknownWebhook(String, String),
+    #[error("Digest \"{0}\" has nowhere to deliver to; skipping it")]
+    NoDelivery(String),
+}
+
+/// "spike, budget, summary or digest", for error messages.
+fn subscribable_categories() -> String {
+    let names: Vec<&str> = Category::SUBSCRIBABLE.iter().map(|c| c.as_str()).collect();
+    let (last, rest) = names.split_last().expect("SUBSCRIBABLE is not empty");
+    format!("{} or {}", rest.join(", "), last)
+}
+
+/// User settings, read from `settings.json` in the app config directory.
+/// Every field has a default so a missing or partial file is fine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    /// Spend budget for the billing period, in dollars. Without one, the
+    /// spending limit set in the Cursor dashboard is used.
+    pub budget_dollars: Option<f64>,
+    /// Menu bar text on macOS. See `template.rs` for placeholders.
+    pub title_template: String,
+    /// Tray tooltip on all platforms.
+    pub tooltip_template: String,
+    /// Extra Cursor accounts to switch between from the tray menu. The
+    /// default Cursor install is always available as the first account.
+    pub accounts: Vec<AccountSettings>,
+    /// Log filter, e.g. `info`, `debug` or `cursor_status_bar_lib=trace`.
+    pub log_level: String,
+    pub http: HttpSettings,
+    pub metrics: MetricsSettings,
+    pub reporting: ReportingSettings,
+    /// Extra named periods for the popup, e.g.
+    /// `{"name": "This sprint", "range": "2026-10-06..2026-10-19"}`.
+    /// See `PeriodRange` for the accepted ranges.
+    pub periods: Vec<CustomPeriod>,
+    pub anomalies: AnomalySettings,
+    pub notifications: NotificationSettings,
+    /// Outgoing webhooks for team channels and automation.
+    pub webhooks: Vec<WebhookSettings>,
+    /// Scheduled daily or weekly summaries. See `DigestSettings`.
+    pub digests: Vec<DigestSettings>,
+    /// List prices used to estimate events Cursor reports without a cost,
+    /// in dollars per million tokens. Adds to or replaces bundled entries, e.g.
+    /// `{"claude-4-opus": {"input": 15, "output": 75, "cacheRead": 1.5, "cacheWrite": 18.75}}`.
+    pub pricing: HashMap<String, ModelPrice>,
+    pub models: ModelSettings,
+}
+
+/// How model names are shown and grouped. See `ModelRegistry`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ModelSettings {
+    /// `exact`, `model` (merging modes and release tags), `family` or `vendor`.
+    pub grouping: ModelGrouping,
+    /// Reported names to treat as another model, e.g. `{"default": "claude-4-sonnet"}`.
+    pub aliases: HashMap<String, String>,
+}
+
+/// Timezone and day/week boundaries for the period buckets and exports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReportingSettings {
+    /// IANA name such as `America/New_York`; the system timezone when unset.
+    /// Exported timestamps carry this zone's offset (UTC when unset).
+    pub timezone: Option<String>,
+    /// `rolling` (the last 7 × 24 hours) or `calendar` (since `weekStart`).
+    pub week: WeekMode,
+    /// First day of a calendar week, e.g. `Mon` or `Sunday`.
+    pub week_start: Weekday,
+    /// Hour (0-23) at which a reporting day begins, for teams whose working
+    /// day runs past midnight.
+    pub day_start_hour: u32,
+}
+
+impl Default for ReportingSettings {
+    fn default() -> Self {
+        Self {
+            timezone: None,
+            week: WeekMode::Rolling,
+            week_start: Weekday::Mon,
+            day_start_hour: 0,
+        }
+    }
+}
+
+/// Spike detection over the event stream. See `anomaly::detect`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AnomalySettings {
+    pub enabled: bool,
+    /// Length of the windows spend is summed over.
+    pub window_minutes: u32,
+    /// A window is a burst when it exceeds the baseline by this factor...
+    pub burst_factor: f64,
+    /// ...and spends at least this much, so a quiet day does not make every
+    /// small session look like a spike.
+    pub min_burst_dollars: f64,
+    /// Hours before each window that its baseline is averaged over.
+    pub baseline_hours: u32,
+    /// The baseline counts as at least this much per window, so the first
+    /// session after a quiet spell is not measured against nothing.
+    pub baseline_floor_dollars: f64,
+    /// Flag any single event costing more than this.
+    pub event_ceiling_dollars: Option<f64>,
+}
+
+impl Default for AnomalySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_minutes: 10,
+            burst_factor: 5.0,
+            min_burst_dollars: 2.0,
+            baseline_hours: 24,
+            baseline_floor_dollars: 1.0,
+            event_ceiling_dollars: Some(5.0),
+        }
+    }
+}
+
+/// Desktop notifications. Every alert goes through these checks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    /// Spending bursts and expensive events.
+    pub spikes: bool,
+    /// Budget thresholds and the spending limit warning.
+    pub budget: bool,
+    /// Yesterday's spend, after the first refresh of each day.
+    pub daily_summary: bool,
+    /// Scheduled digests sent as notifications.
+    pub digests: bool,
+    /// Percentages of the budget that notify, each once per billing period.
+    pub budget_thresholds: Vec<u32>,
+    /// Percentage of the Cursor spending limit that warns, once per billing
+    /// period, before Cursor stops serving requests; 0 to turn off.
+    pub spend_limit_warning_percent: u32,
+    /// e.g. `{"start": "22:00", "end": "08:00"}`, in the reporting timezone.
+    /// Notifications in this window are recorded but not shown.
+    pub quiet_hours: Option<QuietHours>,
+    /// Most notifications shown in any hour; 0 for no limit.
+    pub max_per_hour: u32,
+    /// Identical notifications within this many minutes are dropped.
+    pub dedup_minutes: u32,
+}
+
+impl NotificationSettings {
+    pub fn allows(&self, category: Category) -> bool {
+        match category {
+            Category::Spike => self.spikes,
+            Category::Budget => self.budget,
+            Category::Summary => self.daily_summary,
+            Category::Digest => self.digests,
+            Category::Test => false,
+        }
+    }
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            spikes: true,
+            budget: true,
+            daily_summary: false,
+            digests: true,
+            budget_thresholds: vec![50, 80, 100],
+            spend_limit_warning_percent: 90,
+            quiet_hours: None,
+            max_per_hour: 6,
+            dedup_minutes: 60,
+        }
+    }
+}
+
+/// An outgoing webhook. Alerts are sent whatever the desktop notification
+/// settings say; quiet hours and the hourly cap only apply to the desktop.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookSettings {
+    /// Used in logs instead of the URL, which usually embeds a secret.
+    #[serde(default)]
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Any of [`Category::SUBSCRIBABLE`]; all of them when empty.
+    #[serde(default)]
+    pub categories: Vec<Category>,
+    /// Extra line for each message, with the tray template placeholders,
+    /// e.g. `"Period: {period.spend} of {budget}"`.
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+impl WebhookSettings {
+    pub fn wants(&self, category: Category) -> bool {
+        category == Category::Test || self.categories.is_empty() || self.categories.contains(&category)
+    }
+
+    /// Name for logs and errors: `name`, or the URL's host.
+    pub fn label(&self) -> String {
+        if !self.name.is_empty() {
+            return self.name.clone();
+        }
+        let host = self.url.split("://").nth(1).unwrap_or(&self.url);
+        host.split('/').next().unwrap_or(host).to_string()
+    }
+
+    pub fn parsed_template(&self) -> Result<Option<Template>, SettingsError> {
+        self.template
+            .as_deref()
+            .map(Template::parse)
+            .transpose()
+            .map_err(|e| SettingsError::InvalidTemplate("webhook template", e))
+    }
+}
+
+/// Where a digest goes. Each digest can have several.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DigestDelivery {
+    /// A desktop notification with the headline figures.
+    Notification,
+    /// `cursor-<key>-<first day>.<ext>` in `directory`.
+    File { directory: PathBuf },
+    /// An email through the SMTP server at `smtp` (`host:port`), or the
+    /// local `sendmail` when unset.
+    Mail {
+        to: String,
+        #[serde(default = "default_mail_from")]
+        from: String,
+        #[serde(default)]
+        smtp: Option<String>,
+    },
+    /// The webhook in settings with this `name`.
+    Webhook { webhook: String },
+}
+
+fn default_mail_from() -> String {
+    "cursor-status-bar@localhost".to_string()
+}
+
+/// A scheduled digest, under `digests` in settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestSettings {
+    /// Tells digests apart in logs and in the sent log; defaults to the frequency.
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub frequency: DigestFrequency,
+    /// Wall-clock time in the reporting timezone, e.g. `"09:00"`.
+    #[serde(default = "default_digest_time")]
+    pub time: NaiveTime,
+    /// Day a weekly digest is sent on.
+    #[serde(default = "default_digest_weekday")]
+    pub weekday: Weekday,
+    #[serde(default)]
+    pub format: DigestFormat,
+    pub deliver: Vec<DigestDelivery>,
+}
+
+fn default_digest_time() -> NaiveTime {
+    NaiveTime::from_hms_opt(9, 0, 0).unwrap()
+}
+
+fn default_digest_weekday() -> Weekday {
+    Weekday::Mon
+}
+
+impl DigestSettings {
+    pub fn key(&self) -> String {
+        match (self.name.is_empty(), self.frequency) {
+            (false, _) => self.name.clone(),
+            (true, DigestFrequency::Daily) => "daily".to_string(),
+            (true, DigestFrequency::Weekly) => "weekly".to_string(),
+        }
+    }
+
+    /// The latest scheduled time at or before `now`.
+    pub fn last_due(&self, now: DateTime<Utc>, clock: &ReportingClock) -> DateTime<Utc> {
+        let mut date = clock.local_date(now);
+        let step = match self.frequency {
+            DigestFrequency::Daily => Duration::days(1),
+            DigestFrequency::Weekly => {
+                date -= Duration::days(date.weekday().days_since(self.weekday) as i64);
+                Duration::days(7)
+            }
+        };
+        let scheduled = clock.at(date, self.time);
+        if scheduled > now {
+            clock.at(date - step, self.time)
+        } else {
+            scheduled
+        }
+    }
+
+    /// Whether a digest is due, given when it was last sent.
+    pub fn is_due(&self, now: DateTime<Utc>, last_sent: DateTime<Utc>, clock: &ReportingClock) -> bool {
+        self.last_due(now, clock) > last_sent
+    }
+}
+
+/// Timeouts, proxy and extra trusted certificates for API requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HttpSettings {
+    pub connect_timeout_secs: u64,
+    /// Maximum time to wait for each read from the connection.
+    pub read_timeout_secs: u64,
+    /// Maximum time for a whole request, including the response body.
+    pub request_timeout_secs: u64,
+    /// `http://`, `https://` or `socks5://` proxy URL. When unset, the
+    /// `HTTPS_PROXY` / `ALL_PROXY` environment variables are honoured.
+    pub proxy: Option<String>,
+    /// Comma-separated hosts that bypass `proxy`; defaults to `NO_PROXY`.
+    /// Ignored without `proxy`: proxies from the environment only follow
+    /// `NO_PROXY`.
+    pub no_proxy: Option<String>,
+    /// PEM file with extra root certificates, for TLS-intercepting proxies.
+    pub ca_certificates: Option<PathBuf>,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            read_timeout_secs: 30,
+            request_timeout_secs: 60,
+            proxy: None,
+            no_proxy: None,
+            ca_certificates: None,
+        }
+    }
+}
+
+impl HttpSettings {
+    /// A zero timeout would fail every request straight away.
+    /// Reset zero timeouts to their defaults, reporting each.
+    fn repair(&mut self, problems: &mut Vec<SettingsError>) {
+        let defaults = HttpSettings::default();
+        for (name, secs, default) in [
+            ("connectTimeoutSecs", &mut self.connect_timeout_secs, defaults.connect_timeout_secs),
+            ("readTimeoutSecs", &mut self.read_timeout_secs, defaults.read_timeout_secs),
+            ("requestTimeoutSecs", &mut self.request_timeout_secs, defaults.request_timeout_secs),
+        ] {
+            if *secs == 0 {
+                problems.push(SettingsError::InvalidHttp(name));
+                *secs = default;
+            }
+        }
+    }
+}
+
+/// An additional Cursor account, identified by its local state database.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountSettings {
+    pub name: String,
+    pub database_path: PathBuf,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            budget_dollars: None,
+            title_template: "{today.spend}".to_string(),
+            tooltip_template: "Cursor Status Bar\n\
+                Today: {today.spend} ({today.vs})\n\
+                {last7.label}: {last7.spend} ({last7.requests} req)\n\
+                Billing Period: {period.spend} ({period.requests} req)"
+                .to_string(),
+            accounts: Vec::new(),
+            log_level: "info".to_string(),
+            http: HttpSettings::default(),
+            metrics: MetricsSettings::default(),
+            reporting: ReportingSettings::default(),
+            periods: Vec::new(),
+            anomalies: AnomalySettings::default(),
+            notifications: NotificationSettings::default(),
+            webhooks: Vec::new(),
+            digests: Vec::new(),
+            pricing: HashMap::new(),
+            models: ModelSettings::default(),
+        }
+    }
+}
+
+/// Parsed title and tooltip templates.
+#[derive(Debug, Clone)]
+pub struct TrayTemplates {
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    pub title: Template,
+    pub tooltip: Template,
+}
+
+/// Optional Prometheus `/metrics` endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MetricsSettings {
+    pub enabled: bool,
+    pub listen_addr: String,
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen_addr: "127.0.0.1:9464".to_string(),
+        }
+    }
+}
+
+impl Settings {
+    /// Billing period budget, ignoring zero, negative or non-finite values.
+    pub fn budget(&self) -> Option<Money> {
+        self.budget_dollars
+            .and_then(Money::from_dollars)
+            .filter(|b| *b > Money::ZERO)
+    }
+
+    pub fn tray_templates(&self) -> Result<TrayTemplates, SettingsError> {
+        Ok(TrayTemplates {
+            title: Template::parse(&self.title_template)
+                .map_err(|e| SettingsError::InvalidTemplate("titleTemplate", e))?,
+            tooltip: Template::parse(&self.tooltip_template)
+                .map_err(|e| SettingsError::InvalidTemplate("tooltipTemplate", e))?,
+        })
+    }
+
+    pub fn pricing_table(&self) -> PricingTable {
+        PricingTable::new(&self.pricing, ModelRegistry::new(&self.models))
+    }
+
+    pub fn reporting_clock(&self) -> Result<ReportingClock, SettingsError> {
+        let r = &self.reporting;
+        Ok(ReportingClock::new(
+            r.timezone.as_deref(),
+            r.week,
+            r.week_start,
+            r.day_start_hour,
+        )?)
+    }
+
+    /// Path to the settings file inside the given config directory.
+    pub fn path_in(config_dir: &Path) -> PathBuf {
+        config_dir.join(SETTINGS_FILE)
+    }
+
+    /// Load settings from the config directory, falling back to defaults
+    /// when the file does not exist. Templates and the reporting timezone are
+    /// validated here so a typo is reported at startup rather than rendered
+    /// into the tray.
+    ///
+    /// A setting that is invalid falls back to its default on its own, and
+    /// is returned as a problem alongside the rest. Only a file that cannot
+    /// be read or is not a JSON object is an error.
+    pub fn load(config_dir: &Path) -> Result<(Settings, Vec<SettingsError>), SettingsError> {
+        let path = Self::path_in(config_dir);
+        if !path.exists() {
+            return Ok((Settings::default(), Vec::new()));
+        }
+
+        let display = path.to_string_lossy().to_string();
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| SettingsError::CannotRead(display.clone(), e.to_string()))?;
+        Self::parse(&contents).map_err(|e| SettingsError::Invalid(display, e))
+    }
+
+    /// Settings from the contents of a settings file. Fields, and entries
+    /// of list fields, that do not deserialize are left out; everything is
+    /// then validated and repaired by `repair`.
+    fn parse(contents: &str) -> Result<(Settings, Vec<SettingsError>), String> {
+        let value: serde_json::Value = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+        let serde_json::Value::Object(mut fields) = value else {
+            return Err("expected a JSON object".to_string());
+        };
+
+        let mut problems = Vec::new();
+        // A misspelt category loses only itself, not the whole webhook
+        if let Some(serde_json::Value::Array(hooks)) = fields.get_mut("webhooks") {
+            for (i, hook) in hooks.iter_mut().enumerate() {
+                if let Some(serde_json::Value::Array(categories)) = hook.get_mut("categories") {
+                    categories.retain(|c| {
+                        let known = serde_json::from_value::<Category>(c.clone())
+                            .is_ok_and(|c| Category::SUBSCRIBABLE.contains(&c));
+                        if !known {
+                            problems.push(SettingsError::UnknownCategory(format!("webhooks[{}].categories", i), c.to_string()));
+                        }
+                        known
+                    });
+                }
+            }
+        }
+        let check = |name: &str, value: serde_json::Value| {
+            serde_json::from_value::<Settings>(serde_json::Value::Object([(name.to_string(), value)].into_iter().collect()))
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        };
+        let mut valid = serde_json::Map::new();
+        for (name, value) in fields {
+            let value = match value {
+                serde_json::Value::Array(items) => serde_json::Value::Array(
+                    items
+                        .into_iter()
+                        .enumerate()
+                        .filter_map(|(i, item)| match check(&name, serde_json::Value::Array(vec![item.clone()])) {
+                            Ok(()) => Some(item),
+                            Err(e) => {
+                                problems.push(SettingsError::InvalidField(format!("{}[{}]", name, i), e));
+                                None
+                            }
+                        })
+                        .collect(),
+                ),
+                value => value,
+            };
+            match check(&name, value.clone()) {
+                Ok(()) => {
+                    valid.insert(name, value);
+                }
+                Err(e) => problems.push(SettingsError::InvalidField(name, e)),
+            }
+        }
+
+        let mut settings: Settings = serde_json::from_value(serde_json::Value::Object(valid)).map_err(|e| e.to_string())?;
+        settings.repair(&mut problems);
+        Ok((settings, problems))
+    }
+
+    /// Reset settings that deserialized but are not valid to their defaults,
+    /// reporting each to `problems`.
+    fn repair(&mut self, problems: &mut Vec<SettingsError>) {
+        let defaults = Settings::default();
+        if let Err(e) = Template::parse(&self.title_template) {
+            problems.push(SettingsError::InvalidTemplate("titleTemplate", e));
+            self.title_template = defaults.title_template;
+        }
+        if let Err(e) = Template::parse(&self.tooltip_template) {
+            problems.push(SettingsError::InvalidTemplate("tooltipTemplate", e));
+            self.tooltip_template = defaults.tooltip_template;
+        }
+        if let Err(e) = self.reporting_clock() {
+            problems.push(e);
+            self.reporting = defaults.reporting;
+        }
+        self.http.repair(problems);
+        for hook in &mut self.webhooks {
+            if let Err(e) = hook.parsed_template() {
+                problems.push(e);
+                hook.template = None;
+            }
+        }
+        let webhooks = &self.webhooks;
+        for digest in &mut self.digests {
+            let key = digest.key();
+            digest.deliver.retain(|delivery| match delivery {
+                DigestDelivery::Webhook { webhook } if !webhooks.iter().any(|h| h.name == *webhook) => {
+                    problems.push(SettingsError::UnknownWebhook(key.clone(), webhook.clone()));
+                    false
+                }
+                _ => true,
+            });
+        }
+        // With nowhere to go a digest would be rebuilt on every refresh
+        self.digests.retain(|digest| {
+            if digest.deliver.is_empty() {
+                problems.push(SettingsError::NoDelivery(digest.key()));
+            }
+            !digest.deliver.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_settings_fall_back_one_at_a_time() {
+        let (settings, problems) = Settings::parse(
+            r#"{
+                "budgetDollars": 50,
+                "titleTemplate": "{today.spnd}",
+                "tooltipTemplate": "Today: {today.spend}",
+                "reporting": { "timezone": "Mars/Olympus_Mons" },
+                "http": { "connectTimeoutSecs": 0, "readTimeoutSecs": 5 },
+                "logLevel": 3,
+                "accounts": [{ "name": "Work", "databasePath": "/tmp/work.vscdb" }, { "name": 7 }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.budget_dollars, Some(50.0));
+        assert_eq!(settings.title_template, Settings::default().title_template);
+        assert_eq!(settings.tooltip_template, "Today: {today.spend}");
+        assert!(settings.reporting.timezone.is_none());
+        assert_eq!(settings.http.connect_timeout_secs, 10);
+        assert_eq!(settings.http.read_timeout_secs, 5);
+        assert_eq!(settings.log_level, "info");
+        assert_eq!(settings.accounts.len(), 1);
+
+        let problems: Vec<String> = problems.iter().map(|e| e.to_string()).collect();
+        assert_eq!(problems.len(), 5, "{:?}", problems);
+        assert!(problems.iter().any(|p| p.starts_with("Invalid accounts[1] in settings")));
+        assert!(problems.iter().any(|p| p.starts_with("Invalid logLevel in settings")));
+        assert!(problems.iter().any(|p| p.contains("titleTemplate") && p.contains("{today.spnd}")));
+
+        assert!(Settings::parse("[]").is_err());
+        assert!(Settings::parse("{").is_err());
+    }
+
+    #[test]
+    fn invalid_webhook_settings_lose_only_the_bad_part() {
+        let (settings, problems) = Settings::parse(
+            r#"{
+                "webhooks": [
+                    { "name": "team", "url": "https://example.com/a", "categories": ["budget", "spikes"] },
+                    { "name": "mine", "url": "https://example.com/b", "template": "{period.spnd}" }
+                ],
+                "digests": [
+                    { "frequency": "daily", "deliver": [{ "type": "webhook", "webhook": "team" }, { "type": "webhook", "webhook": "ops" }] },
+                    { "frequency": "weekly", "deliver": [{ "type": "webhook", "webhook": "ops" }] }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.webhooks.len(), 2);
+        assert_eq!(settings.webhooks[0].categories, [Category::Budget]);
+        assert_eq!(settings.webhooks[1].template, None);
+        assert_eq!(settings.digests.len(), 1);
+        assert_eq!(settings.digests[0].deliver.len(), 1);
+        let problems: Vec<String> = problems.iter().map(|e| e.to_string()).collect();
+        assert_eq!(problems.len(), 5, "{:?}", problems);
+        assert_eq!(
+            problems[0],
+            "Unknown category \"spikes\" in webhooks[0].categories, ignoring it (expected spike, budget, summary or digest)"
+        );
+        assert!(problems[1].starts_with("Invalid webhook template in settings, using the default: unknown placeholder {period.spnd}"));
+        assert_eq!(problems[2], "Digest \"daily\" sends to webhook \"ops\", which is not in settings; skipping it");
+        assert_eq!(problems[4], "Digest \"weekly\" has nowhere to deliver to; skipping it");
+    }
+}
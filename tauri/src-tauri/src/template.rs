@@ -0,0 +1,380 @@
+use crate::money::Money;
+use crate::models::{PeriodComparison, PeriodSummary, UsageDisplayData};
+use crate::reporting::ReportingClock;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("unknown placeholder {{{0}}} (valid: {list})", list = placeholder_list())]
+    UnknownPlaceholder(String),
+    #[error("unclosed '{{' at position {0}")]
+    Unclosed(usize),
+    #[error("unmatched '}}' at position {0} (write '}}}}' for a literal brace)")]
+    Unmatched(usize),
+}
+
+/// Every placeholder a template may use, besides the `CUSTOM_FIELDS` of
+/// each custom period as `custom1.spend`, `custom2.label` and so on.
+///
+/// Two figures have no placeholder. Spend by request kind, because the API
+/// files most events under billing kinds rather than chat, agent or tab, so
+/// a `{agent.spend}` would mostly read $0.00. And the count of events
+/// skipped for format problems, which is a warning about the data rather
+/// than a figure and has its own row in the tray menu and window.
+const PLACEHOLDERS: &[&str] = &[
+    "today.spend", "today.requests", "today.tokens", "today.label",
+    "today.delta", "today.change", "today.trend", "today.vs",
+    "last7.spend", "last7.requests", "last7.tokens", "last7.label",
+    "last7.delta", "last7.change", "last7.trend", "last7.vs",
+    "last30.spend", "last30.requests", "last30.tokens", "last30.label",
+    "last30.delta", "last30.change", "last30.trend", "last30.vs",
+    "period.spend", "period.requests", "period.tokens", "period.start", "period.end",
+    "period.delta", "period.change", "period.trend", "period.vs",
+    "top.model", "top.spend", "top.requests", "top.tokens",
+    "models.count",
+    "budget", "budget.percent", "budget.remaining",
+    "limit", "limit.percent", "limit.remaining", "limit.used",
+    "forecast.spend", "forecast.percent",
+    "quota.used", "quota.limit", "quota.remaining", "quota.percent", "quota.ends",
+];
+
+/// Fields of `customN`; custom periods have no comparison.
+const CUSTOM_FIELDS: &[&str] = &["spend", "requests", "tokens", "label"];
+
+fn placeholder_list() -> String {
+    let custom = CUSTOM_FIELDS.iter().map(|f| format!("customN.{}", f));
+    PLACEHOLDERS.iter().map(|p| p.to_string()).chain(custom).collect::<Vec<_>>().join(", ")
+}
+
+/// True for `customN.field` with N from 1.
+fn is_custom(name: &str) -> bool {
+    let Some((group, field)) = name.split_once('.') else {
+        return false;
+    };
+    let index = group.strip_prefix("custom").and_then(|n| n.parse::<usize>().ok());
+    index.is_some_and(|n| n >= 1) && CUSTOM_FIELDS.contains(&field)
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Literal(String),
+    Placeholder(String),
+}
+
+/// A parsed title or tooltip template such as `{today.spend} · {period.spend} / {budget}`.
+/// Use `{{` and `}}` for literal braces.
+#[derive(Debug, Clone)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+/// Values a template is rendered against.
+pub struct TemplateContext<'a> {
+    pub data: &'a UsageDisplayData,
+    pub budget_dollars: Option<Money>,
+    pub now: DateTime<Utc>,
+    /// Dates are reporting days in its timezone.
+    pub clock: &'a ReportingClock,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Template, TemplateError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut pos = 0;
+
+        while let Some(c) = source[pos..].chars().next() {
+            let rest = &source[pos..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                literal.push(c);
+                pos += 2;
+            } else if c == '{' {
+                let close = rest.find('}').ok_or(TemplateError::Unclosed(pos))?;
+                let name = rest[1..close].trim();
+                if !PLACEHOLDERS.contains(&name) && !is_custom(name) {
+                    return Err(TemplateError::UnknownPlaceholder(name.to_string()));
+                }
+
+                if !literal.is_empty() {
+                    parts.push(Part::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(Part::Placeholder(name.to_string()));
+                pos += close + 1;
+            } else if c == '}' {
+                return Err(TemplateError::Unmatched(pos));
+            } else {
+                literal.push(c);
+                pos += c.len_utf8();
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Template { parts })
+    }
+
+    pub fn render(&self, ctx: &TemplateContext) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(s) => s.clone(),
+                Part::Placeholder(name) => value(name, ctx),
+            })
+            .collect()
+    }
+}
+
+const MISSING: &str = "n/a";
+
+fn value(name: &str, ctx: &TemplateContext) -> String {
+    let data = ctx.data;
+    let (group, field) = name.split_once('.').unwrap_or((name, ""));
+
+    let comparison = |c: Option<&PeriodComparison>| match (c, field) {
+        (None, _) => MISSING.to_string(),
+        (Some(c), "delta") => c.signed_delta(),
+        (Some(c), "change") => c.change(),
+        (Some(c), "trend") => c.arrow().to_string(),
+        (Some(c), _) => c.describe(),
+    };
+    let period = |p: &PeriodSummary| match field {
+        "spend" => dollars(p.spend_dollars),
+        "requests" => p.requests.to_string(),
+        "tokens" => tokens(p.tokens),
+        "label" => p.label.clone(),
+        _ => comparison(p.comparison.as_ref()),
+    };
+
+    match group {
+        "today" => period(&data.today),
+        "last7" => period(&data.last7_days),
+        "last30" => period(&data.last30_days),
+        "period" => match field {
+            "spend" => dollars(data.total_spend_dollars),
+            "requests" => data.total_requests.to_string(),
+            "tokens" => tokens(data.total_tokens),
+            "start" => date(data.billing_period_start_utc(), ctx.clock),
+            "end" => date(data.billing_period_end_utc(), ctx.clock),
+            _ => comparison(data.period_comparison.as_ref()),
+        },
+        "top" => match data.line_items.first() {
+            None => MISSING.to_string(),
+            Some(item) => match field {
+                "model" if item.display_name.is_empty() => item.model_name.clone(),
+                "model" => item.display_name.clone(),
+                "spend" => dollars(item.cost_dollars),
+                "requests" => item.request_count.to_string(),
+                _ => tokens(item.total_tokens),
+            },
+        },
+        "models" => data.line_items.len().to_string(),
+        custom if custom.starts_with("custom") => {
+            let index: usize = custom["custom".len()..].parse().unwrap_or(0);
+            match index.checked_sub(1).and_then(|i| data.custom_periods.get(i)) {
+                Some(p) => period(p),
+                None => MISSING.to_string(),
+            }
+        }
+        "budget" => match (ctx.budget_dollars, field) {
+            (None, _) => MISSING.to_string(),
+            (Some(b), "percent") => percent(data.total_spend_dollars.ratio(b)),
+            (Some(b), "remaining") => dollars(b.saturating_sub(data.total_spend_dollars)),
+            (Some(b), _) => dollars(b),
+        },
+        "limit" => match (data.spend_limit_dollars, field) {
+            (_, "used") => dollars(data.usage_based_spend_dollars),
+            (None, _) => MISSING.to_string(),
+            (Some(l), "percent") => percent(data.usage_based_spend_dollars.ratio(l)),
+            (Some(_), "remaining") => data.spend_limit_headroom().map_or_else(|| MISSING.to_string(), dollars),
+            (Some(l), _) => dollars(l),
+        },
+        "quota" => match data.main_quota() {
+            None => MISSING.to_string(),
+            Some(q) => match field {
+                "used" => q.used.to_string(),
+                "limit" => q.limit.to_string(),
+                "remaining" => (q.limit - q.used).max(0).to_string(),
+                "percent" => format!("{:.0}%", q.used as f64 * 100.0 / q.limit as f64),
+                _ if q.is_exhausted() => "now".to_string(),
+                _ => date(q.exhausted_at, ctx.clock),
+            },
+        },
+        _ => match (data.forecast_period_spend(ctx.now), field) {
+            (None, _) => MISSING.to_string(),
+            (Some(f), "percent") => ctx
+                .budget_dollars
+                .map(|b| percent(f.ratio(b)))
+                .unwrap_or_else(|| MISSING.to_string()),
+            (Some(f), _) => dollars(f),
+        },
+    }
+}
+
+fn dollars(amount: Money) -> String {
+    amount.to_string()
+}
+
+fn percent(fraction: Option<f64>) -> String {
+    match fraction {
+        Some(f) => format!("{:.0}%", f * 100.0),
+        None => MISSING.to_string(),
+    }
+}
+
+/// Compact token counts: 950, 12.3K, 4.5M.
+pub fn tokens(count: i64) -> String {
+    let n = count as f64;
+    if n >= 1_000_000.0 {
+        format!("{:.1}M", n / 1_000_000.0)
+    } else if n >= 1_000.0 {
+        format!("{:.1}K", n / 1_000.0)
+    } else {
+        count.to_string()
+    }
+}
+
+fn date(dt: Option<DateTime<Utc>>, clock: &ReportingClock) -> String {
+    dt.map(|d| clock.day_of(d).format("%b %-d").to_string())
+        .unwrap_or_else(|| MISSING.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor_api::aggregate;
+    use crate::model_registry::ModelRegistry;
+    use crate::models::{RequestQuota, UsageEvent};
+    use chrono::{Duration, TimeZone};
+
+    fn parts(source: &str) -> Vec<String> {
+        Template::parse(source)
+            .unwrap()
+            .parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(s) => format!("'{}'", s),
+                Part::Placeholder(name) => name.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_placeholders_and_literals() {
+        assert_eq!(parts("{today.spend} · {period.spend}"), ["today.spend", "' · '", "period.spend"]);
+        assert_eq!(parts("{ budget.percent }"), ["budget.percent"]);
+        assert_eq!(parts("{{today.spend}} €"), ["'{today.spend} €'"]);
+        assert!(parts("").is_empty());
+    }
+
+    #[test]
+    fn reports_where_a_template_is_wrong() {
+        let err = |source: &str| Template::parse(source).unwrap_err();
+        assert!(matches!(err("{today.spnd}"), TemplateError::UnknownPlaceholder(name) if name == "today.spnd"));
+        assert!(matches!(err("Spend: {today.spend"), TemplateError::Unclosed(7)));
+        assert!(matches!(err("€ today}"), TemplateError::Unmatched(9)));
+        assert!(err("{}").to_string().starts_with("unknown placeholder {} (valid: today.spend,"));
+        assert!(err("{}").to_string().ends_with("customN.tokens, customN.label)"));
+    }
+
+    #[test]
+    fn custom_periods_are_numbered_from_one() {
+        assert_eq!(parts("{custom1.spend}{custom12.label}"), ["custom1.spend", "custom12.label"]);
+        for bad in ["{custom0.spend}", "{custom.spend}", "{customx.spend}", "{custom1.delta}", "{custom1}"] {
+            assert!(Template::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 15, 12, 0, 0).unwrap()
+    }
+
+    /// $30 on Sonnet an hour ago and $10 on GPT-5 three days ago, in a
+    /// billing period that started on March 1.
+    fn data(with_events: bool) -> UsageDisplayData {
+        let events = [
+            UsageEvent::test("claude-4-sonnet", now() - Duration::hours(1)).tokens(1000, 500, 0, 0).cents(3000.0),
+            UsageEvent::test("gpt-5", now() - Duration::days(3)).tokens(100, 50, 0, 0).cents(1000.0),
+        ];
+        let events = if with_events { &events[..] } else { &[] };
+        let start = Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap();
+        aggregate(start, events, now(), &ReportingClock::default(), &ModelRegistry::default())
+    }
+
+    fn render(source: &str, data: &UsageDisplayData, budget: Option<f64>) -> String {
+        // Days start at 04:00 UTC
+        let clock = ReportingClock::new(Some("UTC"), Default::default(), chrono::Weekday::Mon, 4).unwrap();
+        Template::parse(source).unwrap().render(&TemplateContext {
+            data,
+            budget_dollars: budget.and_then(Money::from_dollars),
+            now: now(),
+            clock: &clock,
+        })
+    }
+
+    #[test]
+    fn renders_budget_and_spending_limit() {
+        let mut data = data(true);
+        let budget = "{budget} {budget.percent} {budget.remaining}";
+        assert_eq!(render(budget, &data, Some(50.0)), "$50.00 80% $10.00");
+        // Over budget nothing is left rather than a negative amount
+        assert_eq!(render(budget, &data, Some(30.0)), "$30.00 133% $0.00");
+
+        data.spend_limit_dollars = Money::from_dollars(100.0);
+        data.usage_based_spend_dollars = Money::from_dollars(25.0).unwrap();
+        assert_eq!(
+            render("{limit} {limit.percent} {limit.remaining} {limit.used}", &data, None),
+            "$100.00 25% $75.00 $25.00"
+        );
+    }
+
+    #[test]
+    fn renders_n_a_for_what_is_not_known() {
+        let data = data(false);
+        assert_eq!(
+            render(
+                "{budget} {budget.percent} {budget.remaining} {limit} {limit.remaining} {quota.ends} {top.model} {custom1.spend} {forecast.percent}",
+                &data,
+                None
+            ),
+            "n/a n/a n/a n/a n/a n/a n/a n/a n/a"
+        );
+        // Spend towards the limit is known without a limit
+        assert_eq!(render("{limit.used} {models.count}", &data, None), "$0.00 0");
+    }
+
+    #[test]
+    fn renders_quota_top_model_and_custom_periods() {
+        let mut data = data(true);
+        data.quotas = vec![RequestQuota {
+            model: "gpt-4".to_string(),
+            label: "premium".to_string(),
+            used: 500,
+            limit: 500,
+            exhausted_at: None,
+        }];
+        let quota = "{quota.used}/{quota.limit} {quota.remaining} {quota.percent} {quota.ends}";
+        assert_eq!(render(quota, &data, None), "500/500 0 100% now");
+        data.quotas[0].used = 250;
+        // 02:00 on the 20th is still the 19th with days starting at 04:00
+        data.quotas[0].exhausted_at = Some(Utc.with_ymd_and_hms(2026, 3, 20, 2, 0, 0).unwrap());
+        assert_eq!(render(quota, &data, None), "250/500 250 50% Mar 19");
+
+        data.line_items[0].display_name = "Claude 4 Sonnet".to_string();
+        assert_eq!(render("{top.model}: {top.spend} ({top.requests} req)", &data, None), "Claude 4 Sonnet: $30.00 (1 req)");
+        data.line_items[0].display_name.clear();
+        assert_eq!(render("{top.model}", &data, None), "claude-4-sonnet");
+
+        data.custom_periods.push(PeriodSummary {
+            label: "Sprint".to_string(),
+            requests: 3,
+            spend_dollars: Money::from_dollars(12.0).unwrap(),
+            tokens: 4500,
+            comparison: None,
+        });
+        assert_eq!(
+            render("{custom1.label}: {custom1.spend} ({custom1.requests} req, {custom1.tokens}) {custom2.spend}", &data, None),
+            "Sprint: $12.00 (3 req, 4.5K) n/a"
+        );
+    }
+}
@@ -1,11 +1,12 @@
+use crate::secret::Secret;
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine;
 use rusqlite::Connection;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 pub struct TokenInfo {
-    pub session_token: String,
+    pub session_token: Secret,
     pub user_id: String,
 }
 
@@ -26,7 +27,7 @@ pub enum TokenError {
 }
 
 /// Path to the Cursor SQLite database, platform-aware.
-fn database_path() -> PathBuf {
+pub fn database_path() -> PathBuf {
     let base = if cfg!(target_os = "macos") {
         dirs::home_dir()
             .unwrap()
@@ -44,30 +45,37 @@ fn database_path() -> PathBuf {
 /// Returns (session_token, user_id) where session_token is formatted as
 /// "{userId}%3A%3A{jwtToken}" for use as the WorkosCursorSessionToken cookie.
 pub fn extract_token() -> Result<TokenInfo, TokenError> {
-    let db_path = database_path();
+    extract_token_from(&database_path())
+}
 
+/// Same as `extract_token`, but reads a specific `state.vscdb` — used for
+/// additional accounts (e.g. a second Cursor profile) configured in settings.
+pub fn extract_token_from(db_path: &Path) -> Result<TokenInfo, TokenError> {
     if !db_path.exists() {
+        tracing::debug!(db = %db_path.display(), "Cursor database not found");
         return Err(TokenError::DatabaseNotFound(
             db_path.to_string_lossy().to_string(),
         ));
     }
 
-    let conn = Connection::open_with_flags(&db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+    let conn = Connection::open_with_flags(db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
         .map_err(|e| TokenError::CannotOpen(e.to_string()))?;
 
-    let jwt_token: String = conn
+    // Wiped on drop, whichever way this returns
+    let jwt_token: Secret = conn
         .query_row(
             "SELECT value FROM ItemTable WHERE key = 'cursorAuth/accessToken'",
             [],
-            |row| row.get(0),
+            |row| row.get(0).map(Secret::new),
         )
         .map_err(|e| match e {
             rusqlite::Error::QueryReturnedNoRows => TokenError::TokenNotFound,
             other => TokenError::QueryFailed(other.to_string()),
         })?;
 
-    let user_id = extract_user_id_from_jwt(&jwt_token)?;
-    let session_token = format!("{}%3A%3A{}", user_id, jwt_token);
+    let user_id = extract_user_id_from_jwt(jwt_token.expose())?;
+    let session_token = Secret::new(format!("{}%3A%3A{}", user_id, jwt_token.expose()));
+    tracing::debug!(db = %db_path.display(), "extracted session token");
 
     Ok(TokenInfo {
         session_token,
@@ -0,0 +1,191 @@
+use std::f64::consts::TAU;
+use tauri::image::Image;
+
+const SIZE: u32 = 64;
+const OUTER_RADIUS: f64 = 30.0;
+const INNER_RADIUS: f64 = 21.0;
+
+// Same palette as the popup's spend colours (style.css)
+const GREEN: [u8; 3] = [0x66, 0xe6, 0x8a];
+const AMBER: [u8; 3] = [0xff, 0xbf, 0x4d];
+const RED: [u8; 3] = [0xff, 0x66, 0x66];
+const GREY: [u8; 3] = [0x80, 0x80, 0x80];
+
+/// What the tray icon should show.
+#[derive(Debug, Clone, Copy)]
+pub enum IconState {
+    /// Fresh data. `budget_fraction` is spend / budget, or `None` when no
+    /// budget is configured, in which case the ring is coloured by `spend_dollars`.
+    Usage {
+        spend_dollars: f64,
+        budget_fraction: Option<f64>,
+    },
+    /// The last refresh failed but older data is still shown.
+    Stale { budget_fraction: Option<f64> },
+    /// No usable data at all (token or API error).
+    Error,
+}
+
+/// Render the tray icon as a progress ring: the filled arc is the share of
+/// the budget used, coloured green / amber / red as it fills up.
+pub fn render(state: IconState) -> Image<'static> {
+    let (fraction, color, alpha) = match state {
+        IconState::Usage { budget_fraction: Some(f), .. } => (f, budget_color(f), 255),
+        IconState::Usage { spend_dollars, budget_fraction: None } => (1.0, spend_color(spend_dollars), 255),
+        IconState::Stale { budget_fraction } => (budget_fraction.unwrap_or(1.0), GREY, 160),
+        IconState::Error => (0.0, RED, 255),
+    };
+
+    let mut rgba = vec![0u8; (SIZE * SIZE * 4) as usize];
+    let center = SIZE as f64 / 2.0;
+    let track_color = if matches!(state, IconState::Error) { RED } else { GREY };
+    let track_alpha = if matches!(state, IconState::Error) { 255 } else { 70 };
+
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let dx = x as f64 + 0.5 - center;
+            let dy = y as f64 + 0.5 - center;
+            let dist = (dx * dx + dy * dy).sqrt();
+
+            // Anti-aliased ring coverage
+            let coverage = (OUTER_RADIUS + 0.5 - dist).clamp(0.0, 1.0) * (dist - INNER_RADIUS + 0.5).clamp(0.0, 1.0);
+            if coverage > 0.0 {
+                // Angle clockwise from 12 o'clock, in [0, 1)
+                let angle = (dx.atan2(-dy).rem_euclid(TAU)) / TAU;
+                let (rgb, a) = if angle < fraction.min(1.0) {
+                    (color, alpha)
+                } else {
+                    (track_color, track_alpha)
+                };
+                put(&mut rgba, x, y, rgb, (a as f64 * coverage) as u8);
+            }
+
+            if matches!(state, IconState::Error) && in_exclamation(x, y) {
+                put(&mut rgba, x, y, RED, 255);
+            }
+        }
+    }
+
+    Image::new_owned(rgba, SIZE, SIZE)
+}
+
+fn put(rgba: &mut [u8], x: u32, y: u32, rgb: [u8; 3], alpha: u8) {
+    let i = ((y * SIZE + x) * 4) as usize;
+    rgba[i..i + 3].copy_from_slice(&rgb);
+    rgba[i + 3] = alpha;
+}
+
+/// A "!" centred inside the ring.
+fn in_exclamation(x: u32, y: u32) -> bool {
+    let bar = (29..35).contains(&x) && (17..37).contains(&y);
+    let dot = (29..35).contains(&x) && (41..47).contains(&y);
+    bar || dot
+}
+
+fn budget_color(fraction: f64) -> [u8; 3] {
+    if fraction >= 1.0 {
+        RED
+    } else if fraction >= 0.75 {
+        AMBER
+    } else {
+        GREEN
+    }
+}
+
+/// Mirrors `spendColorClass` in main.js for when no budget is set.
+fn spend_color(dollars: f64) -> [u8; 3] {
+    if dollars >= 50.0 {
+        RED
+    } else if dollars >= 10.0 {
+        AMBER
+    } else if dollars > 0.0 {
+        GREEN
+    } else {
+        GREY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pixels in the middle of the ring at 12, 6 and 9 o'clock; 12 o'clock
+    // is just past the start of the arc and 9 o'clock three quarters round.
+    const TOP: (u32, u32) = (32, 6);
+    const BOTTOM: (u32, u32) = (32, 57);
+    const LEFT: (u32, u32) = (6, 31);
+
+    fn pixel(image: &Image, (x, y): (u32, u32)) -> [u8; 4] {
+        let i = ((y * image.width() + x) * 4) as usize;
+        image.rgba()[i..i + 4].try_into().unwrap()
+    }
+
+    fn rgba(rgb: [u8; 3], alpha: u8) -> [u8; 4] {
+        [rgb[0], rgb[1], rgb[2], alpha]
+    }
+
+    fn budget(fraction: f64) -> Image<'static> {
+        render(IconState::Usage {
+            spend_dollars: 0.0,
+            budget_fraction: Some(fraction),
+        })
+    }
+
+    #[test]
+    fn every_state_fills_a_square_rgba_buffer() {
+        for state in [
+            IconState::Usage { spend_dollars: 12.0, budget_fraction: Some(0.4) },
+            IconState::Usage { spend_dollars: 12.0, budget_fraction: None },
+            IconState::Stale { budget_fraction: None },
+            IconState::Error,
+        ] {
+            let image = render(state);
+            assert_eq!((image.width(), image.height()), (SIZE, SIZE));
+            assert_eq!(image.rgba().len(), (SIZE * SIZE * 4) as usize);
+        }
+    }
+
+    #[test]
+    fn ring_fills_and_changes_colour_with_the_budget() {
+        let half = budget(0.5);
+        assert_eq!(pixel(&half, TOP), rgba(GREEN, 255));
+        assert_eq!(pixel(&half, LEFT), rgba(GREY, 70));
+        // The centre stays transparent
+        assert_eq!(pixel(&half, (32, 32))[3], 0);
+
+        assert_eq!(pixel(&budget(0.74), TOP), rgba(GREEN, 255));
+        assert_eq!(pixel(&budget(0.75), TOP), rgba(AMBER, 255));
+        assert_eq!(pixel(&budget(0.75), BOTTOM), rgba(AMBER, 255));
+        assert_eq!(pixel(&budget(0.75), LEFT), rgba(GREY, 70));
+        assert_eq!(pixel(&budget(0.99), LEFT), rgba(AMBER, 255));
+        assert_eq!(pixel(&budget(1.0), TOP), rgba(RED, 255));
+        assert_eq!(pixel(&budget(1.6), LEFT), rgba(RED, 255));
+    }
+
+    #[test]
+    fn without_a_budget_the_full_ring_shows_the_spend_level() {
+        let spend = |dollars| render(IconState::Usage { spend_dollars: dollars, budget_fraction: None });
+        assert_eq!(pixel(&spend(0.0), LEFT), rgba(GREY, 255));
+        assert_eq!(pixel(&spend(5.0), LEFT), rgba(GREEN, 255));
+        assert_eq!(pixel(&spend(10.0), LEFT), rgba(AMBER, 255));
+        assert_eq!(pixel(&spend(50.0), LEFT), rgba(RED, 255));
+    }
+
+    #[test]
+    fn stale_and_error_icons_stand_out() {
+        let stale = render(IconState::Stale { budget_fraction: Some(0.5) });
+        assert_eq!(pixel(&stale, TOP), rgba(GREY, 160));
+        assert_eq!(pixel(&stale, LEFT), rgba(GREY, 70));
+        let stale = render(IconState::Stale { budget_fraction: None });
+        assert_eq!(pixel(&stale, LEFT), rgba(GREY, 160));
+
+        let error = render(IconState::Error);
+        assert_eq!(pixel(&error, TOP), rgba(RED, 255));
+        assert_eq!(pixel(&error, LEFT), rgba(RED, 255));
+        // A "!" inside the ring: bar, gap, dot
+        assert_eq!(pixel(&error, (31, 20)), rgba(RED, 255));
+        assert_eq!(pixel(&error, (31, 39))[3], 0);
+        assert_eq!(pixel(&error, (31, 44)), rgba(RED, 255));
+        assert_eq!(pixel(&budget(0.5), (31, 20))[3], 0);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::models::LineItem;
+use crate::AppState;
+use chrono::{DateTime, Utc};
+use tauri::menu::{CheckMenuItemBuilder, Menu, MenuBuilder, MenuItemBuilder, SubmenuBuilder};
+use tauri::{AppHandle, Wry};
+
+/// How many models the "Top Models" submenu lists.
+const TOP_MODELS: usize = 8;
+
+/// Prefix of the account switcher item ids; the suffix is the account index.
+pub const ACCOUNT_PREFIX: &str = "account:";
+
+/// Build the tray menu for the current state. Called at startup and after
+/// every refresh, so the figures are visible even where the tray has no
+/// hover tooltip (most Linux desktops).
+pub fn build(app: &AppHandle, state: &AppState) -> tauri::Result<Menu<Wry>> {
+    let now = Utc::now();
+    let info = |id: &str, text: String| MenuItemBuilder::with_id(id, text).enabled(false).build(app);
+
+    let mut menu = MenuBuilder::new(app);
+
+    if let Some(error) = &state.error {
+        menu = menu.item(&info("info:error", format!("⚠ {}", first_line(error)))?);
+    }
+    if let Some(warning) = state.schema_drift.warning() {
+        menu = menu.item(&info("info:warning", format!("⚠ {}", warning))?);
+    }
+    for (i, warning) in state.settings_warnings.iter().enumerate() {
+        menu = menu.item(&info(&format!("info:settings-warning:{}", i), format!("⚠ {}", warning))?);
+    }
+
+    match &state.last_data {
+        Some(data) => {
+            for (id, p) in [
+                ("info:today", &data.today),
+                ("info:last7", &data.last7_days),
+            ] {
+                menu = menu.item(&info(id, format!("{}: {} ({} req)", p.label, p.spend_dollars, p.requests))?);
+            }
+            menu = menu.item(&info(
+                "info:period",
+                format!("Billing Period: {} ({} req)", data.total_spend_dollars, data.total_requests),
+            )?);
+
+            if let Some(forecast) = data.forecast_period_spend(now) {
+                let text = match state.budget() {
+                    Some(budget) => format!("Forecast: {} of {} budget", forecast, budget),
+                    None => format!("Forecast: {}", forecast),
+                };
+                menu = menu.item(&info("info:forecast", text)?);
+            }
+            if let (Some(limit), Some(headroom)) = (data.spend_limit_dollars, data.spend_limit_headroom()) {
+                menu = menu.item(&info("info:spend-limit", format!("Spending limit: {} left of {}", headroom, limit))?);
+            }
+            if let Some(quota) = data.main_quota() {
+                menu = menu.item(&info("info:quota", quota.describe())?);
+                menu = menu.item(&info("info:quota-outlook", quota.outlook(data.billing_period_end_utc(), &state.clock))?);
+            }
+
+            let mut models = SubmenuBuilder::with_id(app, "info:models", "Top Models");
+            if data.line_items.is_empty() {
+                models = models.item(&info("info:model:none", "No usage yet".to_string())?);
+            }
+            for (i, label) in top_model_labels(&data.line_items).into_iter().enumerate() {
+                models = models.item(&info(&format!("info:model:{}", i), label)?);
+            }
+            menu = menu.item(&models.build()?);
+        }
+        None if state.error.is_none() => {
+            menu = menu.item(&info("info:loading", "Loading...".to_string())?);
+        }
+        None => {}
+    }
+
+    if let Some(stale) = state.stale_text() {
+        menu = menu.item(&info("info:updated", stale)?);
+    } else if let Some(updated) = state.last_updated {
+        menu = menu.item(&info("info:updated", last_updated_text(updated, now))?);
+    }
+
+    let accounts = state.account_names();
+    if accounts.len() > 1 {
+        let mut switcher = SubmenuBuilder::with_id(app, "accounts", "Account");
+        for (i, name) in accounts.iter().enumerate() {
+            let item = CheckMenuItemBuilder::with_id(format!("{}{}", ACCOUNT_PREFIX, i), name)
+                .checked(i == state.active_account)
+                .build(app)?;
+            switcher = switcher.item(&item);
+        }
+        menu = menu.separator().item(&switcher.build()?);
+    } else {
+        menu = menu.separator();
+    }
+
+    let pause = CheckMenuItemBuilder::with_id("pause", "Pause Polling")
+        .checked(state.paused)
+        .build(app)?;
+
+    menu = menu
+        .item(&pause)
+        .separator()
+        .text("refresh", "Refresh Now")
+        .text("dashboard", "Open Cursor Dashboard")
+        .text("export", "Export Billing Period (CSV)");
+    if !state.settings.webhooks.is_empty() {
+        menu = menu.text("test-webhooks", "Send Test Webhook");
+    }
+    menu.text("diagnostics", "Copy Diagnostics")
+        .text("quit", "Quit")
+        .build()
+}
+
+/// The first `TOP_MODELS` line items, which come sorted by spend, with "~"
+/// before costs that are partly estimates.
+fn top_model_labels(items: &[LineItem]) -> Vec<String> {
+    items
+        .iter()
+        .take(TOP_MODELS)
+        .map(|item| {
+            format!(
+                "{} — {}{} ({} req)",
+                item.display_name,
+                if item.estimated_requests > 0 { "~" } else { "" },
+                item.cost_dollars,
+                item.request_count
+            )
+        })
+        .collect()
+}
+
+fn last_updated_text(updated: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    match (now - updated).num_minutes() {
+        // A clock that moved back counts as just now
+        n if n <= 0 => "Updated just now".to_string(),
+        1 => "Updated 1 min ago".to_string(),
+        n if n < 60 => format!("Updated {} min ago", n),
+        n => format!("Updated {} h ago", n / 60),
+    }
+}
+
+/// Menu items are single-line; API errors can carry a multi-line body.
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::Money;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn last_updated_rounds_down_to_minutes_then_hours() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 15, 12, 0, 0).unwrap();
+        let ago = |seconds| last_updated_text(now - Duration::seconds(seconds), now);
+        assert_eq!(ago(0), "Updated just now");
+        assert_eq!(ago(59), "Updated just now");
+        assert_eq!(ago(-30), "Updated just now");
+        assert_eq!(ago(60), "Updated 1 min ago");
+        assert_eq!(ago(119), "Updated 1 min ago");
+        assert_eq!(ago(120), "Updated 2 min ago");
+        assert_eq!(ago(59 * 60 + 59), "Updated 59 min ago");
+        assert_eq!(ago(60 * 60), "Updated 1 h ago");
+        assert_eq!(ago(2 * 60 * 60 - 1), "Updated 1 h ago");
+        assert_eq!(ago(26 * 60 * 60), "Updated 26 h ago");
+    }
+
+    fn item(name: &str, dollars: f64, requests: i32, estimated_requests: i32) -> LineItem {
+        LineItem {
+            model_name: name.to_lowercase(),
+            group: name.to_lowercase(),
+            display_name: name.to_string(),
+            request_count: requests,
+            cost_dollars: Money::from_dollars(dollars).unwrap(),
+            total_tokens: 0,
+            estimated_dollars: Money::ZERO,
+            estimated_requests,
+            price_discrepancy: None,
+        }
+    }
+
+    #[test]
+    fn lists_the_top_models_in_order() {
+        assert!(top_model_labels(&[]).is_empty());
+
+        let items: Vec<LineItem> = (0..10).map(|i| item(&format!("Model {}", i), 10.0 - i as f64, 1, 0)).collect();
+        let labels = top_model_labels(&items);
+        assert_eq!(labels.len(), TOP_MODELS);
+        assert_eq!(labels[0], "Model 0 — $10.00 (1 req)");
+        assert_eq!(labels[TOP_MODELS - 1], "Model 7 — $3.00 (1 req)");
+
+        let labels = top_model_labels(&[item("Claude 4 Sonnet", 4.2, 12, 0), item("GPT-5", 1.25, 3, 1)]);
+        assert_eq!(labels, ["Claude 4 Sonnet — $4.20 (12 req)", "GPT-5 — ~$1.25 (3 req)"]);
+    }
+}
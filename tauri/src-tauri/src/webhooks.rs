@@ -0,0 +1,232 @@
+use crate::models::UsageDisplayData;
+use crate::notifications::Notification;
+use crate::secret;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Response bodies kept in errors; chat services can send whole HTML pages.
+const MAX_BODY_LEN: usize = 200;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("Webhook returned HTTP {0}: {1}")]
+    Status(StatusCode, String),
+    /// The URL is stripped: for most chat services it is the credential.
+    #[error("Webhook request failed: {0}")]
+    Http(reqwest::Error),
+}
+
+impl WebhookError {
+    /// Network errors, rate limiting and server errors may clear up;
+    /// anything else (a bad URL, a rejected payload) will not.
+    fn is_retryable(&self) -> bool {
+        match self {
+            WebhookError::Status(status, _) => status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS,
+            WebhookError::Http(_) => true,
+        }
+    }
+}
+
+/// Request body shape.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WebhookFormat {
+    /// `{"text": ...}`; Slack incoming webhooks, Mattermost and most chat tools.
+    #[default]
+    Slack,
+    /// `{"content": ...}`.
+    Discord,
+    /// The alert plus the full usage data, for scripts and automation.
+    Json,
+}
+
+/// How often and how patiently to try a delivery.
+#[derive(Debug, Clone, Copy)]
+pub struct Retry {
+    pub attempts: u32,
+    /// Wait before the second attempt; doubled after each failure.
+    pub initial_delay: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            attempts: 4,
+            initial_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+impl Retry {
+    /// A single attempt, for the "send test" action where the user is waiting.
+    pub fn once() -> Self {
+        Self {
+            attempts: 1,
+            initial_delay: Duration::ZERO,
+        }
+    }
+}
+
+/// Request body for `notification`. `extra` is the hook's rendered template
+/// line, if it has one.
+pub fn payload(
+    format: WebhookFormat,
+    notification: &Notification,
+    extra: Option<&str>,
+    data: Option<&UsageDisplayData>,
+) -> Value {
+    let mut text = notification.body.clone();
+    if let Some(extra) = extra {
+        text.push('\n');
+        text.push_str(extra);
+    }
+    match format {
+        WebhookFormat::Slack => json!({ "text": format!("*{}*\n{}", notification.title, text) }),
+        WebhookFormat::Discord => json!({ "content": format!("**{}**\n{}", notification.title, text) }),
+        WebhookFormat::Json => json!({
+            "category": notification.category,
+            "title": notification.title,
+            "text": text,
+            "usage": data,
+        }),
+    }
+}
+
+/// POST `payload` to `url`, retrying transient failures with exponential backoff.
+pub async fn deliver(client: &Client, url: &str, payload: &Value, retry: Retry) -> Result<(), WebhookError> {
+    let mut delay = retry.initial_delay;
+    let mut attempt = 1;
+    loop {
+        match post(client, url, payload).await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < retry.attempts && e.is_retryable() => {
+                tracing::warn!(attempt, error = %secret::sanitize_error(&e.to_string()), "webhook delivery failed, retrying");
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+async fn post(client: &Client, url: &str, payload: &Value) -> Result<(), WebhookError> {
+    let resp = client
+        .post(url)
+        .json(payload)
+        .send()
+        .await
+        .map_err(|e| WebhookError::Http(e.without_url()))?;
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let mut body = resp.text().await.unwrap_or_default();
+    if let Some((cut, _)) = body.char_indices().nth(MAX_BODY_LEN) {
+        body.truncate(cut);
+        body.push('…');
+    }
+    Err(WebhookError::Status(status, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::Category;
+    use axum::extract::State;
+    use axum::http::StatusCode as AxumStatus;
+    use std::sync::{Arc, Mutex};
+
+    /// Received bodies, and statuses still to answer with (then 200).
+    #[derive(Default)]
+    struct StandIn {
+        received: Mutex<Vec<Value>>,
+        statuses: Mutex<Vec<u16>>,
+    }
+
+    async fn receive(State(stand_in): State<Arc<StandIn>>, body: axum::body::Bytes) -> AxumStatus {
+        stand_in.received.lock().unwrap().push(serde_json::from_slice(&body).unwrap());
+        let mut statuses = stand_in.statuses.lock().unwrap();
+        let status = if statuses.is_empty() { 200 } else { statuses.remove(0) };
+        AxumStatus::from_u16(status).unwrap()
+    }
+
+    /// A local chat service answering with `statuses` in turn.
+    async fn stand_in(statuses: &[u16]) -> (String, Arc<StandIn>) {
+        let shared = Arc::new(StandIn {
+            statuses: Mutex::new(statuses.to_vec()),
+            ..Default::default()
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hooks/T000/B000/secret", listener.local_addr().unwrap());
+        let router = axum::Router::new().fallback(receive).with_state(shared.clone());
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, router).await;
+        });
+        (url, shared)
+    }
+
+    fn alert() -> Notification {
+        Notification {
+            category: Category::Budget,
+            key: "budget:test".to_string(),
+            title: "Cursor budget 80% used".to_string(),
+            body: "$400.00 of $500.00 spent this billing period".to_string(),
+        }
+    }
+
+    fn quick() -> Retry {
+        Retry {
+            attempts: 3,
+            initial_delay: Duration::from_millis(1),
+        }
+    }
+
+    #[test]
+    fn payloads_match_each_format() {
+        let slack = payload(WebhookFormat::Slack, &alert(), Some("Today: $4.20"), None);
+        assert_eq!(
+            slack,
+            json!({"text": "*Cursor budget 80% used*\n$400.00 of $500.00 spent this billing period\nToday: $4.20"})
+        );
+        let discord = payload(WebhookFormat::Discord, &alert(), None, None);
+        assert_eq!(discord["content"], "**Cursor budget 80% used**\n$400.00 of $500.00 spent this billing period");
+        let generic = payload(WebhookFormat::Json, &alert(), None, None);
+        assert_eq!(generic["category"], "budget");
+        assert_eq!(generic["usage"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_until_delivered() {
+        let (url, server) = stand_in(&[503, 500]).await;
+        let body = payload(WebhookFormat::Slack, &alert(), None, None);
+        deliver(&Client::new(), &url, &body, quick()).await.unwrap();
+        let received = server.received.lock().unwrap();
+        assert_eq!(received.len(), 3);
+        assert!(received.iter().all(|b| *b == body));
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_client_errors_and_after_the_last_attempt() {
+        let (url, server) = stand_in(&[400]).await;
+        let body = payload(WebhookFormat::Slack, &alert(), None, None);
+        let err = deliver(&Client::new(), &url, &body, quick()).await.unwrap_err();
+        assert!(matches!(err, WebhookError::Status(StatusCode::BAD_REQUEST, _)));
+        assert_eq!(server.received.lock().unwrap().len(), 1);
+
+        let (url, server) = stand_in(&[500, 500, 500, 500]).await;
+        let err = deliver(&Client::new(), &url, &body, quick()).await.unwrap_err();
+        assert!(matches!(err, WebhookError::Status(StatusCode::INTERNAL_SERVER_ERROR, _)));
+        assert_eq!(server.received.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn network_errors_do_not_leak_the_url() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let url = format!("http://127.0.0.1:{}/hooks/T000/B000/secret", port);
+        let err = deliver(&Client::new(), &url, &json!({}), Retry::once()).await.unwrap_err();
+        assert!(!err.to_string().contains("secret"));
+    }
+}
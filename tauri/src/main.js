@@ -9,30 +9,93 @@ function spendColorClass(dollars) {
   return "spend-dim";
 }
 
-function formatModelName(name) {
-  return name
-    .replace(/-high-thinking/g, " (thinking)")
-    .replace(/-preview/g, "");
+function escapeHtml(text) {
+  const div = document.createElement("div");
+  div.textContent = text;
+  return div.innerHTML;
 }
 
 function formatDollars(amount) {
   return "$" + amount.toFixed(2);
 }
 
+const ERROR_KIND_LABELS = {
+  offline: "offline",
+  auth: "signed out",
+  api: "API error",
+};
+
+function renderStale(el, status) {
+  if (!status.stale || !status.fetchedAt) {
+    el.style.display = "none";
+    return;
+  }
+  const since = new Date(status.fetchedAt).toLocaleTimeString([], {
+    hour: "2-digit",
+    minute: "2-digit",
+  });
+  const reason = ERROR_KIND_LABELS[status.errorKind];
+  el.textContent = reason ? `Stale since ${since} (${reason})` : `Stale since ${since}`;
+  el.style.display = "block";
+}
+
+function renderSettingsWarnings(el, warnings) {
+  el.innerHTML = "";
+  warnings.forEach((w) => {
+    const row = document.createElement("div");
+    row.textContent = w;
+    el.appendChild(row);
+  });
+  el.style.display = warnings.length ? "block" : "none";
+}
+
+const TREND_ARROWS = { up: "\u2191", down: "\u2193", flat: "\u2192" };
+
+// "↑ 35%" against the previous window, or the dollar change when there was
+// no spend to take a percentage of.
+function formatComparison(c) {
+  if (!c) return "";
+  const change =
+    c.deltaFraction != null
+      ? Math.abs(Math.round(c.deltaFraction * 100)) + "%"
+      : formatDollars(Math.abs(c.deltaDollars));
+  return `<span class="period-trend trend-${c.trend}" title="vs ${escapeHtml(c.label)}">${TREND_ARROWS[c.trend]} ${change}</span>`;
+}
+
 function renderPeriods(container, periods) {
   container.innerHTML = "";
   periods.forEach((p) => {
     const row = document.createElement("div");
     row.className = "period-row";
     row.innerHTML = `
-      <span class="period-label">${p.label}</span>
+      <span class="period-label">${escapeHtml(p.label)}</span>
       <span class="period-spend ${spendColorClass(p.spendDollars)}">${formatDollars(p.spendDollars)}</span>
       <span class="period-reqs">(${p.requests} req)</span>
+      ${formatComparison(p.comparison)}
     `;
     container.appendChild(row);
   });
 }
 
+// "~" marks costs that are partly list-price estimates; "!" marks charges
+// that differ noticeably from list price
+function formatModelCost(item) {
+  const notes = [];
+  let marks = "";
+  if (item.estimatedRequests > 0) {
+    marks += "~";
+    notes.push(`${formatDollars(item.estimatedDollars)} estimated for ${item.estimatedRequests} req without a reported cost`);
+  }
+  if (item.priceDiscrepancy != null) {
+    marks += "!";
+    const percent = Math.round(Math.abs(item.priceDiscrepancy) * 100);
+    notes.push(`Charged ${percent}% ${item.priceDiscrepancy > 0 ? "above" : "below"} list price`);
+  }
+  const title = notes.length ? ` title="${escapeHtml(notes.join("; "))}"` : "";
+  const flag = item.priceDiscrepancy != null ? " price-discrepancy" : "";
+  return `<span class="model-cost ${spendColorClass(item.costDollars)}${flag}"${title}>${marks}${formatDollars(item.costDollars)}</span>`;
+}
+
 function renderModels(container, lineItems) {
   container.innerHTML = "";
   const items = lineItems.slice(0, 5);
@@ -40,10 +103,131 @@ function renderModels(container, lineItems) {
     const row = document.createElement("div");
     row.className = "model-row";
     row.innerHTML = `
-      <span class="model-name">${formatModelName(item.modelName)}</span>
+      <span class="model-name">${escapeHtml(item.displayName || item.modelName)}</span>
       <span class="model-reqs">${item.requestCount} req</span>
       <span class="model-dash">&mdash;</span>
-      <span class="model-cost ${spendColorClass(item.costDollars)}">${formatDollars(item.costDollars)}</span>
+      ${formatModelCost(item)}
+    `;
+    container.appendChild(row);
+  });
+}
+
+// Included requests, and when usage-based pricing starts at the current rate
+function renderQuota(el, data) {
+  const q = (data.quotas || []).find((x) => x.model === "gpt-4") || (data.quotas || [])[0];
+  el.style.display = q ? "block" : "none";
+  if (!q) return;
+  const exhausted = q.used >= q.limit;
+  let outlook = "";
+  if (exhausted) {
+    outlook = " · usage-based pricing applies";
+  } else if (q.exhaustedAt) {
+    const at = new Date(q.exhaustedAt);
+    const end = new Date(data.billingPeriodStart);
+    end.setMonth(end.getMonth() + 1);
+    outlook = at < end
+      ? ` · runs out ${at.toLocaleDateString(undefined, { month: "short", day: "numeric" })}`
+      : " · lasts the billing period";
+  }
+  el.className = exhausted ? "quota quota-exhausted" : "quota";
+  el.textContent = `${q.used} / ${q.limit} ${q.label} requests used${outlook}`;
+}
+
+// Headroom under the spending limit set in the Cursor dashboard
+function renderSpendLimit(el, data) {
+  const limit = data.spendLimitDollars;
+  el.style.display = limit ? "block" : "none";
+  if (!limit) return;
+  const left = Math.max(0, limit - (data.usageBasedSpendDollars || 0));
+  el.className = left < limit * 0.1 ? "quota quota-exhausted" : "quota";
+  el.textContent = left > 0
+    ? `${formatDollars(left)} left of ${formatDollars(limit)} spending limit`
+    : `Spending limit of ${formatDollars(limit)} reached`;
+}
+
+const KIND_LABELS = {
+  chat: "Chat",
+  agent: "Agent",
+  tab: "Tab",
+  "background-agent": "Background Agent",
+  "usage-based": "Usage-based",
+  "included-in-pro": "Included in Pro",
+  unknown: "Unknown",
+};
+
+// Other kinds arrive in lower-kebab-case, e.g. "free-credit"
+function kindLabel(kind) {
+  if (KIND_LABELS[kind]) return KIND_LABELS[kind];
+  const words = kind.replace(/-/g, " ");
+  return words.charAt(0).toUpperCase() + words.slice(1);
+}
+
+// Cursor mostly reports billing kinds rather than what sort of request it
+// was, so every kind present is listed
+function renderKinds(section, container, kinds) {
+  const any = (kinds || []).length > 0;
+  section.style.display = any ? "block" : "none";
+  container.innerHTML = "";
+  if (!any) return;
+  kinds.forEach((k) => {
+    const top = k.models[0];
+    const title = top ? ` title="Top model: ${escapeHtml(top.displayName || top.modelName)}"` : "";
+    const row = document.createElement("div");
+    row.className = "model-row";
+    row.innerHTML = `
+      <span class="model-name"${title}>${escapeHtml(kindLabel(k.kind))}</span>
+      <span class="model-reqs">${k.requests} req</span>
+      <span class="model-dash">&mdash;</span>
+      <span class="model-cost ${spendColorClass(k.spendDollars)}">${formatDollars(k.spendDollars)}</span>
+    `;
+    container.appendChild(row);
+  });
+}
+
+const ANOMALY_DAYS = 7;
+
+// Newest first, as returned by get_anomalies
+function renderAnomalies(section, container, anomalies) {
+  const cutoff = Date.now() - ANOMALY_DAYS * 24 * 60 * 60 * 1000;
+  const recent = anomalies.filter((a) => Date.parse(a.at) >= cutoff).slice(0, 3);
+  section.style.display = recent.length ? "block" : "none";
+  container.innerHTML = "";
+  recent.forEach((a) => {
+    const when = new Date(a.at).toLocaleString([], { weekday: "short", hour: "2-digit", minute: "2-digit" });
+    const what = a.kind === "burst" ? `${a.requests} req` : "1 request";
+    const row = document.createElement("div");
+    row.className = "model-row";
+    row.innerHTML = `
+      <span class="model-name">${escapeHtml(a.model)}</span>
+      <span class="model-reqs">${when} &middot; ${what}</span>
+      <span class="model-dash">&mdash;</span>
+      <span class="model-cost spend-red">${formatDollars(a.spendDollars)}</span>
+    `;
+    container.appendChild(row);
+  });
+}
+
+// Why a notification was recorded but not shown
+const HELD_BACK = {
+  quietHours: "quiet hours",
+  rateLimited: "rate limited",
+};
+
+async function renderNotifications(container) {
+  const history = await invoke("get_notification_history");
+  container.innerHTML = "";
+  if (!history.length) {
+    container.innerHTML = `<div class="loading">No notifications yet</div>`;
+    return;
+  }
+  history.slice(0, 10).forEach((n) => {
+    const when = new Date(n.at).toLocaleString([], { weekday: "short", hour: "2-digit", minute: "2-digit" });
+    const held = HELD_BACK[n.outcome] ? ` (${HELD_BACK[n.outcome]})` : "";
+    const row = document.createElement("div");
+    row.className = "notification-row";
+    row.innerHTML = `
+      <div class="model-row"><span class="model-name">${escapeHtml(n.title)}</span><span class="model-reqs">${when}${held}</span></div>
+      <div class="notification-body">${escapeHtml(n.body)}</div>
     `;
     container.appendChild(row);
   });
@@ -55,9 +239,11 @@ async function loadData() {
   const loadingEl = document.getElementById("loading");
 
   try {
-    const [data, error] = await Promise.all([
+    const [data, error, status, anomalies] = await Promise.all([
       invoke("get_usage_data"),
       invoke("get_error"),
+      invoke("get_status"),
+      invoke("get_anomalies"),
     ]);
 
     loadingEl.style.display = "none";
@@ -69,14 +255,35 @@ async function loadData() {
       errorEl.style.display = "none";
     }
 
+    renderStale(document.getElementById("stale"), status);
+
+    const warningEl = document.getElementById("warning");
+    warningEl.textContent = status.warning || "";
+    warningEl.style.display = status.warning ? "block" : "none";
+
+    renderSettingsWarnings(document.getElementById("settings-warnings"), status.settingsWarnings || []);
+
     if (data) {
       contentEl.style.display = "block";
       renderPeriods(document.getElementById("periods"), [
         data.today,
         data.last7Days,
         data.last30Days,
+        ...(data.customPeriods || []),
       ]);
+      renderQuota(document.getElementById("quota"), data);
+      renderSpendLimit(document.getElementById("spend-limit"), data);
       renderModels(document.getElementById("models"), data.lineItems);
+      renderKinds(
+        document.getElementById("kinds-section"),
+        document.getElementById("kinds"),
+        data.kinds,
+      );
+      renderAnomalies(
+        document.getElementById("anomalies-section"),
+        document.getElementById("anomalies"),
+        anomalies,
+      );
     }
   } catch (e) {
     loadingEl.style.display = "none";
@@ -97,6 +304,15 @@ document.getElementById("btn-dashboard").addEventListener("click", async () => {
   await invoke("open_dashboard");
 });
 
+document.getElementById("btn-notifications").addEventListener("click", async () => {
+  const section = document.getElementById("notifications-section");
+  const show = section.style.display === "none";
+  if (show) {
+    await renderNotifications(document.getElementById("notifications"));
+  }
+  section.style.display = show ? "block" : "none";
+});
+
 document.getElementById("btn-quit").addEventListener("click", () => {
   window.__TAURI__.process.exit(0);
 });